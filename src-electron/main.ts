@@ -1,10 +1,11 @@
 import { app, BrowserWindow, ipcMain, dialog } from "electron";
+import { statSync } from "node:fs";
 import { join } from "node:path";
 import * as adb from "./adb";
 import { extractPackageName } from "./apk-parser";
 import * as opLog from "./op-log";
 import * as logcat from "./logcat";
-import type { OpLogEntry } from "../src/types";
+import type { OpLogEntry, TransferResult } from "../src/types";
 import pkg from "electron-updater";
 const { autoUpdater } = pkg;
 
@@ -140,6 +141,7 @@ function registerIpcHandlers(): void {
   ipcMain.handle(
     "push-file",
     async (_e, serial: string, localPath: string, remotePath: string) => {
+      const started = Date.now();
       const result = adb.pushFile(serial, localPath, remotePath);
       const fileName =
         localPath.split("/").pop() ||
@@ -161,13 +163,20 @@ function registerIpcHandlers(): void {
       };
       opLog.addEntry(entry);
       if (!success) throw new Error(raw);
-      return raw;
+      const transferred: TransferResult = {
+        transfer_id: `${serial}-${started}`,
+        bytes: statSync(localPath).size,
+        duration_ms: Date.now() - started,
+        stat: null,
+      };
+      return transferred;
     },
   );
 
   ipcMain.handle(
     "pull-file",
     async (_e, serial: string, remotePath: string, localPath: string) => {
+      const started = Date.now();
       const result = adb.pullFile(serial, remotePath, localPath);
       const fileName =
         remotePath.split("/").pop() || remotePath;
@@ -187,7 +196,13 @@ function registerIpcHandlers(): void {
       };
       opLog.addEntry(entry);
       if (!success) throw new Error(raw);
-      return raw;
+      const transferred: TransferResult = {
+        transfer_id: `${serial}-${started}`,
+        bytes: statSync(localPath).size,
+        duration_ms: Date.now() - started,
+        stat: null,
+      };
+      return transferred;
     },
   );
 
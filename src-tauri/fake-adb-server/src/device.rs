@@ -0,0 +1,597 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+// ---------------------------------------------------------------------------
+// Virtual device model
+// ---------------------------------------------------------------------------
+
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFLNK: u32 = 0o120000;
+
+/// Fixed mtime for seeded files so listings are deterministic.
+const SEED_MTIME: u32 = 1_700_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FakePackage {
+    pub name: String,
+    #[serde(default)]
+    pub version_name: String,
+    #[serde(default)]
+    pub version_code: String,
+    /// APK path as printed by `pm list packages -f`.
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub system: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FakeFile {
+    pub data: Vec<u8>,
+    pub mode: u32,
+    pub mtime: u32,
+}
+
+/// One virtual device served by the fake server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FakeDevice {
+    pub serial: String,
+    /// `device`, `unauthorized`, `offline`, ...
+    #[serde(default = "default_state")]
+    pub state: String,
+    #[serde(default)]
+    pub props: BTreeMap<String, String>,
+    #[serde(default)]
+    pub packages: Vec<FakePackage>,
+    /// Regular files keyed by absolute path; parent directories are implied.
+    #[serde(default)]
+    pub files: BTreeMap<String, FakeFile>,
+    /// Extra (empty) directories.
+    #[serde(default)]
+    pub dirs: BTreeSet<String>,
+    /// Symbolic links keyed by path, each pointing to an absolute path.
+    #[serde(default)]
+    pub links: BTreeMap<String, String>,
+    /// Output of `df /data` as (1K-blocks, available).
+    #[serde(default = "default_storage")]
+    pub storage_kb: (u64, u64),
+    /// `level` reported by `dumpsys battery`.
+    #[serde(default = "default_battery_level")]
+    pub battery_level: u8,
+    /// Bytes returned by `screencap -p`.
+    #[serde(default)]
+    pub screencap: Vec<u8>,
+    /// Lines replayed by `logcat`, in threadtime format.
+    #[serde(default)]
+    pub logcat: Vec<String>,
+    /// What `pm install` prints for an existing file.
+    #[serde(default = "default_install_response")]
+    pub install_response: String,
+    /// Advertise `shell_v2`; off emulates a pre-7.0 device with only `shell:`.
+    #[serde(default = "default_true")]
+    pub shell_v2: bool,
+    /// `adb reverse` listeners as (local, remote). They live in adbd, so a
+    /// server restart keeps them.
+    #[serde(default)]
+    pub reverses: Vec<(String, String)>,
+}
+
+fn default_state() -> String {
+    "device".to_string()
+}
+
+fn default_storage() -> (u64, u64) {
+    (52_428_800, 20_971_520)
+}
+
+fn default_battery_level() -> u8 {
+    100
+}
+
+fn default_install_response() -> String {
+    "Success".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Result of an emulated shell command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShellResult {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Both streams in the order a PTY would show them (`shell:` service).
+    pub merged: Vec<u8>,
+    pub exit_code: u8,
+}
+
+impl ShellResult {
+    fn ok(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            stdout: stdout.into(),
+            ..Self::default()
+        }
+    }
+
+    fn err(stderr: impl Into<String>, exit_code: u8) -> Self {
+        Self {
+            stderr: stderr.into().into_bytes(),
+            exit_code,
+            ..Self::default()
+        }
+    }
+}
+
+impl FakeDevice {
+    /// A healthy Pixel 7 on Android 14 with a few apps and files.
+    pub fn new(serial: &str) -> Self {
+        let mut device = Self {
+            serial: serial.to_string(),
+            state: default_state(),
+            props: BTreeMap::new(),
+            packages: Vec::new(),
+            files: BTreeMap::new(),
+            dirs: BTreeSet::new(),
+            links: BTreeMap::new(),
+            storage_kb: default_storage(),
+            battery_level: default_battery_level(),
+            screencap: b"\x89PNG\r\n\x1a\nfake-screencap".to_vec(),
+            logcat: Vec::new(),
+            install_response: default_install_response(),
+            shell_v2: true,
+            reverses: Vec::new(),
+        };
+        device
+            .prop("ro.product.model", "Pixel 7")
+            .prop("ro.product.name", "panther")
+            .prop("ro.product.device", "panther")
+            .prop("ro.product.manufacturer", "Google")
+            .prop("ro.product.brand", "google")
+            .prop("ro.product.cpu.abilist", "arm64-v8a")
+            .prop("ro.build.type", "user")
+            .prop("ro.build.version.release", "14")
+            .prop("ro.build.version.sdk", "34")
+            .package("com.android.settings", "14", "34", true)
+            .package("com.example.notes", "1.2.3", "123", false)
+            .file(
+                "/sdcard/Download/readme.txt",
+                b"hello from the fake device\n",
+            )
+            .dir("/sdcard/DCIM")
+            .dir("/data/local/tmp");
+        device
+    }
+
+    pub fn with_state(mut self, state: &str) -> Self {
+        self.state = state.to_string();
+        self
+    }
+
+    pub fn with_shell_v2(mut self, enabled: bool) -> Self {
+        self.shell_v2 = enabled;
+        self
+    }
+
+    /// Feature list as returned by `host-serial:<serial>:features`.
+    pub fn features(&self) -> String {
+        let mut features = vec!["cmd", "stat_v2", "ls_v2"];
+        if self.shell_v2 {
+            features.insert(0, "shell_v2");
+        }
+        features.join(",")
+    }
+
+    pub fn prop(&mut self, key: &str, value: &str) -> &mut Self {
+        self.props.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn package(
+        &mut self,
+        name: &str,
+        version_name: &str,
+        version_code: &str,
+        system: bool,
+    ) -> &mut Self {
+        let path = if system {
+            format!("/system/priv-app/{}/{}.apk", name, name)
+        } else {
+            format!("/data/app/~~fake==/{}-fake==/base.apk", name)
+        };
+        self.packages.retain(|p| p.name != name);
+        self.packages.push(FakePackage {
+            name: name.to_string(),
+            version_name: version_name.to_string(),
+            version_code: version_code.to_string(),
+            path,
+            system,
+        });
+        self
+    }
+
+    pub fn file(&mut self, path: &str, data: &[u8]) -> &mut Self {
+        self.files.insert(
+            path.to_string(),
+            FakeFile {
+                data: data.to_vec(),
+                mode: S_IFREG | 0o660,
+                mtime: SEED_MTIME,
+            },
+        );
+        self
+    }
+
+    pub fn dir(&mut self, path: &str) -> &mut Self {
+        self.dirs.insert(path.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn link(&mut self, path: &str, target: &str) -> &mut Self {
+        self.links.insert(normalize(path), normalize(target));
+        self
+    }
+
+    pub fn logcat_line(&mut self, line: &str) -> &mut Self {
+        self.logcat.push(line.to_string());
+        self
+    }
+
+    // -- file system --------------------------------------------------------
+
+    /// `path` with every symlink in it replaced by its target.
+    pub fn resolve(&self, path: &str) -> String {
+        let mut path = normalize(path);
+        // Give up on loops like the kernel does, after 40 links
+        for _ in 0..40 {
+            let Some((link, target)) = self
+                .links
+                .iter()
+                .find(|(link, _)| path == **link || path.starts_with(&format!("{}/", link)))
+            else {
+                break;
+            };
+            path = format!("{}{}", target, &path[link.len()..]);
+        }
+        path
+    }
+
+    pub fn is_dir(&self, path: &str) -> bool {
+        let path = self.resolve(path);
+        if path == "/" || self.dirs.contains(&path) {
+            return true;
+        }
+        let prefix = format!("{}/", path);
+        self.files.keys().any(|f| f.starts_with(&prefix))
+            || self.dirs.iter().any(|d| d.starts_with(&prefix))
+    }
+
+    /// (mode, size, mtime) of what `path` points to, for `STA2`; all zero
+    /// when the path does not exist.
+    pub fn stat(&self, path: &str) -> (u32, u32, u32) {
+        let path = self.resolve(path);
+        if let Some(file) = self.files.get(&path) {
+            (file.mode, file.data.len() as u32, file.mtime)
+        } else if self.is_dir(&path) {
+            (S_IFDIR | 0o771, 4096, SEED_MTIME)
+        } else {
+            (0, 0, 0)
+        }
+    }
+
+    /// Like [`stat`](Self::stat) but a symlink is reported as itself, for
+    /// `STAT` and `LST2`. A trailing `/` still follows it, as on Linux.
+    pub fn lstat(&self, path: &str) -> (u32, u32, u32) {
+        if !path.ends_with('/') {
+            if let Some((parent, name)) = normalize(path).rsplit_once('/') {
+                let resolved = format!("{}/{}", self.resolve(parent).trim_end_matches('/'), name);
+                if let Some(target) = self.links.get(&resolved) {
+                    return (S_IFLNK | 0o777, target.len() as u32, SEED_MTIME);
+                }
+            }
+        }
+        self.stat(path)
+    }
+
+    /// Direct children of a directory as (name, mode, size, mtime).
+    pub fn list(&self, dir: &str) -> Vec<(String, u32, u32, u32)> {
+        let dir = self.resolve(dir);
+        let prefix = if dir == "/" {
+            "/".to_string()
+        } else {
+            format!("{}/", dir)
+        };
+        let mut names = BTreeSet::new();
+        for path in self.files.keys().chain(&self.dirs).chain(self.links.keys()) {
+            if let Some(rest) = path.strip_prefix(&prefix) {
+                if let Some(name) = rest.split('/').next().filter(|n| !n.is_empty()) {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        names
+            .into_iter()
+            .map(|name| {
+                let (mode, size, mtime) = self.lstat(&format!("{}{}", prefix, name));
+                (name, mode, size, mtime)
+            })
+            .collect()
+    }
+
+    pub fn write_file(&mut self, path: &str, data: Vec<u8>, mode: u32, mtime: u32) {
+        let mode = if mode & S_IFREG == 0 {
+            S_IFREG | 0o660
+        } else {
+            mode
+        };
+        self.files
+            .insert(self.resolve(path), FakeFile { data, mode, mtime });
+    }
+
+    // -- shell --------------------------------------------------------------
+
+    /// Emulate a `shell:` command line: `;`-separated commands with `$?`
+    /// expanded to the previous exit status. Unknown commands fail like `sh`
+    /// does.
+    pub fn shell(&mut self, command: &str) -> ShellResult {
+        let mut result = ShellResult::default();
+        for step in command.split(';') {
+            let step = step.replace("$?", &result.exit_code.to_string());
+            let next = self.run_one(&step);
+            result.stdout.extend_from_slice(&next.stdout);
+            result.stderr.extend_from_slice(&next.stderr);
+            result.merged.extend_from_slice(&next.stdout);
+            result.merged.extend_from_slice(&next.stderr);
+            result.exit_code = next.exit_code;
+        }
+        result
+    }
+
+    fn run_one(&mut self, command: &str) -> ShellResult {
+        if let Some(command) = command.trim_end().strip_suffix("2>/dev/null") {
+            let mut result = self.run_one(command);
+            result.stderr.clear();
+            return result;
+        }
+        if let Some(text) = command.trim_start().strip_prefix("echo ") {
+            let text = text.trim();
+            let text = text
+                .strip_prefix('"')
+                .and_then(|t| t.strip_suffix('"'))
+                .unwrap_or(text);
+            return ShellResult::ok(format!("{}\n", text));
+        }
+        // Quoted words lose their quotes; quoted spaces are not supported
+        let args: Vec<&str> = command
+            .split_whitespace()
+            .map(|a| a.trim_matches('\''))
+            .collect();
+        match args.as_slice() {
+            [] => ShellResult::ok(""),
+            ["getprop"] => {
+                let mut out = String::new();
+                for (k, v) in &self.props {
+                    out.push_str(&format!("[{}]: [{}]\n", k, v));
+                }
+                ShellResult::ok(out)
+            }
+            ["getprop", key] => ShellResult::ok(format!(
+                "{}\n",
+                self.props.get(*key).map(String::as_str).unwrap_or("")
+            )),
+            ["df", ..] => {
+                let (total, free) = self.storage_kb;
+                ShellResult::ok(format!(
+                    "Filesystem     1K-blocks    Used Available Use% Mounted on\n/dev/block/dm-5 {} {} {} {}% /data\n",
+                    total,
+                    total - free,
+                    free,
+                    (total - free) * 100 / total.max(1)
+                ))
+            }
+            ["wm", "size"] => ShellResult::ok("Physical size: 1080x2400\n"),
+            ["wm", "density"] => ShellResult::ok(format!(
+                "Physical density: {}\n",
+                self.props
+                    .get("ro.sf.lcd_density")
+                    .map(String::as_str)
+                    .unwrap_or("420")
+            )),
+            ["dumpsys", "battery"] => ShellResult::ok(format!(
+                "Current Battery Service state:\n  AC powered: false\n  USB powered: true\n  status: 2\n  level: {}\n  scale: 100\n",
+                self.battery_level
+            )),
+            ["cat", "/proc/meminfo"] => ShellResult::ok(
+                "MemTotal:        8045096 kB\nMemFree:          412356 kB\nMemAvailable:    3932160 kB\n",
+            ),
+            ["cat", "/proc/uptime"] => ShellResult::ok("3600.25 14001.80\n"),
+            ["pm", "list", "packages", flags @ ..] => self.list_packages(flags),
+            ["pm", "install", rest @ ..] => match rest.last() {
+                Some(path) if self.files.contains_key(&normalize(path)) => {
+                    ShellResult::ok(format!("{}\n", self.install_response))
+                }
+                _ => ShellResult::ok("Failure [INSTALL_FAILED_INVALID_URI]\n"),
+            },
+            ["pm", "uninstall", .., name] => {
+                let before = self.packages.len();
+                self.packages.retain(|p| &p.name != name);
+                if self.packages.len() < before {
+                    ShellResult::ok("Success\n")
+                } else {
+                    ShellResult::ok("Failure [DELETE_FAILED_INTERNAL_ERROR]\n")
+                }
+            }
+            ["pm", "clear", _] => ShellResult::ok("Success\n"),
+            ["am", "force-stop", _] => ShellResult::ok(""),
+            ["monkey", "-p", name, ..] => {
+                if self.packages.iter().any(|p| &p.name == name) {
+                    ShellResult::ok("Events injected: 1\n")
+                } else {
+                    ShellResult::err("** No activities found to run, monkey aborted.\n", 252)
+                }
+            }
+            ["dumpsys", "package", name] => self.dumpsys_package(name),
+            ["ls", rest @ ..] => self.ls(rest),
+            ["rm", rest @ ..] => self.rm(rest),
+            [name, ..] => ShellResult::err(
+                format!("/system/bin/sh: {}: inaccessible or not found\n", name),
+                127,
+            ),
+        }
+    }
+
+    fn rm(&mut self, args: &[&str]) -> ShellResult {
+        let force = args.iter().any(|a| a.starts_with('-') && a.contains('f'));
+        let mut result = ShellResult::default();
+        for path in args.iter().filter(|a| !a.starts_with('-')) {
+            let error = if path.starts_with("/system/") {
+                Some("Read-only file system")
+            } else if self.files.remove(&normalize(path)).is_none() && !force {
+                Some("No such file or directory")
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                let line = format!("rm: {}: {}\n", path, error);
+                result.stderr.extend_from_slice(line.as_bytes());
+                result.exit_code = 1;
+            }
+        }
+        result
+    }
+
+    fn list_packages(&self, flags: &[&str]) -> ShellResult {
+        let third_party = flags.contains(&"-3");
+        let system_only = flags.contains(&"-s");
+        let with_path = flags.contains(&"-f");
+        let mut out = String::new();
+        for p in &self.packages {
+            if (third_party && p.system) || (system_only && !p.system) {
+                continue;
+            }
+            if with_path {
+                out.push_str(&format!("package:{}={}\n", p.path, p.name));
+            } else {
+                out.push_str(&format!("package:{}\n", p.name));
+            }
+        }
+        ShellResult::ok(out)
+    }
+
+    fn dumpsys_package(&self, name: &str) -> ShellResult {
+        let Some(p) = self.packages.iter().find(|p| p.name == name) else {
+            return ShellResult::ok(format!("Unable to find package: {}\n", name));
+        };
+        ShellResult::ok(format!(
+            "Packages:\n  Package [{}] (fake):\n    codePath={}\n    versionCode={} minSdk=24 targetSdk=34\n    versionName={}\n",
+            p.name, p.path, p.version_code, p.version_name
+        ))
+    }
+
+    fn ls(&self, args: &[&str]) -> ShellResult {
+        let long = args.iter().any(|a| a.starts_with('-') && a.contains('l'));
+        let Some(path) = args.iter().find(|a| !a.starts_with('-')) else {
+            return ShellResult::err("ls: missing operand\n", 1);
+        };
+        let (mode, size, _) = self.stat(path);
+        if mode == 0 {
+            return ShellResult::err(format!("ls: {}: No such file or directory\n", path), 1);
+        }
+        let entries = if mode & S_IFDIR != 0 {
+            self.list(path)
+        } else {
+            let name = path.rsplit('/').next().unwrap_or(path).to_string();
+            vec![(name, mode, size, 0)]
+        };
+
+        let mut out = String::new();
+        if long {
+            out.push_str(&format!("total {}\n", entries.len() * 8));
+        }
+        for (name, mode, size, _) in entries {
+            if long {
+                let kind = if mode & S_IFDIR != 0 { 'd' } else { '-' };
+                out.push_str(&format!(
+                    "{}rw-rw---- 1 root sdcard_rw {:>8} 2023-11-14 22:13 {}\n",
+                    kind, size, name
+                ));
+            } else {
+                out.push_str(&format!("{}\n", name));
+            }
+        }
+        ShellResult::ok(out)
+    }
+}
+
+/// Strip trailing slashes (except for the root).
+fn normalize(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ls_and_rm() {
+        let mut device = FakeDevice::new("SER");
+        let out = device.shell("ls -la /sdcard");
+        assert!(String::from_utf8_lossy(&out.stdout).contains("Download"));
+
+        let missing = device.shell("ls /nope");
+        assert_eq!(missing.exit_code, 1);
+
+        device.shell("rm -f /sdcard/Download/readme.txt");
+        assert_eq!(device.stat("/sdcard/Download/readme.txt").0, 0);
+        assert_eq!(device.shell("rm /sdcard/Download/readme.txt").exit_code, 1);
+        assert_eq!(
+            device.shell("rm -f /sdcard/Download/readme.txt").exit_code,
+            0
+        );
+    }
+
+    #[test]
+    fn test_symlinks() {
+        let mut device = FakeDevice::new("SER");
+        device.link("/mnt/sdcard", "/sdcard");
+        assert_eq!(device.lstat("/mnt/sdcard").0 & S_IFLNK, S_IFLNK);
+        assert_eq!(device.lstat("/mnt/sdcard/").0 & S_IFDIR, S_IFDIR);
+        assert_eq!(device.stat("/mnt/sdcard").0 & S_IFDIR, S_IFDIR);
+
+        device.write_file("/mnt/sdcard/new.txt", b"x".to_vec(), 0, 0);
+        assert!(device.files.contains_key("/sdcard/new.txt"));
+        assert!(device
+            .list("/mnt")
+            .iter()
+            .any(|(name, ..)| name == "sdcard"));
+    }
+
+    #[test]
+    fn test_sequence_and_status() {
+        let mut device = FakeDevice::new("SER");
+        let out = device.shell("ls /nope; echo \"status $?\"");
+        assert_eq!(out.exit_code, 0);
+        assert_eq!(out.stdout, b"status 1\n");
+        assert!(String::from_utf8_lossy(&out.stderr).contains("No such file"));
+    }
+
+    #[test]
+    fn test_pm_list_filters() {
+        let mut device = FakeDevice::new("SER");
+        let third = device.shell("pm list packages -3 -f");
+        let text = String::from_utf8_lossy(&third.stdout).to_string();
+        assert!(text.contains("=com.example.notes"));
+        assert!(!text.contains("com.android.settings"));
+    }
+}
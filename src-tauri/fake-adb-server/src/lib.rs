@@ -0,0 +1,15 @@
+//! Test-only emulation of the adb server.
+//!
+//! Speaks the host protocol on a local TCP port and serves configurable
+//! virtual devices: canned `getprop`, `pm`, `dumpsys`, `ls`, `df` and
+//! `screencap` output, forward and reverse listeners, an in-memory file
+//! system behind the SYNC service and a scripted logcat stream. Integration
+//! tests point the app's adb client at [`FakeAdbServer::port`] instead of a
+//! real server.
+
+mod device;
+mod server;
+mod sync;
+
+pub use device::{FakeDevice, FakeFile, FakePackage, ShellResult};
+pub use server::FakeAdbServer;
@@ -0,0 +1,49 @@
+use fake_adb_server::{FakeAdbServer, FakeDevice};
+
+/// Run the fake server standalone, e.g. to point a real `adb` client or the
+/// app at it by hand:
+///
+/// ```text
+/// fake-adb-server [--port 5037] [devices.json]
+/// ```
+///
+/// `devices.json` is an array of `FakeDevice`; without it one default device
+/// (`FAKE0001`) is served.
+#[tokio::main]
+async fn main() {
+    let mut port: u16 = 5037;
+    let mut config: Option<String> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                port = args
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or_else(|| exit("--port needs a number"));
+            }
+            _ => config = Some(arg),
+        }
+    }
+
+    let devices = match config {
+        Some(path) => {
+            let data = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| exit(&format!("{}: {}", path, e)));
+            serde_json::from_str::<Vec<FakeDevice>>(&data)
+                .unwrap_or_else(|e| exit(&format!("{}: {}", path, e)))
+        }
+        None => vec![FakeDevice::new("FAKE0001")],
+    };
+
+    let server = FakeAdbServer::bind(&format!("127.0.0.1:{}", port), devices)
+        .await
+        .unwrap_or_else(|e| exit(&format!("bind 127.0.0.1:{}: {}", port, e)));
+    println!("fake adb server listening on {}", server.addr());
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("fake-adb-server: {}", message);
+    std::process::exit(2);
+}
@@ -0,0 +1,496 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::device::FakeDevice;
+use crate::sync;
+
+// ---------------------------------------------------------------------------
+// Server
+// ---------------------------------------------------------------------------
+
+/// Shared device table. Tests mutate it through [`FakeAdbServer::update`];
+/// `host:track-devices` clients are notified of every change.
+struct Shared {
+    devices: Mutex<Vec<FakeDevice>>,
+    changed: watch::Sender<u64>,
+    /// `adb forward` listeners as (local, remote) per serial. They belong to
+    /// the server, so a restart drops them.
+    forwards: Mutex<BTreeMap<String, Vec<(String, String)>>>,
+}
+
+/// A running fake adb server on 127.0.0.1. Stops when dropped.
+pub struct FakeAdbServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl FakeAdbServer {
+    /// Bind an ephemeral port and start serving `devices`.
+    pub async fn start(devices: Vec<FakeDevice>) -> std::io::Result<Self> {
+        Self::bind("127.0.0.1:0", devices).await
+    }
+
+    /// Bind a specific address (e.g. `127.0.0.1:5037`) and start serving.
+    pub async fn bind(addr: &str, devices: Vec<FakeDevice>) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let (changed, _) = watch::channel(0);
+        let shared = Arc::new(Shared {
+            devices: Mutex::new(devices),
+            changed,
+            forwards: Mutex::new(BTreeMap::new()),
+        });
+
+        let task = serve(listener, shared.clone());
+        Ok(Self { addr, shared, task })
+    }
+
+    /// Simulate an adb server restart: drop every open connection (tracking
+    /// clients see EOF) and all forwards, then listen again on the same port
+    /// with the same devices.
+    pub async fn restart(&mut self) -> std::io::Result<()> {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+        self.shared.forwards.lock().expect("forwards").clear();
+        let listener = TcpListener::bind(self.addr).await?;
+        self.task = serve(listener, self.shared.clone());
+        Ok(())
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Mutate the device table (attach, detach, change state, edit files...).
+    pub fn update<R>(&self, f: impl FnOnce(&mut Vec<FakeDevice>) -> R) -> R {
+        let result = {
+            let mut devices = self.shared.devices.lock().expect("device table");
+            f(&mut devices)
+        };
+        self.shared.changed.send_modify(|v| *v += 1);
+        result
+    }
+
+    /// Active forwards as (serial, local, remote).
+    pub fn forwards(&self) -> Vec<(String, String, String)> {
+        let forwards = self.shared.forwards.lock().expect("forwards");
+        forwards
+            .iter()
+            .flat_map(|(serial, listeners)| {
+                listeners
+                    .iter()
+                    .map(|(local, remote)| (serial.clone(), local.clone(), remote.clone()))
+            })
+            .collect()
+    }
+
+    /// Snapshot of one device, e.g. to assert on files after a push.
+    pub fn device(&self, serial: &str) -> Option<FakeDevice> {
+        let devices = self.shared.devices.lock().ok()?;
+        devices.iter().find(|d| d.serial == serial).cloned()
+    }
+}
+
+impl Drop for FakeAdbServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Host services
+// ---------------------------------------------------------------------------
+
+/// Accept loop. Connections run in a `JoinSet` owned by the loop, so aborting
+/// the returned task closes them too.
+fn serve(listener: TcpListener, shared: Arc<Shared>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut connections = JoinSet::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            while connections.try_join_next().is_some() {}
+            let shared = shared.clone();
+            connections.spawn(async move {
+                let _ = handle_connection(stream, shared).await;
+            });
+        }
+    })
+}
+
+async fn handle_connection(mut stream: TcpStream, shared: Arc<Shared>) -> std::io::Result<()> {
+    let request = read_request(&mut stream).await?;
+
+    match request.as_str() {
+        "host:version" => write_okay_payload(&mut stream, "0029").await,
+        "host:devices" => write_okay_payload(&mut stream, &listing(&shared, false)).await,
+        "host:devices-l" => write_okay_payload(&mut stream, &listing(&shared, true)).await,
+        "host:track-devices" | "host:track-devices-l" => {
+            track_devices(stream, shared, request.ends_with("-l")).await
+        }
+        "host:kill" => stream.write_all(b"OKAY").await,
+        "host:list-forward" => {
+            let listing: String = {
+                let forwards = shared.forwards.lock().expect("forwards");
+                forwards
+                    .iter()
+                    .flat_map(|(serial, listeners)| {
+                        listeners.iter().map(move |(local, remote)| {
+                            format!("{} {} {}\n", serial, local, remote)
+                        })
+                    })
+                    .collect()
+            };
+            write_okay_payload(&mut stream, &listing).await
+        }
+        "host:transport-any" => {
+            let serial = {
+                let devices = shared.devices.lock().expect("device table");
+                devices.first().map(|d| d.serial.clone())
+            };
+            match serial {
+                Some(serial) => local_service(stream, shared, &serial).await,
+                None => write_fail(&mut stream, "no devices/emulators found").await,
+            }
+        }
+        _ => {
+            if let Some(serial) = request.strip_prefix("host:transport:") {
+                local_service(stream, shared, serial).await
+            } else if let Some(serial) = request
+                .strip_prefix("host-serial:")
+                .and_then(|r| r.strip_suffix(":features"))
+            {
+                let features = {
+                    let devices = shared.devices.lock().expect("device table");
+                    devices
+                        .iter()
+                        .find(|d| d.serial == serial)
+                        .map(|d| d.features())
+                };
+                match features {
+                    Some(features) => write_okay_payload(&mut stream, &features).await,
+                    None => {
+                        let msg = format!("device '{}' not found", serial);
+                        write_fail(&mut stream, &msg).await
+                    }
+                }
+            } else if let Some((serial, service)) = request
+                .strip_prefix("host-serial:")
+                .and_then(|r| r.split_once(':'))
+                .filter(|(_, service)| {
+                    service.starts_with("forward:") || service.starts_with("killforward:")
+                })
+            {
+                let known = {
+                    let devices = shared.devices.lock().expect("device table");
+                    devices.iter().any(|d| d.serial == serial)
+                };
+                if !known {
+                    return write_fail(&mut stream, &format!("device '{}' not found", serial))
+                        .await;
+                }
+                stream.write_all(b"OKAY").await?;
+                let reply = {
+                    let mut forwards = shared.forwards.lock().expect("forwards");
+                    edit_listeners(forwards.entry(serial.to_string()).or_default(), service)
+                };
+                write_forward_reply(&mut stream, reply).await
+            } else if let Some(addr) = request.strip_prefix("host:connect:") {
+                let msg = format!("failed to connect to '{}': Connection refused", addr);
+                write_okay_payload(&mut stream, &msg).await
+            } else {
+                write_fail(&mut stream, &format!("unknown host service '{}'", request)).await
+            }
+        }
+    }
+}
+
+fn listing(shared: &Shared, long: bool) -> String {
+    let devices = shared.devices.lock().expect("device table");
+    let mut out = String::new();
+    for d in devices.iter() {
+        if long {
+            let get = |k: &str| {
+                d.props
+                    .get(k)
+                    .cloned()
+                    .unwrap_or_default()
+                    .replace(' ', "_")
+            };
+            out.push_str(&format!(
+                "{:<22} {} product:{} model:{} device:{} transport_id:1\n",
+                d.serial,
+                d.state,
+                get("ro.product.name"),
+                get("ro.product.model"),
+                get("ro.product.device")
+            ));
+        } else {
+            out.push_str(&format!("{}\t{}\n", d.serial, d.state));
+        }
+    }
+    out
+}
+
+async fn track_devices(
+    mut stream: TcpStream,
+    shared: Arc<Shared>,
+    long: bool,
+) -> std::io::Result<()> {
+    let mut changed = shared.changed.subscribe();
+    stream.write_all(b"OKAY").await?;
+    loop {
+        let payload = listing(&shared, long);
+        stream
+            .write_all(format!("{:04x}{}", payload.len(), payload).as_bytes())
+            .await?;
+        if changed.changed().await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Device (local) services
+// ---------------------------------------------------------------------------
+
+async fn local_service(
+    mut stream: TcpStream,
+    shared: Arc<Shared>,
+    serial: &str,
+) -> std::io::Result<()> {
+    let state = {
+        let devices = shared.devices.lock().expect("device table");
+        devices
+            .iter()
+            .find(|d| d.serial == serial)
+            .map(|d| d.state.clone())
+    };
+    match state.as_deref() {
+        None => return write_fail(&mut stream, &format!("device '{}' not found", serial)).await,
+        Some("device") => stream.write_all(b"OKAY").await?,
+        Some("unauthorized") => {
+            let msg = "device unauthorized.\nThis adb server's $ADB_VENDOR_KEYS is not set";
+            return write_fail(&mut stream, msg).await;
+        }
+        Some(other) => return write_fail(&mut stream, &format!("device {}", other)).await,
+    }
+
+    let request = read_request(&mut stream).await?;
+    if let Some(service) = request.strip_prefix("reverse:") {
+        stream.write_all(b"OKAY").await?;
+        if service == "list-forward" {
+            let listing: String = with_device(&shared, serial, |d| {
+                d.reverses
+                    .iter()
+                    .map(|(local, remote)| format!("UsbFfs {} {}\n", local, remote))
+                    .collect()
+            });
+            return write_payload(&mut stream, &listing).await;
+        }
+        let reply = with_device(&shared, serial, |d| {
+            edit_listeners(&mut d.reverses, service)
+        });
+        return write_forward_reply(&mut stream, reply).await;
+    }
+    if request == "sync:" {
+        stream.write_all(b"OKAY").await?;
+        return sync::serve(stream, shared_device(&shared, serial)).await;
+    }
+
+    if let Some(rest) = request.strip_prefix("shell,v2,") {
+        if !with_device(&shared, serial, |d| d.shell_v2) {
+            return write_fail(&mut stream, "closed").await;
+        }
+        // `shell,v2,raw:<cmd>` or `shell,v2,TERM=xterm,raw:<cmd>`
+        let command = rest
+            .split_once(':')
+            .map(|(_, c)| c)
+            .unwrap_or("")
+            .to_string();
+        stream.write_all(b"OKAY").await?;
+        let output = with_device(&shared, serial, |d| d.shell(&command));
+        write_packet(&mut stream, SHELL_STDOUT, &output.stdout).await?;
+        write_packet(&mut stream, SHELL_STDERR, &output.stderr).await?;
+        write_packet(&mut stream, SHELL_EXIT, &[output.exit_code]).await?;
+        return stream.shutdown().await;
+    }
+
+    let command = request
+        .strip_prefix("shell:")
+        .or_else(|| request.strip_prefix("exec:"))
+        .map(str::to_string);
+    let Some(command) = command else {
+        return write_fail(&mut stream, &format!("unknown service '{}'", request)).await;
+    };
+    stream.write_all(b"OKAY").await?;
+
+    if command.split_whitespace().next() == Some("logcat") {
+        return stream_logcat(stream, &shared, serial, &command).await;
+    }
+
+    let output = with_device(&shared, serial, |d| {
+        if command.trim() == "screencap -p" {
+            d.screencap.clone()
+        } else {
+            // `shell:` merges stderr into stdout, just like a PTY does
+            d.shell(&command).merged
+        }
+    });
+    stream.write_all(&output).await?;
+    stream.shutdown().await
+}
+
+/// Replay the scripted logcat lines, then keep the stream open (unless `-d`)
+/// until the client hangs up.
+async fn stream_logcat(
+    mut stream: TcpStream,
+    shared: &Shared,
+    serial: &str,
+    command: &str,
+) -> std::io::Result<()> {
+    let lines = with_device(shared, serial, |d| d.logcat.clone());
+    for line in lines {
+        stream.write_all(format!("{}\n", line).as_bytes()).await?;
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    if command.split_whitespace().any(|a| a == "-d") {
+        return stream.shutdown().await;
+    }
+    let mut buf = [0u8; 64];
+    while stream.read(&mut buf).await? > 0 {}
+    Ok(())
+}
+
+/// Apply `forward:<local>;<remote>`, `killforward:<local>` or
+/// `killforward-all` to a listener list. Returns the port allocated for
+/// `tcp:0`, or the failure message.
+fn edit_listeners(
+    listeners: &mut Vec<(String, String)>,
+    service: &str,
+) -> Result<Option<String>, String> {
+    if service == "killforward-all" {
+        listeners.clear();
+        return Ok(None);
+    }
+    if let Some(local) = service.strip_prefix("killforward:") {
+        let before = listeners.len();
+        listeners.retain(|(l, _)| l != local);
+        if listeners.len() == before {
+            return Err(format!("listener '{}' not found", local));
+        }
+        return Ok(None);
+    }
+    let Some((local, remote)) = service
+        .strip_prefix("forward:")
+        .and_then(|spec| spec.split_once(';'))
+    else {
+        return Err(format!("unknown forward service '{}'", service));
+    };
+    let (local, port) = if local == "tcp:0" {
+        let port = 40_000 + listeners.len();
+        (format!("tcp:{}", port), Some(port.to_string()))
+    } else {
+        (local.to_string(), None)
+    };
+    listeners.retain(|(l, _)| *l != local);
+    listeners.push((local, remote.to_string()));
+    Ok(port)
+}
+
+/// Second status of a forward service, then the port of a `tcp:0` listener.
+async fn write_forward_reply(
+    stream: &mut TcpStream,
+    reply: Result<Option<String>, String>,
+) -> std::io::Result<()> {
+    match reply {
+        Ok(Some(port)) => write_okay_payload(stream, &port).await,
+        Ok(None) => stream.write_all(b"OKAY").await,
+        Err(message) => write_fail(stream, &message).await,
+    }
+}
+
+/// Run `f` against the named device (which must exist).
+fn with_device<R>(shared: &Shared, serial: &str, f: impl FnOnce(&mut FakeDevice) -> R) -> R {
+    let mut devices = shared.devices.lock().expect("device table");
+    let device = devices
+        .iter_mut()
+        .find(|d| d.serial == serial)
+        .expect("device checked by local_service");
+    f(device)
+}
+
+/// Handle used by the sync service to reach its device.
+pub(crate) struct DeviceRef {
+    shared: Arc<Shared>,
+    serial: String,
+}
+
+impl DeviceRef {
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut FakeDevice) -> R) -> R {
+        with_device(&self.shared, &self.serial, f)
+    }
+}
+
+fn shared_device(shared: &Arc<Shared>, serial: &str) -> DeviceRef {
+    DeviceRef {
+        shared: shared.clone(),
+        serial: serial.to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Wire helpers
+// ---------------------------------------------------------------------------
+
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let len = usize::from_str_radix(&String::from_utf8_lossy(&len_buf), 16)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let mut data = vec![0u8; len];
+    stream.read_exact(&mut data).await?;
+    Ok(String::from_utf8_lossy(&data).to_string())
+}
+
+async fn write_okay_payload(stream: &mut TcpStream, payload: &str) -> std::io::Result<()> {
+    stream.write_all(b"OKAY").await?;
+    write_payload(stream, payload).await
+}
+
+async fn write_payload(stream: &mut TcpStream, payload: &str) -> std::io::Result<()> {
+    stream
+        .write_all(format!("{:04x}{}", payload.len(), payload).as_bytes())
+        .await
+}
+
+const SHELL_STDOUT: u8 = 1;
+const SHELL_STDERR: u8 = 2;
+const SHELL_EXIT: u8 = 3;
+
+/// Shell v2 packet: id, little-endian u32 length, data.
+async fn write_packet(stream: &mut TcpStream, id: u8, data: &[u8]) -> std::io::Result<()> {
+    if data.is_empty() && id != SHELL_EXIT {
+        return Ok(());
+    }
+    let mut packet = vec![id];
+    packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    packet.extend_from_slice(data);
+    stream.write_all(&packet).await
+}
+
+async fn write_fail(stream: &mut TcpStream, message: &str) -> std::io::Result<()> {
+    stream
+        .write_all(format!("FAIL{:04x}{}", message.len(), message).as_bytes())
+        .await
+}
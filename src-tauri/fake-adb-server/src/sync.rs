@@ -0,0 +1,140 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::server::DeviceRef;
+
+// ---------------------------------------------------------------------------
+// SYNC service (STAT / LST2 / STA2 / LIST / SEND / RECV / QUIT) over the in-memory file system
+// ---------------------------------------------------------------------------
+
+const SYNC_DATA_MAX: usize = 64 * 1024;
+
+pub(crate) async fn serve(mut stream: TcpStream, device: DeviceRef) -> std::io::Result<()> {
+    loop {
+        let mut id = [0u8; 4];
+        if stream.read_exact(&mut id).await.is_err() {
+            return Ok(());
+        }
+        let len = read_u32(&mut stream).await? as usize;
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).await?;
+        let arg = String::from_utf8_lossy(&payload).to_string();
+
+        match &id {
+            b"STAT" => {
+                let (mode, size, mtime) = device.with(|d| d.lstat(&arg));
+                stream.write_all(b"STAT").await?;
+                write_u32s(&mut stream, &[mode, size, mtime]).await?;
+            }
+            b"LST2" | b"STA2" => {
+                let (mode, size, mtime) = device.with(|d| {
+                    if &id == b"STA2" {
+                        d.stat(&arg)
+                    } else {
+                        d.lstat(&arg)
+                    }
+                });
+                // error, dev, ino, mode, nlink, uid, gid, size, atime, mtime, ctime
+                let error: u32 = if mode == 0 { 2 } else { 0 }; // ENOENT
+                let mut reply = id.to_vec();
+                reply.extend(error.to_le_bytes());
+                reply.extend([0u8; 16]);
+                for value in [mode, 1, 0, 0] {
+                    reply.extend(value.to_le_bytes());
+                }
+                for value in [size as u64, mtime as u64, mtime as u64, mtime as u64] {
+                    reply.extend(value.to_le_bytes());
+                }
+                stream.write_all(&reply).await?;
+            }
+            b"LIST" => {
+                let mut entries = vec![
+                    (".".to_string(), device.with(|d| d.stat(&arg))),
+                    ("..".to_string(), (crate::device::S_IFDIR | 0o755, 4096, 0)),
+                ];
+                entries.extend(
+                    device
+                        .with(|d| d.list(&arg))
+                        .into_iter()
+                        .map(|(name, mode, size, mtime)| (name, (mode, size, mtime))),
+                );
+                for (name, (mode, size, mtime)) in entries {
+                    stream.write_all(b"DENT").await?;
+                    write_u32s(&mut stream, &[mode, size, mtime, name.len() as u32]).await?;
+                    stream.write_all(name.as_bytes()).await?;
+                }
+                stream.write_all(b"DONE").await?;
+                write_u32s(&mut stream, &[0, 0, 0, 0]).await?;
+            }
+            b"SEND" => receive_file(&mut stream, &device, &arg).await?,
+            b"RECV" => {
+                let data = device.with(|d| d.files.get(&d.resolve(&arg)).map(|f| f.data.clone()));
+                let Some(data) = data else {
+                    return write_fail(
+                        &mut stream,
+                        &format!("open failed: {}: No such file or directory", arg),
+                    )
+                    .await;
+                };
+                for chunk in data.chunks(SYNC_DATA_MAX) {
+                    stream.write_all(b"DATA").await?;
+                    write_u32s(&mut stream, &[chunk.len() as u32]).await?;
+                    stream.write_all(chunk).await?;
+                }
+                stream.write_all(b"DONE").await?;
+                write_u32s(&mut stream, &[0]).await?;
+            }
+            b"QUIT" => return Ok(()),
+            _ => return write_fail(&mut stream, "unknown sync command").await,
+        }
+    }
+}
+
+/// `SEND path,mode` followed by DATA chunks and `DONE <mtime>`.
+async fn receive_file(
+    stream: &mut TcpStream,
+    device: &DeviceRef,
+    header: &str,
+) -> std::io::Result<()> {
+    let (path, mode) = match header.rsplit_once(',') {
+        Some((path, mode)) => (path.to_string(), mode.parse().unwrap_or(0)),
+        None => (header.to_string(), 0),
+    };
+
+    let mut data = Vec::new();
+    loop {
+        let mut id = [0u8; 4];
+        stream.read_exact(&mut id).await?;
+        let value = read_u32(stream).await?;
+        match &id {
+            b"DATA" => {
+                let mut chunk = vec![0u8; value as usize];
+                stream.read_exact(&mut chunk).await?;
+                data.extend_from_slice(&chunk);
+            }
+            b"DONE" => {
+                device.with(|d| d.write_file(&path, data, mode, value));
+                stream.write_all(b"OKAY").await?;
+                return write_u32s(stream, &[0]).await;
+            }
+            _ => return write_fail(stream, "unexpected packet during SEND").await,
+        }
+    }
+}
+
+async fn read_u32(stream: &mut TcpStream) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf).await?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+async fn write_u32s(stream: &mut TcpStream, values: &[u32]) -> std::io::Result<()> {
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    stream.write_all(&bytes).await
+}
+
+async fn write_fail(stream: &mut TcpStream, message: &str) -> std::io::Result<()> {
+    stream.write_all(b"FAIL").await?;
+    write_u32s(stream, &[message.len() as u32]).await?;
+    stream.write_all(message.as_bytes()).await
+}
@@ -1,8 +1,18 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
 use serde::{Deserialize, Serialize};
 use tauri::AppHandle;
-use tauri_plugin_shell::ShellExt;
+use tauri::Emitter;
+use tokio_util::sync::CancellationToken;
 
+use crate::device_info;
+use crate::device_registry::DeviceLabel;
+use crate::error::{self, AdbError};
 use crate::error_codes;
+use crate::operation::{self, OpClass};
+use crate::sync::{RemoteEntry, RemoteStat, SyncConnection, TransferProgress, TransferResult};
+use crate::transport::AdbTransport;
 
 // ---------------------------------------------------------------------------
 // Structs
@@ -14,16 +24,42 @@ pub struct AdbDevice {
     pub state: String,
     pub model: String,
     pub product: String,
+    /// Alias, tags and notes from the device registry.
+    #[serde(default)]
+    pub label: Option<DeviceLabel>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceDetail {
     pub serial: String,
     pub model: String,
+    pub manufacturer: String,
+    pub brand: String,
     pub android_version: String,
     pub sdk_version: String,
+    pub fingerprint: String,
+    pub security_patch: String,
+    /// `user`, `userdebug` or `eng`
+    pub build_type: String,
+    /// A `su` binary is on the shell's PATH.
+    pub rooted: bool,
+    pub abis: Vec<String>,
+    /// `WIDTHxHEIGHT`, the override when one is set.
+    pub screen_resolution: String,
+    pub screen_density: Option<u32>,
+    pub battery_level: Option<u8>,
+    /// `charging`, `discharging`, `not_charging`, `full` or `unknown`
+    pub battery_status: String,
+    pub ram_total_mb: u64,
+    pub ram_available_mb: u64,
+    pub uptime_secs: u64,
+    pub wifi_ip: Option<String>,
+    /// `/data`, same as the `data` entry in `storage`.
     pub storage_total_mb: u64,
     pub storage_free_mb: u64,
+    pub storage: Vec<device_info::StorageMount>,
+    #[serde(default)]
+    pub label: Option<DeviceLabel>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,52 +81,23 @@ pub struct InstallResult {
 }
 
 // ---------------------------------------------------------------------------
-// Core executors
+// Device management
 // ---------------------------------------------------------------------------
 
-/// Run adb via sidecar, return stdout on success.
-/// Returns Err only if the sidecar process itself fails to spawn/run.
-pub async fn exec(app: &AppHandle, args: &[&str]) -> Result<String, String> {
-    let output = app
-        .shell()
-        .sidecar("adb")
-        .map_err(|e| format!("Failed to create sidecar: {}", e))?
-        .args(args)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute adb: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-    // Return stdout even for non-zero exits (adb install returns 0 on failure)
-    // Only fail if there's no useful output at all and stderr has content
-    if stdout.trim().is_empty() && !stderr.trim().is_empty() && !output.status.success() {
-        return Err(format!("adb error: {}", stderr.trim()));
-    }
-
-    Ok(stdout)
+/// List connected devices (`host:devices-l`, or `adb devices -l` via the sidecar).
+pub async fn list_devices(adb: &impl AdbTransport) -> Result<Vec<AdbDevice>, AdbError> {
+    let output = adb.devices().await?;
+    Ok(parse_devices_output(&output))
 }
 
-/// Run adb with `-s serial` prefix.
-pub async fn exec_device(app: &AppHandle, serial: &str, args: &[&str]) -> Result<String, String> {
-    let mut full_args: Vec<&str> = vec!["-s", serial];
-    full_args.extend_from_slice(args);
-    exec(app, &full_args).await
-}
-
-// ---------------------------------------------------------------------------
-// Device management
-// ---------------------------------------------------------------------------
-
-/// Parse `adb devices -l` output into a list of AdbDevice.
-pub async fn list_devices(app: &AppHandle) -> Result<Vec<AdbDevice>, String> {
-    let output = exec(app, &["devices", "-l"]).await?;
+/// Parse `adb devices -l` / `host:devices-l` output into a list of AdbDevice.
+/// The sidecar prints a "List of devices attached" header; the server does not.
+pub fn parse_devices_output(output: &str) -> Vec<AdbDevice> {
     let mut devices = Vec::new();
 
-    for line in output.lines().skip(1) {
+    for line in output.lines() {
         let line = line.trim();
-        if line.is_empty() {
+        if line.is_empty() || line.starts_with("List of devices") || line.starts_with('*') {
             continue;
         }
 
@@ -118,76 +125,89 @@ pub async fn list_devices(app: &AppHandle) -> Result<Vec<AdbDevice>, String> {
             state,
             model,
             product,
+            label: None,
         });
     }
 
-    Ok(devices)
+    devices
 }
 
-/// Get detailed device info via getprop and df.
+/// Get detailed device info: one `getprop` dump plus one batched shell call
+/// for the probes in [`device_info::DETAIL_PROBES`].
 pub async fn get_device_detail(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
-) -> Result<DeviceDetail, String> {
-    // Fetch properties in parallel-style (sequential for simplicity, all fast)
-    let model = exec_device(app, serial, &["shell", "getprop", "ro.product.model"])
+) -> Result<DeviceDetail, AdbError> {
+    let props = device_info::parse_getprop(
+        &adb.exec_device(serial, &["shell", "getprop"], OpClass::Fast)
+            .await?,
+    );
+    let prop = |key: &str| props.get(key).cloned().unwrap_or_default();
+
+    // Not checked: `which su` exits non-zero on unrooted devices
+    let probes = adb
+        .shell(
+            serial,
+            &device_info::probe_command(device_info::DETAIL_PROBES),
+            OpClass::Fast,
+        )
         .await
-        .unwrap_or_default()
-        .trim()
-        .to_string();
+        .map(|output| device_info::split_probes(&output.stdout))
+        .unwrap_or_default();
+    let probe = |name: &str| probes.get(name).map(String::as_str).unwrap_or_default();
 
-    let android_version =
-        exec_device(app, serial, &["shell", "getprop", "ro.build.version.release"])
-            .await
-            .unwrap_or_default()
-            .trim()
-            .to_string();
-
-    let sdk_version =
-        exec_device(app, serial, &["shell", "getprop", "ro.build.version.sdk"])
-            .await
-            .unwrap_or_default()
-            .trim()
-            .to_string();
-
-    // Parse storage from `df /data`
-    let df_output = exec_device(app, serial, &["shell", "df", "/data"])
-        .await
+    let storage = device_info::parse_df(probe("df"));
+    let (storage_total_mb, storage_free_mb) = storage
+        .iter()
+        .find(|m| m.kind == "data")
+        .map(|m| (m.total_mb, m.free_mb))
         .unwrap_or_default();
+    let (battery_level, battery_status) = device_info::parse_battery(probe("battery"));
+    let (ram_total_mb, ram_available_mb) = device_info::parse_meminfo(probe("meminfo"));
 
-    let (storage_total_mb, storage_free_mb) = parse_df_output(&df_output);
+    let abilist = match prop("ro.product.cpu.abilist") {
+        list if list.is_empty() => prop("ro.product.cpu.abi"),
+        list => list,
+    };
+    let wifi_ip = device_info::parse_inet_addr(probe("wifi")).or_else(|| {
+        props
+            .get("dhcp.wlan0.ipaddress")
+            .filter(|ip| !ip.is_empty())
+            .cloned()
+    });
 
     Ok(DeviceDetail {
         serial: serial.to_string(),
-        model,
-        android_version,
-        sdk_version,
+        model: prop("ro.product.model"),
+        manufacturer: prop("ro.product.manufacturer"),
+        brand: prop("ro.product.brand"),
+        android_version: prop("ro.build.version.release"),
+        sdk_version: prop("ro.build.version.sdk"),
+        fingerprint: prop("ro.build.fingerprint"),
+        security_patch: prop("ro.build.version.security_patch"),
+        build_type: prop("ro.build.type"),
+        rooted: probe("su").lines().any(|line| line.trim().ends_with("/su")),
+        abis: abilist
+            .split(',')
+            .filter(|abi| !abi.is_empty())
+            .map(str::to_string)
+            .collect(),
+        screen_resolution: device_info::parse_wm_value(probe("wm_size")).unwrap_or_default(),
+        screen_density: device_info::parse_wm_value(probe("wm_density"))
+            .and_then(|d| d.parse().ok()),
+        battery_level,
+        battery_status,
+        ram_total_mb,
+        ram_available_mb,
+        uptime_secs: device_info::parse_uptime(probe("uptime")),
+        wifi_ip,
         storage_total_mb,
         storage_free_mb,
+        storage,
+        label: None,
     })
 }
 
-/// Parse `df` output to extract total and free storage in MB.
-/// df output typically has columns: Filesystem, 1K-blocks, Used, Available, Use%, Mounted on
-fn parse_df_output(output: &str) -> (u64, u64) {
-    for line in output.lines().skip(1) {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 4 {
-            // 1K-blocks = total, Available = free
-            let total_kb = parts[1]
-                .replace('K', "")
-                .parse::<u64>()
-                .unwrap_or(0);
-            let free_kb = parts[3]
-                .replace('K', "")
-                .parse::<u64>()
-                .unwrap_or(0);
-            return (total_kb / 1024, free_kb / 1024);
-        }
-    }
-    (0, 0)
-}
-
 // ---------------------------------------------------------------------------
 // App installation / management
 // ---------------------------------------------------------------------------
@@ -196,23 +216,16 @@ fn parse_df_output(output: &str) -> (u64, u64) {
 /// Uses combined stdout+stderr because `adb install` may output the result
 /// (Success/Failure) to either stream depending on adb version and device.
 pub async fn install_apk(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     apk_path: &str,
     flags: &[&str],
-) -> Result<InstallResult, String> {
+) -> Result<InstallResult, AdbError> {
     let mut args: Vec<&str> = vec!["-s", serial, "install"];
     args.extend_from_slice(flags);
     args.push(apk_path);
 
-    let output = app
-        .shell()
-        .sidecar("adb")
-        .map_err(|e| format!("Failed to create sidecar: {}", e))?
-        .args(&args)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute adb: {}", e))?;
+    let output = adb.run(&args, OpClass::Long).await?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -246,20 +259,27 @@ pub async fn install_apk(
 }
 
 /// Uninstall an app by package name.
+/// `adb uninstall` prints "Failure [...]" on stdout and still exits 0.
 pub async fn uninstall_app(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     package_name: &str,
-) -> Result<String, String> {
-    exec_device(app, serial, &["uninstall", package_name]).await
+) -> Result<String, AdbError> {
+    let output = adb
+        .exec_device(serial, &["uninstall", package_name], OpClass::Normal)
+        .await?;
+    if output.contains("Failure") {
+        return Err(AdbError::from_adb_message(&output));
+    }
+    Ok(output)
 }
 
 /// List installed packages. When `include_system` is false, only third-party apps.
 pub async fn list_packages(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     include_system: bool,
-) -> Result<Vec<InstalledApp>, String> {
+) -> Result<Vec<InstalledApp>, AdbError> {
     let flag = if include_system { "" } else { "-3" };
     let args = if flag.is_empty() {
         vec!["shell", "pm", "list", "packages", "-f"]
@@ -267,7 +287,7 @@ pub async fn list_packages(
         vec!["shell", "pm", "list", "packages", flag, "-f"]
     };
 
-    let output = exec_device(app, serial, &args).await?;
+    let output = adb.exec_device(serial, &args, OpClass::Normal).await?;
     let mut apps = Vec::new();
 
     for line in output.lines() {
@@ -281,7 +301,7 @@ pub async fn list_packages(
 
                 // Try to get version info via dumpsys
                 let (version_name, version_code) =
-                    get_app_version(app, serial, &package_name).await;
+                    get_app_version(adb, serial, &package_name).await;
 
                 apps.push(InstalledApp {
                     package_name,
@@ -298,17 +318,14 @@ pub async fn list_packages(
 
 /// Helper to get app version name and code from dumpsys.
 async fn get_app_version(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     package_name: &str,
 ) -> (String, String) {
-    let output = exec_device(
-        app,
-        serial,
-        &["shell", "dumpsys", "package", package_name],
-    )
-    .await
-    .unwrap_or_default();
+    let output = adb
+        .exec_device(serial, &["shell", "dumpsys", "package", package_name], OpClass::Normal)
+        .await
+        .unwrap_or_default();
 
     let mut version_name = String::new();
     let mut version_code = String::new();
@@ -342,30 +359,31 @@ async fn get_app_version(
 
 /// Clear app data.
 pub async fn clear_app_data(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     package_name: &str,
-) -> Result<String, String> {
-    exec_device(app, serial, &["shell", "pm", "clear", package_name]).await
+) -> Result<String, AdbError> {
+    adb.exec_device(serial, &["shell", "pm", "clear", package_name], OpClass::Normal)
+        .await
 }
 
 /// Force stop an app.
 pub async fn force_stop_app(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     package_name: &str,
-) -> Result<String, String> {
-    exec_device(app, serial, &["shell", "am", "force-stop", package_name]).await
+) -> Result<String, AdbError> {
+    adb.exec_device(serial, &["shell", "am", "force-stop", package_name], OpClass::Normal)
+        .await
 }
 
 /// Launch an app using monkey (sends LAUNCHER intent).
 pub async fn launch_app(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     package_name: &str,
-) -> Result<String, String> {
-    exec_device(
-        app,
+) -> Result<String, AdbError> {
+    adb.exec_device(
         serial,
         &[
             "shell",
@@ -376,6 +394,7 @@ pub async fn launch_app(
             "android.intent.category.LAUNCHER",
             "1",
         ],
+        OpClass::Normal,
     )
     .await
 }
@@ -387,40 +406,23 @@ pub async fn launch_app(
 /// Take a screenshot: use `exec-out screencap -p` to capture raw PNG bytes
 /// and write directly to local file, avoiding intermediate device file.
 pub async fn screenshot(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     local_path: &str,
-) -> Result<String, String> {
-    let output = app
-        .shell()
-        .sidecar("adb")
-        .map_err(|e| format!("Failed to create sidecar: {}", e))?
-        .args(&["-s", serial, "exec-out", "screencap", "-p"])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute adb: {}", e))?;
+) -> Result<String, AdbError> {
+    let png = adb.exec_out(serial, "screencap -p", OpClass::Normal).await?;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("screencap failed: {}", stderr.trim()));
-    }
-
-    if output.stdout.is_empty() {
-        return Err("截图失败：screencap 返回空数据".to_string());
+    if png.is_empty() {
+        return Err(AdbError::Command("screencap 返回空数据".to_string()));
     }
 
     // Verify PNG signature (first 4 bytes: 0x89 P N G)
-    if output.stdout.len() < 8
-        || output.stdout[0] != 0x89
-        || output.stdout[1] != b'P'
-        || output.stdout[2] != b'N'
-        || output.stdout[3] != b'G'
-    {
-        return Err("截图失败：返回数据不是有效的 PNG 格式".to_string());
+    if png.len() < 8 || png[0] != 0x89 || png[1] != b'P' || png[2] != b'N' || png[3] != b'G' {
+        return Err(AdbError::Command("screencap 返回数据不是有效的 PNG 格式".to_string()));
     }
 
-    std::fs::write(local_path, &output.stdout)
-        .map_err(|e| format!("写入截图文件失败: {}", e))?;
+    std::fs::write(local_path, &png)
+        .map_err(|e| AdbError::Io(e.to_string()))?;
 
     Ok(local_path.to_string())
 }
@@ -429,34 +431,289 @@ pub async fn screenshot(
 // File operations
 // ---------------------------------------------------------------------------
 
-/// Push a local file to the device.
+/// Push a local file to the device over the SYNC protocol, reporting
+/// progress to `on_progress`. If `remote_path` is an existing directory the
+/// file name is appended, like `adb push` does. Local directories and an
+/// unreachable adb server fall back to the adb process (no progress, no stat).
+/// The whole transfer is bounded by the `Long` timeout.
 pub async fn push_file(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     local_path: &str,
     remote_path: &str,
-) -> Result<String, String> {
-    exec_device(app, serial, &["push", local_path, remote_path]).await
+    transfer_id: &str,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&TransferProgress) + Send,
+) -> Result<TransferResult, AdbError> {
+    operation::with_timeout(
+        adb.timeout(OpClass::Long),
+        &format!("adb push {}", local_path),
+        push_inner(adb, serial, local_path, remote_path, transfer_id, cancel, on_progress),
+    )
+    .await
 }
 
-/// Pull a file from the device to local.
+async fn push_inner(
+    adb: &impl AdbTransport,
+    serial: &str,
+    local_path: &str,
+    remote_path: &str,
+    transfer_id: &str,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&TransferProgress) + Send,
+) -> Result<TransferResult, AdbError> {
+    let started = Instant::now();
+    let native = if Path::new(local_path).is_dir() {
+        None
+    } else {
+        open_sync(adb, serial).await.ok()
+    };
+    let Some(mut conn) = native else {
+        adb.exec_device(serial, &["push", local_path, remote_path], OpClass::Long)
+            .await?;
+        return Ok(sidecar_transfer_result(transfer_id, local_path, started, None));
+    };
+
+    let target = conn.stat_target(remote_path).await?;
+    let remote = if target.is_dir {
+        let file_name = Path::new(local_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!("{}/{}", remote_path.trim_end_matches('/'), file_name)
+    } else {
+        remote_path.to_string()
+    };
+
+    let mut reporter = ProgressReporter::new(on_progress, transfer_id, serial, "push", &remote);
+    let bytes = conn
+        .send(Path::new(local_path), &remote, &mut |d, t| reporter.report(d, t), cancel)
+        .await?;
+    let stat = conn.stat(&remote).await?;
+    conn.quit().await;
+
+    Ok(TransferResult {
+        transfer_id: transfer_id.to_string(),
+        bytes,
+        duration_ms: started.elapsed().as_millis() as u64,
+        stat: Some(stat),
+    })
+}
+
+/// Pull a file from the device over the SYNC protocol, reporting progress
+/// to `on_progress`. Remote directories and an unreachable adb server fall
+/// back to the adb process. Bounded by the `Long` timeout.
+///
+/// The native path downloads to `<local_path>.part` and renames it on
+/// success, so a failed pull never touches an existing file at `local_path`.
 pub async fn pull_file(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     remote_path: &str,
     local_path: &str,
-) -> Result<String, String> {
-    exec_device(app, serial, &["pull", remote_path, local_path]).await
+    transfer_id: &str,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&TransferProgress) + Send,
+) -> Result<TransferResult, AdbError> {
+    let result = operation::with_timeout(
+        adb.timeout(OpClass::Long),
+        &format!("adb pull {}", remote_path),
+        pull_inner(adb, serial, remote_path, local_path, transfer_id, cancel, on_progress),
+    )
+    .await;
+    // A timed-out recv is dropped before it can clean up after itself
+    if matches!(result, Err(AdbError::Timeout(_))) {
+        let _ = std::fs::remove_file(partial_path(local_path));
+    }
+    result
+}
+
+async fn pull_inner(
+    adb: &impl AdbTransport,
+    serial: &str,
+    remote_path: &str,
+    local_path: &str,
+    transfer_id: &str,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&TransferProgress) + Send,
+) -> Result<TransferResult, AdbError> {
+    let started = Instant::now();
+    let Ok(mut conn) = open_sync(adb, serial).await else {
+        adb.exec_device(serial, &["pull", remote_path, local_path], OpClass::Long)
+            .await?;
+        return Ok(sidecar_transfer_result(transfer_id, local_path, started, None));
+    };
+
+    let stat = conn.stat_target(remote_path).await?;
+    if !stat.exists {
+        return Err(AdbError::NoSuchPath(remote_path.to_string()));
+    }
+    if stat.is_dir {
+        conn.quit().await;
+        adb.exec_device(serial, &["pull", remote_path, local_path], OpClass::Long)
+            .await?;
+        return Ok(sidecar_transfer_result(transfer_id, local_path, started, Some(stat)));
+    }
+
+    let partial = partial_path(local_path);
+    let mut reporter = ProgressReporter::new(on_progress, transfer_id, serial, "pull", remote_path);
+    let bytes = conn
+        .recv(
+            remote_path,
+            Path::new(&partial),
+            stat.size,
+            &mut |d, t| reporter.report(d, t),
+            cancel,
+        )
+        .await?;
+    conn.quit().await;
+    if let Err(e) = tokio::fs::rename(&partial, local_path).await {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(AdbError::Io(e.to_string()));
+    }
+
+    Ok(TransferResult {
+        transfer_id: transfer_id.to_string(),
+        bytes,
+        duration_ms: started.elapsed().as_millis() as u64,
+        stat: Some(stat),
+    })
+}
+
+/// Where a native pull writes until it completes.
+fn partial_path(local_path: &str) -> String {
+    format!("{}.part", local_path)
+}
+
+/// SYNC connection through the transport's adb server.
+async fn open_sync(adb: &impl AdbTransport, serial: &str) -> Result<SyncConnection, AdbError> {
+    let client = adb.client().ok_or_else(|| {
+        AdbError::ServerUnavailable("adb server is not reachable over tcp".to_string())
+    })?;
+    SyncConnection::open(client, serial).await
+}
+
+/// Result for a transfer that went through the adb process; size comes from the local side.
+fn sidecar_transfer_result(
+    transfer_id: &str,
+    local_path: &str,
+    started: Instant,
+    stat: Option<RemoteStat>,
+) -> TransferResult {
+    TransferResult {
+        transfer_id: transfer_id.to_string(),
+        bytes: std::fs::metadata(local_path).map(|m| m.len()).unwrap_or(0),
+        duration_ms: started.elapsed().as_millis() as u64,
+        stat,
+    }
+}
+
+/// `LIST` a remote directory over the SYNC protocol, without `.` and `..`.
+pub async fn list_dir(
+    adb: &impl AdbTransport,
+    serial: &str,
+    remote_dir: &str,
+) -> Result<Vec<RemoteEntry>, AdbError> {
+    let list = async {
+        let mut conn = open_sync(adb, serial).await?;
+        let entries = conn.list(remote_dir).await?;
+        conn.quit().await;
+        Ok(entries)
+    };
+    let entries = operation::with_timeout(
+        adb.timeout(OpClass::Normal),
+        &format!("sync LIST {}", remote_dir),
+        list,
+    )
+    .await?;
+    Ok(entries
+        .into_iter()
+        .filter(|e: &RemoteEntry| e.name != "." && e.name != "..")
+        .collect())
+}
+
+/// `STAT` a remote path over the SYNC protocol.
+pub async fn stat_file(
+    adb: &impl AdbTransport,
+    serial: &str,
+    remote_path: &str,
+) -> Result<RemoteStat, AdbError> {
+    let stat = async {
+        let mut conn = open_sync(adb, serial).await?;
+        let stat = conn.stat(remote_path).await?;
+        conn.quit().await;
+        Ok(stat)
+    };
+    operation::with_timeout(
+        adb.timeout(OpClass::Fast),
+        &format!("sync STAT {}", remote_path),
+        stat,
+    )
+    .await
+}
+
+/// Progress callback that emits `transfer-progress` events to the frontend.
+pub fn emit_progress(app: &AppHandle) -> impl FnMut(&TransferProgress) + Send + '_ {
+    move |progress| {
+        let _ = app.emit("transfer-progress", progress);
+    }
+}
+
+/// Throttles progress reports (at most every 200 ms, plus the last chunk).
+struct ProgressReporter<F> {
+    on_progress: F,
+    progress: TransferProgress,
+    started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl<F: FnMut(&TransferProgress)> ProgressReporter<F> {
+    fn new(on_progress: F, transfer_id: &str, serial: &str, direction: &str, path: &str) -> Self {
+        Self {
+            on_progress,
+            progress: TransferProgress {
+                transfer_id: transfer_id.to_string(),
+                serial: serial.to_string(),
+                direction: direction.to_string(),
+                path: path.to_string(),
+                bytes_done: 0,
+                bytes_total: 0,
+                rate_bps: 0.0,
+            },
+            started: Instant::now(),
+            last_emit: None,
+        }
+    }
+
+    fn report(&mut self, done: u64, total: u64) {
+        let finished = done >= total;
+        let due = self
+            .last_emit
+            .is_none_or(|t| t.elapsed() >= Duration::from_millis(200));
+        if !finished && !due {
+            return;
+        }
+
+        let secs = self.started.elapsed().as_secs_f64();
+        self.progress.bytes_done = done;
+        self.progress.bytes_total = total;
+        self.progress.rate_bps = if secs > 0.0 { done as f64 / secs } else { 0.0 };
+        self.last_emit = Some(Instant::now());
+        (self.on_progress)(&self.progress);
+    }
 }
 
 /// List files in a remote directory via `ls -la`.
 /// Returns empty vec if the directory does not exist.
 pub async fn list_files(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     remote_dir: &str,
-) -> Result<Vec<String>, String> {
-    match exec_device(app, serial, &["shell", "ls", "-la", remote_dir]).await {
+) -> Result<Vec<String>, AdbError> {
+    let output = adb
+        .exec_device(serial, &["shell", "ls", "-la", remote_dir], OpClass::Normal)
+        .await;
+    match output {
         Ok(output) => {
             let files: Vec<String> = output
                 .lines()
@@ -465,18 +722,19 @@ pub async fn list_files(
                 .collect();
             Ok(files)
         }
-        Err(e) if e.contains("No such file or directory") => Ok(vec![]),
+        Err(AdbError::NoSuchPath(_)) => Ok(vec![]),
         Err(e) => Err(e),
     }
 }
 
-/// Delete a remote file.
+/// Delete a remote file. A missing path or a read-only location is reported
+/// from `rm`'s exit status.
 pub async fn delete_file(
-    app: &AppHandle,
+    adb: &impl AdbTransport,
     serial: &str,
     remote_path: &str,
-) -> Result<String, String> {
-    exec_device(app, serial, &["shell", "rm", "-f", remote_path]).await
+) -> Result<String, AdbError> {
+    adb.exec_device(serial, &["shell", "rm", remote_path], OpClass::Normal).await
 }
 
 // ---------------------------------------------------------------------------
@@ -484,18 +742,19 @@ pub async fn delete_file(
 // ---------------------------------------------------------------------------
 
 /// Check adb server status / version.
-pub async fn check_server(app: &AppHandle) -> Result<String, String> {
-    exec(app, &["version"]).await
+/// Asks the running server first; the sidecar fallback also starts it if needed.
+pub async fn check_server(adb: &impl AdbTransport) -> Result<String, AdbError> {
+    adb.version().await
 }
 
 /// Kill the adb server.
-pub async fn kill_server(app: &AppHandle) -> Result<String, String> {
-    exec(app, &["kill-server"]).await
+pub async fn kill_server(adb: &impl AdbTransport) -> Result<String, AdbError> {
+    adb.exec(&["kill-server"], OpClass::Normal).await
 }
 
 /// Start the adb server.
-pub async fn start_server(app: &AppHandle) -> Result<String, String> {
-    exec(app, &["start-server"]).await
+pub async fn start_server(adb: &impl AdbTransport) -> Result<String, AdbError> {
+    adb.exec(&["start-server"], OpClass::Normal).await
 }
 
 // ---------------------------------------------------------------------------
@@ -503,13 +762,13 @@ pub async fn start_server(app: &AppHandle) -> Result<String, String> {
 // ---------------------------------------------------------------------------
 
 /// Connect to a device over WiFi (address should be ip:port).
-pub async fn connect_wifi(app: &AppHandle, address: &str) -> Result<String, String> {
-    exec(app, &["connect", address]).await
+pub async fn connect_wifi(adb: &impl AdbTransport, address: &str) -> Result<String, AdbError> {
+    adb.exec(&["connect", address], OpClass::Normal).await
 }
 
 /// Disconnect a WiFi device.
-pub async fn disconnect_wifi(app: &AppHandle, address: &str) -> Result<String, String> {
-    exec(app, &["disconnect", address]).await
+pub async fn disconnect_wifi(adb: &impl AdbTransport, address: &str) -> Result<String, AdbError> {
+    adb.exec(&["disconnect", address], OpClass::Normal).await
 }
 
 // ---------------------------------------------------------------------------
@@ -520,21 +779,13 @@ pub async fn disconnect_wifi(app: &AppHandle, address: &str) -> Result<String, S
 /// Looks for pattern like "Failure [INSTALL_FAILED_UPDATE_INCOMPATIBLE]"
 /// or "Failure [INSTALL_FAILED_UPDATE_INCOMPATIBLE: ...]".
 pub fn extract_error_code(output: &str) -> String {
-    // Match "Failure [CODE]" or "Failure [CODE: detail]"
-    if let Some(start) = output.find("Failure [") {
-        let after = &output[start + 9..]; // skip "Failure ["
-        if let Some(end) = after.find(']') {
-            let code_section = &after[..end];
-            // Handle "CODE: detail" format — take only the code part
-            return code_section
-                .split(':')
-                .next()
-                .unwrap_or(code_section)
-                .trim()
-                .to_string();
-        }
-    }
-    "UNKNOWN_ERROR".to_string()
+    error::extract_failure_code(output).unwrap_or_else(|| "UNKNOWN_ERROR".to_string())
+}
+
+/// Last component of a local or remote path; accepts `\\` as well as `/`
+/// since local paths may come from Windows.
+pub fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
 }
 
 #[cfg(test)]
@@ -566,10 +817,300 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_df_output() {
-        let df = "Filesystem     1K-blocks    Used Available Use% Mounted on\n/dev/block/dm-0 52428800 31457280 20971520  60% /data\n";
-        let (total, free) = parse_df_output(df);
-        assert_eq!(total, 52428800 / 1024);
-        assert_eq!(free, 20971520 / 1024);
+    fn test_parse_devices_output() {
+        let sidecar = "List of devices attached\nR58M123ABC             device usb:1-1 product:beyond1 model:SM_G973F device:beyond1 transport_id:2\nemulator-5554          unauthorized transport_id:1\n\n";
+        let devices = parse_devices_output(sidecar);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].serial, "R58M123ABC");
+        assert_eq!(devices[0].model, "SM_G973F");
+        assert_eq!(devices[0].product, "beyond1");
+        assert_eq!(devices[1].state, "unauthorized");
+
+        // host:devices-l has no header line
+        let native = "R58M123ABC             device product:beyond1 model:SM_G973F\n";
+        assert_eq!(parse_devices_output(native).len(), 1);
+    }
+
+    // -- Transcript fixtures (Android 8 – 15) --------------------------------
+
+    use crate::transport::mock::MockTransport;
+
+    struct Fixture {
+        transcript: &'static str,
+        model: &'static str,
+        release: &'static str,
+        sdk: &'static str,
+        manufacturer: &'static str,
+        abis: &'static str,
+        screen: (&'static str, u32),
+        battery: (u8, &'static str),
+        ram_total_mb: u64,
+        wifi_ip: Option<&'static str>,
+        build_type: &'static str,
+        rooted: bool,
+        total_kb: u64,
+        free_kb: u64,
+        packages: [(&'static str, &'static str, &'static str); 2],
+    }
+
+    macro_rules! transcript {
+        ($name:literal) => {
+            include_str!(concat!("../tests/fixtures/transcripts/", $name, ".txt"))
+        };
+    }
+
+    fn fixtures() -> Vec<Fixture> {
+        vec![
+            Fixture {
+                transcript: transcript!("android-8"),
+                model: "Pixel 2",
+                release: "8.1.0",
+                sdk: "27",
+                manufacturer: "Google",
+                abis: "arm64-v8a,armeabi-v7a,armeabi",
+                screen: ("1080x1920", 420),
+                battery: (64, "discharging"),
+                ram_total_mb: 3754,
+                wifi_ip: Some("192.168.1.23"),
+                build_type: "user",
+                rooted: false,
+                total_kb: 52576712,
+                free_kb: 43105432,
+                packages: [
+                    ("com.example.notes", "3.2.1", "321"),
+                    ("org.mozilla.firefox", "68.11.0", "2015709729"),
+                ],
+            },
+            Fixture {
+                transcript: transcript!("android-9"),
+                model: "SM-G960F",
+                release: "9",
+                sdk: "28",
+                manufacturer: "samsung",
+                abis: "arm64-v8a,armeabi-v7a,armeabi",
+                screen: ("1080x2220", 480),
+                battery: (91, "charging"),
+                ram_total_mb: 3636,
+                wifi_ip: None,
+                build_type: "user",
+                rooted: false,
+                total_kb: 56880984,
+                free_kb: 35811876,
+                packages: [
+                    ("com.example.notes", "3.4.0", "340"),
+                    ("com.whatsapp", "2.19.360", "452778"),
+                ],
+            },
+            Fixture {
+                transcript: transcript!("android-10"),
+                model: "Pixel 3a",
+                release: "10",
+                sdk: "29",
+                manufacturer: "Google",
+                abis: "arm64-v8a,armeabi-v7a,armeabi",
+                screen: ("1080x2220", 440),
+                battery: (100, "full"),
+                ram_total_mb: 3690,
+                wifi_ip: Some("10.0.0.57"),
+                build_type: "user",
+                rooted: false,
+                total_kb: 53867488,
+                free_kb: 40862756,
+                packages: [
+                    ("com.example.notes", "4.0.0", "400"),
+                    ("com.spotify.music", "8.5.68.904", "59180262"),
+                ],
+            },
+            Fixture {
+                transcript: transcript!("android-11"),
+                model: "Pixel 4a",
+                release: "11",
+                sdk: "30",
+                manufacturer: "Google",
+                abis: "arm64-v8a,armeabi-v7a,armeabi",
+                screen: ("1080x2340", 440),
+                battery: (42, "not_charging"),
+                ram_total_mb: 5674,
+                wifi_ip: Some("192.168.0.105"),
+                build_type: "user",
+                rooted: true,
+                total_kb: 112337152,
+                free_kb: 80868096,
+                packages: [
+                    ("com.example.notes", "4.1.2", "412"),
+                    ("com.android.chrome", "90.0.4430.91", "443009134"),
+                ],
+            },
+            Fixture {
+                transcript: transcript!("android-12"),
+                model: "SM-G991B",
+                release: "12",
+                sdk: "31",
+                manufacturer: "samsung",
+                abis: "arm64-v8a,armeabi-v7a,armeabi",
+                screen: ("1080x2400", 420),
+                battery: (76, "discharging"),
+                ram_total_mb: 7485,
+                wifi_ip: Some("192.168.178.34"),
+                build_type: "user",
+                rooted: false,
+                total_kb: 114812216,
+                free_kb: 66467160,
+                packages: [
+                    ("com.example.notes", "5.0.0", "500"),
+                    ("com.samsung.android.app.notes.addons", "4.3.02.7", "430207000"),
+                ],
+            },
+            Fixture {
+                transcript: transcript!("android-13"),
+                model: "sdk_gphone64_x86_64",
+                release: "13",
+                sdk: "33",
+                manufacturer: "Google",
+                abis: "x86_64,arm64-v8a",
+                screen: ("1080x2400", 420),
+                battery: (100, "charging"),
+                ram_total_mb: 1967,
+                wifi_ip: Some("10.0.2.16"),
+                build_type: "userdebug",
+                rooted: true,
+                total_kb: 6082144,
+                free_kb: 3706396,
+                packages: [
+                    ("com.example.notes", "5.2.0", "520"),
+                    ("io.flutter.demo", "1.0.0", "1"),
+                ],
+            },
+            Fixture {
+                transcript: transcript!("android-14"),
+                model: "Pixel 8",
+                release: "14",
+                sdk: "34",
+                manufacturer: "Google",
+                abis: "arm64-v8a",
+                screen: ("1080x2400", 420),
+                battery: (58, "discharging"),
+                ram_total_mb: 7674,
+                wifi_ip: Some("192.168.1.88"),
+                build_type: "user",
+                rooted: false,
+                total_kb: 117000000,
+                free_kb: 52650000,
+                packages: [
+                    ("com.example.notes", "6.0.0", "600"),
+                    ("com.google.android.apps.maps", "11.107.0101", "1063741031"),
+                ],
+            },
+            Fixture {
+                transcript: transcript!("android-15"),
+                model: "Pixel 9",
+                release: "15",
+                sdk: "35",
+                manufacturer: "Google",
+                abis: "arm64-v8a",
+                screen: ("1080x2424", 420),
+                battery: (23, "charging"),
+                ram_total_mb: 11480,
+                wifi_ip: None,
+                build_type: "user",
+                rooted: false,
+                total_kb: 232718336,
+                free_kb: 161116160,
+                packages: [
+                    ("com.example.notes", "7.0.0", "700"),
+                    ("org.telegram.messenger", "11.2.3", "5335"),
+                ],
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_device_detail_fixtures() {
+        for f in fixtures() {
+            let mock = MockTransport::from_transcript(f.transcript);
+            let detail = get_device_detail(&mock, "SERIAL").await.unwrap();
+            assert_eq!(detail.model, f.model, "{}", f.release);
+            assert_eq!(detail.android_version, f.release);
+            assert_eq!(detail.sdk_version, f.sdk);
+            assert_eq!(detail.manufacturer, f.manufacturer);
+            assert_eq!(detail.abis.join(","), f.abis);
+            assert_eq!(
+                (detail.screen_resolution.as_str(), detail.screen_density),
+                (f.screen.0, Some(f.screen.1)),
+                "{}",
+                f.release
+            );
+            assert_eq!(
+                (detail.battery_level, detail.battery_status.as_str()),
+                (Some(f.battery.0), f.battery.1)
+            );
+            assert_eq!(detail.ram_total_mb, f.ram_total_mb);
+            assert!(detail.ram_available_mb > 0 && detail.uptime_secs > 0);
+            assert_eq!(detail.wifi_ip.as_deref(), f.wifi_ip, "{}", f.release);
+            assert_eq!(detail.build_type, f.build_type);
+            assert_eq!(detail.rooted, f.rooted, "{}", f.release);
+            assert!(detail.fingerprint.contains(f.release));
+            assert!(!detail.security_patch.is_empty());
+            assert_eq!(detail.storage_total_mb, f.total_kb / 1024, "{}", f.release);
+            assert_eq!(detail.storage_free_mb, f.free_kb / 1024, "{}", f.release);
+            let kinds: Vec<&str> = detail.storage.iter().map(|m| m.kind.as_str()).collect();
+            assert!(
+                kinds.starts_with(&["system", "data", "shared"]),
+                "{} {:?}",
+                f.release,
+                kinds
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_packages_fixtures() {
+        for f in fixtures() {
+            let mock = MockTransport::from_transcript(f.transcript);
+            let apps = list_packages(&mock, "SERIAL", false).await.unwrap();
+            let got: Vec<(&str, &str, &str)> = apps
+                .iter()
+                .map(|a| {
+                    assert!(!a.is_system);
+                    (
+                        a.package_name.as_str(),
+                        a.version_name.as_str(),
+                        a.version_code.as_str(),
+                    )
+                })
+                .collect();
+            assert_eq!(got, f.packages.to_vec(), "Android {}", f.release);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_install_failure_from_transcript() {
+        let mock = MockTransport::from_transcript(
+            "$ adb -s SER install -r /tmp/app.apk\n\
+             Performing Streamed Install\n\
+             ! adb: failed to install /tmp/app.apk: Failure [INSTALL_FAILED_VERSION_DOWNGRADE: Downgrade detected]\n\
+             [exit 1]\n",
+        );
+        let result = install_apk(&mock, "SER", "/tmp/app.apk", &["-r"]).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(
+            result.error_code.as_deref(),
+            Some("INSTALL_FAILED_VERSION_DOWNGRADE")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_screenshot_rejects_non_png() {
+        let path = std::env::temp_dir().join(format!("adb_shot_{}.png", std::process::id()));
+        let local = path.to_string_lossy().to_string();
+
+        let command = "-s SER exec-out screencap -p";
+        let mock = MockTransport::new().with_output(command, b"\x89PNG\r\n\x1a\n....".to_vec());
+        assert_eq!(screenshot(&mock, "SER", &local).await.unwrap(), local);
+        assert!(path.exists());
+
+        let mock = MockTransport::new().with_output(command, "error: no display");
+        assert!(screenshot(&mock, "SER", &local).await.is_err());
+        let _ = std::fs::remove_file(&path);
     }
 }
@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::error::AdbError;
+
+// ---------------------------------------------------------------------------
+// Native adb server client
+//
+// Talks the host protocol directly to the adb server (TCP 5037 by default)
+// instead of spawning an `adb` process per call. Every request is framed as
+// a 4-digit hex length followed by the service name; the server answers with
+// `OKAY` or `FAIL` + hex length + message.
+// ---------------------------------------------------------------------------
+
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+pub const DEFAULT_PORT: u16 = 5037;
+
+#[derive(Debug, Clone)]
+pub struct AdbClient {
+    pub host: String,
+    pub port: u16,
+    features: FeatureCache,
+}
+
+/// Feature lists by serial. Every shell and SYNC call needs the device's
+/// features, so they are asked for once; clones share the cache. The device
+/// tracker forgets a serial when the device detaches or changes state.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureCache(Arc<Mutex<HashMap<String, String>>>);
+
+impl FeatureCache {
+    fn get(&self, serial: &str) -> Option<String> {
+        self.0.lock().ok()?.get(serial).cloned()
+    }
+
+    fn insert(&self, serial: &str, features: &str) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.insert(serial.to_string(), features.to_string());
+        }
+    }
+
+    pub fn forget(&self, serial: &str) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.remove(serial);
+        }
+    }
+}
+
+impl Default for AdbClient {
+    fn default() -> Self {
+        Self::new(DEFAULT_HOST, DEFAULT_PORT)
+    }
+}
+
+impl AdbClient {
+    pub fn new(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            features: FeatureCache::default(),
+        }
+    }
+
+    /// Share `cache` with other clients, e.g. the app-wide one.
+    pub fn with_feature_cache(mut self, cache: FeatureCache) -> Self {
+        self.features = cache;
+        self
+    }
+
+    /// Open a fresh connection to the adb server.
+    pub async fn connect(&self) -> Result<TcpStream, AdbError> {
+        TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| AdbError::ServerUnavailable(e.to_string()))
+    }
+
+    /// `host:version` — returns the server's internal protocol version (e.g. 41).
+    pub async fn version(&self) -> Result<u32, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, "host:version").await?;
+        let payload = read_length_prefixed(&mut stream).await?;
+        u32::from_str_radix(payload.trim(), 16)
+            .map_err(|_| AdbError::Protocol(format!("invalid server version: {}", payload)))
+    }
+
+    /// `host:devices-l` — same listing as `adb devices -l`, without the header.
+    pub async fn devices_long(&self) -> Result<String, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, "host:devices-l").await?;
+        read_length_prefixed(&mut stream).await
+    }
+
+    /// One-shot host service answered with a length-prefixed message,
+    /// e.g. `host:connect:10.0.0.5:5555`.
+    pub async fn host_query(&self, service: &str) -> Result<String, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, service).await?;
+        read_length_prefixed(&mut stream).await
+    }
+
+    /// `host-serial:<serial>:features` — comma-separated feature list of the
+    /// device (e.g. `shell_v2,cmd,stat_v2`). Cached; an empty list (device not
+    /// authorized yet) is asked for again next time.
+    pub async fn features(&self, serial: &str) -> Result<String, AdbError> {
+        if let Some(features) = self.features.get(serial) {
+            return Ok(features);
+        }
+        let features = self
+            .host_query(&format!("host-serial:{}:features", serial))
+            .await?;
+        if !features.is_empty() {
+            self.features.insert(serial, &features);
+        }
+        Ok(features)
+    }
+
+    /// `host:kill` — ask the server to exit.
+    pub async fn kill(&self) -> Result<(), AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, "host:kill").await
+    }
+
+    /// `host:track-devices-l` — returns the open connection; the server pushes a
+    /// length-prefixed device listing now and again on every change.
+    pub async fn track_devices(&self) -> Result<TcpStream, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, "host:track-devices-l").await?;
+        Ok(stream)
+    }
+
+    /// Switch a fresh connection to the transport of the given device.
+    /// The returned stream is ready to receive a local service request.
+    pub async fn transport(&self, serial: &str) -> Result<TcpStream, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, &format!("host:transport:{}", serial)).await?;
+        Ok(stream)
+    }
+
+    /// Run `shell:<command>` on the device and return everything it printed,
+    /// stderr included. See [`crate::shell::run`] for exit codes.
+    pub async fn shell(&self, serial: &str, command: &str) -> Result<Vec<u8>, AdbError> {
+        let mut stream = self.transport(serial).await?;
+        send_request(&mut stream, &format!("shell:{}", command)).await?;
+        read_to_end(&mut stream).await
+    }
+
+    /// Run `exec:<command>` on the device — like `shell:` but without a PTY,
+    /// so binary output (e.g. `screencap -p`) is not mangled.
+    pub async fn exec_out(&self, serial: &str, command: &str) -> Result<Vec<u8>, AdbError> {
+        self.device_service(serial, &format!("exec:{}", command))
+            .await
+    }
+
+    /// Open a local service that prints a reply and closes the connection,
+    /// e.g. `reboot:bootloader` or `tcpip:5555`, and return the reply.
+    pub async fn device_service(&self, serial: &str, service: &str) -> Result<Vec<u8>, AdbError> {
+        let mut stream = self.transport(serial).await?;
+        send_request(&mut stream, service).await?;
+        read_to_end(&mut stream).await
+    }
+
+    /// `host-serial:<serial>:<service>` for `forward:<local>;<remote>` and
+    /// `killforward:<local>`. Returns the allocated port of a `tcp:0`
+    /// listener, empty otherwise.
+    pub async fn forward(&self, serial: &str, service: &str) -> Result<String, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, &format!("host-serial:{}:{}", serial, service)).await?;
+        read_forward_reply(&mut stream, service).await
+    }
+
+    /// `reverse:<service>` on the device: the services of [`Self::forward`]
+    /// with the listener on the device, plus `killforward-all` and
+    /// `list-forward`.
+    pub async fn reverse(&self, serial: &str, service: &str) -> Result<String, AdbError> {
+        let mut stream = self.transport(serial).await?;
+        send_request(&mut stream, &format!("reverse:{}", service)).await?;
+        if service == "list-forward" {
+            return read_length_prefixed(&mut stream).await;
+        }
+        read_forward_reply(&mut stream, service).await
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Wire helpers
+// ---------------------------------------------------------------------------
+
+/// Send a framed request and wait for the OKAY/FAIL status.
+pub async fn send_request(stream: &mut TcpStream, request: &str) -> Result<(), AdbError> {
+    let framed = format!("{:04x}{}", request.len(), request);
+    stream
+        .write_all(framed.as_bytes())
+        .await
+        .map_err(|e| AdbError::Protocol(e.to_string()))?;
+    read_status(stream).await
+}
+
+/// Read a 4-byte status; on `FAIL` read the attached message and return it as Err.
+pub async fn read_status(stream: &mut TcpStream) -> Result<(), AdbError> {
+    let mut status = [0u8; 4];
+    stream
+        .read_exact(&mut status)
+        .await
+        .map_err(|e| AdbError::Protocol(e.to_string()))?;
+
+    match &status {
+        b"OKAY" => Ok(()),
+        b"FAIL" => {
+            let message = read_length_prefixed(stream).await?;
+            Err(AdbError::from_adb_message(&message))
+        }
+        other => Err(AdbError::Protocol(format!(
+            "unexpected response: {}",
+            String::from_utf8_lossy(other)
+        ))),
+    }
+}
+
+/// Read a payload framed as 4-digit hex length + data.
+pub async fn read_length_prefixed(stream: &mut TcpStream) -> Result<String, AdbError> {
+    let mut len_buf = [0u8; 4];
+    stream
+        .read_exact(&mut len_buf)
+        .await
+        .map_err(|e| AdbError::Protocol(e.to_string()))?;
+    let len = usize::from_str_radix(&String::from_utf8_lossy(&len_buf), 16)
+        .map_err(|_| AdbError::Protocol("invalid length prefix".to_string()))?;
+
+    let mut data = vec![0u8; len];
+    stream
+        .read_exact(&mut data)
+        .await
+        .map_err(|e| AdbError::Protocol(e.to_string()))?;
+    Ok(String::from_utf8_lossy(&data).to_string())
+}
+
+/// Forward services answer with a second status once the listener is set
+/// up (or removed), followed by the port for a `tcp:0` listener.
+async fn read_forward_reply(stream: &mut TcpStream, service: &str) -> Result<String, AdbError> {
+    read_status(stream).await?;
+    if service.starts_with("forward:tcp:0;") {
+        return read_length_prefixed(stream).await;
+    }
+    Ok(String::new())
+}
+
+async fn read_to_end(stream: &mut TcpStream) -> Result<Vec<u8>, AdbError> {
+    let mut output = Vec::new();
+    stream
+        .read_to_end(&mut output)
+        .await
+        .map_err(|e| AdbError::Protocol(e.to_string()))?;
+    Ok(output)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Read one framed request from a client connection.
+    async fn read_request(stream: &mut TcpStream) -> String {
+        read_length_prefixed(stream).await.expect("framed request")
+    }
+
+    async fn write_payload(stream: &mut TcpStream, payload: &str) {
+        let framed = format!("OKAY{:04x}{}", payload.len(), payload);
+        stream.write_all(framed.as_bytes()).await.unwrap();
+    }
+
+    /// Start a fake adb server that handles a single connection with `handler`.
+    async fn fake_server<F, Fut>(handler: F) -> AdbClient
+    where
+        F: FnOnce(TcpStream) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handler(stream).await;
+        });
+        AdbClient::new("127.0.0.1", port)
+    }
+
+    #[tokio::test]
+    async fn test_version() {
+        let client = fake_server(|mut s| async move {
+            assert_eq!(read_request(&mut s).await, "host:version");
+            write_payload(&mut s, "0029").await;
+        })
+        .await;
+        assert_eq!(client.version().await.unwrap(), 41);
+    }
+
+    #[tokio::test]
+    async fn test_devices_long() {
+        let listing =
+            "emulator-5554          device product:sdk model:Pixel_7 device:emu64 transport_id:1\n";
+        let client = fake_server(move |mut s| async move {
+            assert_eq!(read_request(&mut s).await, "host:devices-l");
+            write_payload(&mut s, listing).await;
+        })
+        .await;
+        assert_eq!(client.devices_long().await.unwrap(), listing);
+    }
+
+    #[tokio::test]
+    async fn test_track_devices_pushes_updates() {
+        let client = fake_server(|mut s| async move {
+            assert_eq!(read_request(&mut s).await, "host:track-devices-l");
+            s.write_all(b"OKAY").await.unwrap();
+            for listing in ["", "ABC123 unauthorized\n", "ABC123 device model:Pixel_7\n"] {
+                let framed = format!("{:04x}{}", listing.len(), listing);
+                s.write_all(framed.as_bytes()).await.unwrap();
+            }
+        })
+        .await;
+        let mut stream = client.track_devices().await.unwrap();
+        assert_eq!(read_length_prefixed(&mut stream).await.unwrap(), "");
+        assert_eq!(
+            read_length_prefixed(&mut stream).await.unwrap(),
+            "ABC123 unauthorized\n"
+        );
+        assert!(read_length_prefixed(&mut stream)
+            .await
+            .unwrap()
+            .contains("model:Pixel_7"));
+        assert!(read_length_prefixed(&mut stream).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_features_are_cached() {
+        // The fake server takes a single connection, so a second query fails
+        let client = fake_server(|mut s| async move {
+            assert_eq!(read_request(&mut s).await, "host-serial:ABC123:features");
+            write_payload(&mut s, "shell_v2,cmd,stat_v2").await;
+        })
+        .await;
+        let cache = FeatureCache::default();
+        let client = client.with_feature_cache(cache.clone());
+        assert_eq!(
+            client.features("ABC123").await.unwrap(),
+            "shell_v2,cmd,stat_v2"
+        );
+        assert_eq!(
+            client.clone().features("ABC123").await.unwrap(),
+            "shell_v2,cmd,stat_v2"
+        );
+        cache.forget("ABC123");
+        assert!(client.features("ABC123").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shell_reads_until_close() {
+        let client = fake_server(|mut s| async move {
+            assert_eq!(read_request(&mut s).await, "host:transport:ABC123");
+            s.write_all(b"OKAY").await.unwrap();
+            assert_eq!(read_request(&mut s).await, "shell:getprop ro.product.model");
+            s.write_all(b"OKAYPixel 7\n").await.unwrap();
+        })
+        .await;
+        let out = client
+            .shell("ABC123", "getprop ro.product.model")
+            .await
+            .unwrap();
+        assert_eq!(out, b"Pixel 7\n");
+    }
+
+    #[tokio::test]
+    async fn test_fail_status_is_error() {
+        let client = fake_server(|mut s| async move {
+            read_request(&mut s).await;
+            let msg = "device 'XYZ' not found";
+            let framed = format!("FAIL{:04x}{}", msg.len(), msg);
+            s.write_all(framed.as_bytes()).await.unwrap();
+        })
+        .await;
+        let err = client.shell("XYZ", "ls").await.unwrap_err();
+        assert_eq!(
+            err,
+            AdbError::DeviceNotFound("device 'XYZ' not found".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_refused() {
+        // Bind then drop to get a port nobody listens on.
+        let port = {
+            let l = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            l.local_addr().unwrap().port()
+        };
+        let client = AdbClient::new("127.0.0.1", port);
+        assert!(matches!(
+            client.version().await,
+            Err(AdbError::ServerUnavailable(_))
+        ));
+    }
+}
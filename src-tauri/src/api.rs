@@ -0,0 +1,948 @@
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::AdbError;
+use crate::logcat::{self, LogcatStream};
+use crate::op_log::{self, OpLogEntry, OpLogState};
+use crate::storage;
+use crate::transport::AppTransport;
+
+// ---------------------------------------------------------------------------
+// Local automation API
+//
+// Opt-in HTTP server on 127.0.0.1 for test scripts. `POST /rpc` takes
+// JSON-RPC 2.0 calls named after the Tauri commands, with the same camelCase
+// params the frontend passes to `invoke`. `GET /logcat?serial=...` upgrades
+// to a WebSocket that streams parsed logcat lines as JSON text frames.
+//
+// Every request carries the token as `Authorization: Bearer <token>`. Only
+// the WebSocket route also takes `?token=`, since browsers cannot set headers
+// on a WebSocket; `/rpc` refuses it so tokens stay out of URLs and proxy
+// logs. Every call ends up
+// in the op log with source `api`: commands that log for themselves are tagged
+// through `op_log::with_source`, the rest get one entry per call.
+// ---------------------------------------------------------------------------
+
+pub const DEFAULT_PORT: u16 = 17321;
+pub const SOURCE: &str = "api";
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Application error; `data` holds the `AdbError` as `{ code, message, detail }`.
+const COMMAND_ERROR: i64 = -32000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiConfig {
+    /// Start the server with the app.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Generated on first enable; kept across restarts so scripts keep working.
+    #[serde(default)]
+    pub token: String,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiStatus {
+    #[serde(flatten)]
+    pub config: ApiConfig,
+    pub running: bool,
+}
+
+// ---------------------------------------------------------------------------
+// Persistence helpers
+// ---------------------------------------------------------------------------
+
+/// `~/AdbTool/api.json`; a missing or unreadable config means the API stays
+/// off.
+const CONFIG_FILE: &str = "api.json";
+
+/// 32 random hex characters.
+fn new_token() -> Result<String, AdbError> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| AdbError::Io(format!("random token: {e}")))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// ---------------------------------------------------------------------------
+// Server lifecycle
+// ---------------------------------------------------------------------------
+
+/// Managed state: saved config and the shutdown handle of the running server.
+pub struct ApiState {
+    pub config: Mutex<ApiConfig>,
+    server: Mutex<Option<CancellationToken>>,
+}
+
+impl ApiState {
+    /// Create state, pre-loading the saved config from disk.
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(storage::load_or_default(CONFIG_FILE)),
+            server: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> ApiStatus {
+        ApiStatus {
+            config: self.config.lock().map(|c| c.clone()).unwrap_or_default(),
+            running: self.server.lock().is_ok_and(|s| s.is_some()),
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(shutdown) = self.server.lock().ok().and_then(|mut s| s.take()) {
+            shutdown.cancel();
+        }
+    }
+}
+
+impl Default for ApiState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Start the server at app launch when it was left enabled.
+pub fn start_if_enabled(app: AppHandle) {
+    let state = app.state::<ApiState>();
+    let config = state.status().config;
+    if !config.enabled || config.token.is_empty() {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        match serve(&app, config.port).await {
+            Ok(shutdown) => {
+                if let Ok(mut server) = app.state::<ApiState>().server.lock() {
+                    *server = Some(shutdown);
+                }
+            }
+            Err(e) => eprintln!("[api] failed to start: {e}"),
+        }
+    });
+}
+
+/// Turn the server on or off (optionally on a new port) and save the choice.
+pub async fn set_enabled(
+    app: &AppHandle,
+    enabled: bool,
+    port: Option<u16>,
+) -> Result<ApiStatus, AdbError> {
+    let state = app.state::<ApiState>();
+    let mut config = state.status().config;
+    config.enabled = enabled;
+    if let Some(port) = port {
+        if port == 0 {
+            return Err(AdbError::InvalidConfig("api port 0".to_string()));
+        }
+        config.port = port;
+    }
+    if config.token.is_empty() {
+        config.token = new_token()?;
+    }
+
+    state.stop();
+    if enabled {
+        let shutdown = serve(app, config.port).await?;
+        if let Ok(mut server) = state.server.lock() {
+            *server = Some(shutdown);
+        }
+    }
+    storage::write(CONFIG_FILE, &config)?;
+    if let Ok(mut current) = state.config.lock() {
+        *current = config;
+    }
+    Ok(state.status())
+}
+
+/// Replace the token. Takes effect for the next request; open WebSockets stay up.
+pub fn regenerate_token(state: &ApiState) -> Result<ApiStatus, AdbError> {
+    let mut config = state.status().config;
+    config.token = new_token()?;
+    storage::write(CONFIG_FILE, &config)?;
+    if let Ok(mut current) = state.config.lock() {
+        *current = config;
+    }
+    Ok(state.status())
+}
+
+#[derive(Clone)]
+struct ApiContext {
+    app: AppHandle,
+    shutdown: CancellationToken,
+}
+
+/// Bind 127.0.0.1:`port` and serve until the returned token is cancelled.
+async fn serve(app: &AppHandle, port: u16) -> Result<CancellationToken, AdbError> {
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .await
+        .map_err(|e| AdbError::InvalidConfig(format!("bind 127.0.0.1:{}: {}", port, e)))?;
+    let shutdown = CancellationToken::new();
+    let ctx = ApiContext {
+        app: app.clone(),
+        shutdown: shutdown.clone(),
+    };
+    let router = Router::new()
+        .route("/rpc", post(rpc))
+        .route("/logcat", get(logcat_socket))
+        .with_state(ctx);
+    let stop = shutdown.clone();
+    tauri::async_runtime::spawn(async move {
+        let server = axum::serve(listener, router).with_graceful_shutdown(stop.cancelled_owned());
+        if let Err(e) = server.await {
+            eprintln!("[api] server stopped: {e}");
+        }
+    });
+    Ok(shutdown)
+}
+
+// ---------------------------------------------------------------------------
+// Authentication
+// ---------------------------------------------------------------------------
+
+/// Bearer header or `?token=`, compared in constant time. An empty expected
+/// token (never enabled) rejects everything.
+fn authorized(expected: &str, headers: &HeaderMap, query_token: Option<&str>) -> bool {
+    if expected.is_empty() {
+        return false;
+    }
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let Some(given) = bearer.or(query_token) else {
+        return false;
+    };
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// `authorized` against the current token.
+fn token_ok(ctx: &ApiContext, headers: &HeaderMap, query_token: Option<&str>) -> bool {
+    let expected = ctx.app.state::<ApiState>().status().config.token;
+    authorized(&expected, headers, query_token)
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, "invalid or missing token").into_response()
+}
+
+// ---------------------------------------------------------------------------
+// JSON-RPC
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug)]
+enum RpcError {
+    MethodNotFound(String),
+    InvalidParams(String),
+    Command(AdbError),
+}
+
+impl From<AdbError> for RpcError {
+    fn from(e: AdbError) -> Self {
+        RpcError::Command(e)
+    }
+}
+
+impl RpcError {
+    fn to_json(&self) -> Value {
+        match self {
+            RpcError::MethodNotFound(method) => {
+                json!({ "code": METHOD_NOT_FOUND, "message": format!("unknown method {}", method) })
+            }
+            RpcError::InvalidParams(detail) => {
+                json!({ "code": INVALID_PARAMS, "message": detail })
+            }
+            RpcError::Command(e) => {
+                json!({ "code": COMMAND_ERROR, "message": e.message(), "data": e })
+            }
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            RpcError::MethodNotFound(method) => format!("unknown method {}", method),
+            RpcError::InvalidParams(detail) => detail.clone(),
+            RpcError::Command(e) => e.to_string(),
+        }
+    }
+}
+
+fn rpc_response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": e.to_json() }),
+    }
+}
+
+async fn rpc(State(ctx): State<ApiContext>, headers: HeaderMap, body: Bytes) -> Response {
+    if !token_ok(&ctx, &headers, None) {
+        return unauthorized();
+    }
+    let request: RpcRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = json!({ "code": PARSE_ERROR, "message": e.to_string() });
+            return Json(json!({ "jsonrpc": "2.0", "id": null, "error": error })).into_response();
+        }
+    };
+
+    let (result, logged) =
+        op_log::with_source(SOURCE, dispatch(&ctx.app, &request.method, &request.params)).await;
+    if !logged {
+        let state = ctx.app.state::<OpLogState>();
+        op_log::add_entry(
+            &state,
+            call_entry(&request.method, &request.params, &result),
+        );
+    }
+    Json(rpc_response(request.id, result)).into_response()
+}
+
+/// Entry for a call whose command keeps no op log of its own.
+fn call_entry(method: &str, params: &Value, result: &Result<Value, RpcError>) -> OpLogEntry {
+    OpLogEntry {
+        error_message: result.as_ref().err().map(RpcError::detail),
+        command: Some(format!("POST /rpc {} {}", method, params)),
+        source: Some(SOURCE.to_string()),
+        ..OpLogEntry::new(
+            method,
+            params
+                .get("serial")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+            format!("API 调用 {}", method),
+            result.is_ok(),
+        )
+    }
+}
+
+/// Named param, deserialized; a missing one reads as `null` (fine for `Option`).
+fn arg<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    serde_json::from_value(params.get(name).cloned().unwrap_or(Value::Null))
+        .map_err(|e| RpcError::InvalidParams(format!("{}: {}", name, e)))
+}
+
+fn reply<T: Serialize>(result: Result<T, AdbError>) -> Result<Value, RpcError> {
+    let value = result?;
+    serde_json::to_value(value).map_err(|e| RpcError::Command(AdbError::Io(e.to_string())))
+}
+
+/// Call the Tauri command named `method`. Left out on purpose:
+/// - this server's own lifecycle and token (`get_api_status`,
+///   `set_api_enabled`, `regenerate_api_token`), so a script cannot lock
+///   itself or the user out;
+/// - app-wide configuration: adb settings and timeouts (`get/set_adb_settings`,
+///   `get/set_timeouts`) and `clear_op_logs`, which stay with the user;
+/// - app updates and `restart_app`;
+/// - the event-based `start_logcat`/`stop_logcat`; use `GET /logcat` instead.
+async fn dispatch(app: &AppHandle, method: &str, p: &Value) -> Result<Value, RpcError> {
+    let a = app.clone();
+    match method {
+        "adb_version" => reply(crate::adb_version(a).await),
+        "get_devices" => reply(crate::get_devices(a, app.state()).await),
+        "get_device_detail" => reply(
+            crate::get_device_detail(
+                a,
+                app.state(),
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "install_apk" => reply(
+            crate::install_apk(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "apkPath")?,
+                arg::<Option<Vec<String>>>(p, "flags")?.unwrap_or_default(),
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "uninstall_app" => reply(
+            crate::uninstall_app(a, app.state(), arg(p, "serial")?, arg(p, "packageName")?).await,
+        ),
+        "get_packages" => reply(
+            crate::get_packages(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg::<Option<bool>>(p, "includeSystem")?.unwrap_or(false),
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "clear_app_data" => reply(
+            crate::clear_app_data(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "packageName")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "force_stop" => reply(
+            crate::force_stop(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "packageName")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "launch_app" => reply(
+            crate::launch_app(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "packageName")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "take_screenshot" => reply(
+            crate::take_screenshot(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "localPath")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "push_file" => reply(
+            crate::push_file(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "localPath")?,
+                arg(p, "remotePath")?,
+                arg(p, "transferId")?,
+            )
+            .await,
+        ),
+        "pull_file" => reply(
+            crate::pull_file(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "remotePath")?,
+                arg(p, "localPath")?,
+                arg(p, "transferId")?,
+            )
+            .await,
+        ),
+        "stat_remote_file" => {
+            reply(crate::stat_remote_file(a, arg(p, "serial")?, arg(p, "remotePath")?).await)
+        }
+        "list_remote_dir" => {
+            reply(crate::list_remote_dir(a, arg(p, "serial")?, arg(p, "remoteDir")?).await)
+        }
+        "list_remote_files" => reply(
+            crate::list_remote_files(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "remoteDir")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "delete_remote_file" => reply(
+            crate::delete_remote_file(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "remotePath")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "connect_wifi" => reply(
+            crate::connect_wifi(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "address")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "disconnect_wifi" => reply(
+            crate::disconnect_wifi(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "address")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "pair_wifi" => {
+            reply(crate::pair_wifi(a, app.state(), arg(p, "address")?, arg(p, "code")?).await)
+        }
+        "discover_wireless_devices" => reply(crate::discover_wireless_devices(a).await),
+        "start_qr_pairing" => reply(crate::start_qr_pairing()),
+        "complete_qr_pairing" => reply(
+            crate::complete_qr_pairing(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serviceName")?,
+                arg(p, "password")?,
+                arg(p, "timeoutSecs")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "wifi_handoff" => reply(
+            crate::wifi_handoff(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "port")?,
+                arg(p, "timeoutSecs")?,
+            )
+            .await,
+        ),
+        "list_wireless_endpoints" => reply(Ok(crate::list_wireless_endpoints(app.state()))),
+        "save_wireless_endpoint" => reply(crate::save_wireless_endpoint(
+            app.state(),
+            arg(p, "endpoint")?,
+        )),
+        "remove_wireless_endpoint" => reply(crate::remove_wireless_endpoint(
+            app.state(),
+            arg(p, "address")?,
+        )),
+        "reconnect_wireless_endpoints" => reply(Ok(crate::reconnect_wireless_endpoints(a).await)),
+        "list_port_rules" => reply(crate::list_port_rules(a, arg(p, "serial")?).await),
+        "add_port_rule" => reply(
+            crate::add_port_rule(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "rule")?,
+            )
+            .await,
+        ),
+        "remove_port_rule" => reply(
+            crate::remove_port_rule(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "rule")?,
+            )
+            .await,
+        ),
+        "remove_all_port_rules" => reply(
+            crate::remove_all_port_rules(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "direction")?,
+            )
+            .await,
+        ),
+        "get_port_presets" => reply(Ok(crate::get_port_presets(app.state()))),
+        "set_port_preset" => reply(crate::set_port_preset(
+            app.state(),
+            arg(p, "serial")?,
+            arg(p, "rules")?,
+        )),
+        "list_device_settings" => {
+            reply(crate::list_device_settings(a, arg(p, "serial")?, arg(p, "namespace")?).await)
+        }
+        "get_device_setting" => reply(
+            crate::get_device_setting(a, arg(p, "serial")?, arg(p, "namespace")?, arg(p, "key")?)
+                .await,
+        ),
+        "set_device_setting" => reply(
+            crate::set_device_setting(
+                a,
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "namespace")?,
+                arg(p, "key")?,
+                arg(p, "value")?,
+            )
+            .await,
+        ),
+        "pending_setting_reverts" => reply(Ok(crate::pending_setting_reverts(
+            app.state(),
+            arg(p, "serial")?,
+        ))),
+        "revert_device_settings" => {
+            reply(crate::revert_device_settings(a, app.state(), arg(p, "serial")?).await)
+        }
+        "list_dev_presets" => reply(crate::list_dev_presets()),
+        "get_applied_dev_presets" => reply(Ok(crate::get_applied_dev_presets(
+            app.state(),
+            arg(p, "serial")?,
+        ))),
+        "apply_dev_preset" => reply(
+            crate::apply_dev_preset(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "name")?,
+            )
+            .await,
+        ),
+        "revert_dev_preset" => reply(
+            crate::revert_dev_preset(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "name")?,
+            )
+            .await,
+        ),
+        "emulator_console" => {
+            reply(crate::emulator_console(app.state(), arg(p, "serial")?, arg(p, "command")?).await)
+        }
+        "list_emulator_snapshots" => reply(crate::list_emulator_snapshots(arg(p, "serial")?).await),
+        "kill_server" => reply(crate::kill_server(a).await),
+        "start_server" => reply(crate::start_server(a).await),
+        "reboot_device" => reply(
+            crate::reboot_device(
+                a,
+                app.state(),
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "mode")?,
+                arg(p, "timeoutSecs")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "wait_for_boot" => reply(
+            crate::wait_for_boot(
+                a,
+                app.state(),
+                arg(p, "serial")?,
+                arg(p, "timeoutSecs")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "get_device_labels" => reply(Ok(crate::get_device_labels(app.state()))),
+        "set_device_label" => reply(crate::set_device_label(
+            app.state(),
+            arg(p, "serial")?,
+            arg(p, "label")?,
+        )),
+        "remove_device_label" => reply(crate::remove_device_label(app.state(), arg(p, "serial")?)),
+        "get_health_config" => reply(Ok(crate::get_health_config(app.state()))),
+        "set_health_config" => reply(crate::set_health_config(a, arg(p, "config")?)),
+        "get_health_history" => reply(Ok(crate::get_health_history(
+            app.state(),
+            arg(p, "serial")?,
+        ))),
+        "get_device_props" => reply(crate::get_device_props(a, arg(p, "serial")?).await),
+        "diff_device_props" => {
+            reply(crate::diff_device_props(a, arg(p, "left")?, arg(p, "right")?).await)
+        }
+        "save_prop_snapshot" => reply(
+            crate::save_prop_snapshot(a, app.state(), arg(p, "serial")?, arg(p, "name")?).await,
+        ),
+        "list_prop_snapshots" => reply(Ok(crate::list_prop_snapshots())),
+        "delete_prop_snapshot" => reply(crate::delete_prop_snapshot(arg(p, "name")?)),
+        "broadcast_install_apk" => reply(
+            crate::broadcast_install_apk(
+                a,
+                app.state(),
+                arg(p, "serials")?,
+                arg(p, "apkPath")?,
+                arg::<Option<Vec<String>>>(p, "flags")?.unwrap_or_default(),
+                arg(p, "parallelism")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "broadcast_uninstall_app" => reply(
+            crate::broadcast_uninstall_app(
+                a,
+                app.state(),
+                arg(p, "serials")?,
+                arg(p, "packageName")?,
+                arg(p, "parallelism")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "broadcast_clear_app_data" => reply(
+            crate::broadcast_clear_app_data(
+                a,
+                app.state(),
+                arg(p, "serials")?,
+                arg(p, "packageName")?,
+                arg(p, "parallelism")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "broadcast_force_stop" => reply(
+            crate::broadcast_force_stop(
+                a,
+                app.state(),
+                arg(p, "serials")?,
+                arg(p, "packageName")?,
+                arg(p, "parallelism")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "broadcast_launch_app" => reply(
+            crate::broadcast_launch_app(
+                a,
+                app.state(),
+                arg(p, "serials")?,
+                arg(p, "packageName")?,
+                arg(p, "parallelism")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "broadcast_push_file" => reply(
+            crate::broadcast_push_file(
+                a,
+                app.state(),
+                arg(p, "serials")?,
+                arg(p, "localPath")?,
+                arg(p, "remotePath")?,
+                arg(p, "parallelism")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "broadcast_take_screenshot" => reply(
+            crate::broadcast_take_screenshot(
+                a,
+                app.state(),
+                arg(p, "serials")?,
+                arg(p, "localDir")?,
+                arg(p, "parallelism")?,
+                arg(p, "opId")?,
+            )
+            .await,
+        ),
+        "get_op_logs" => reply(Ok(crate::get_op_logs(
+            app.state(),
+            arg(p, "opType")?,
+            arg(p, "device")?,
+        ))),
+        "cancel_operation" => reply(crate::cancel_operation(app.state(), arg(p, "opId")?)),
+        _ => Err(RpcError::MethodNotFound(method.to_string())),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Logcat over WebSocket
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct LogcatQuery {
+    serial: String,
+    /// Space-separated logcat filterspecs, e.g. `MyApp:D *:S`.
+    #[serde(default)]
+    filter: Option<String>,
+    #[serde(default)]
+    token: Option<String>,
+}
+
+async fn logcat_socket(
+    State(ctx): State<ApiContext>,
+    Query(query): Query<LogcatQuery>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if !token_ok(&ctx, &headers, query.token.as_deref()) {
+        return unauthorized();
+    }
+    let filters = match query
+        .filter
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(logcat::parse_filter_spec)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(filters) => filters,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let state = ctx.app.state::<OpLogState>();
+    op_log::add_entry(
+        &state,
+        OpLogEntry {
+            command: Some(format!("GET /logcat {}", filters.join(" "))),
+            source: Some(SOURCE.to_string()),
+            ..OpLogEntry::new("logcat", &query.serial, "API 订阅 logcat", true)
+        },
+    );
+    upgrade.on_upgrade(move |socket| stream_logcat(ctx, query.serial, filters, socket))
+}
+
+/// Forward parsed lines until the client leaves, the device closes the
+/// stream or the server shuts down. Like the app's own stream, an
+/// unreachable server falls back to an adb process. Failures to open are
+/// sent as `{ "error": AdbError }` before closing.
+async fn stream_logcat(
+    ctx: ApiContext,
+    serial: String,
+    filters: Vec<String>,
+    mut socket: WebSocket,
+) {
+    let opened = LogcatStream::open(&AppTransport::new(&ctx.app), &serial, false, &filters).await;
+    let mut stream = match opened {
+        Ok(stream) => stream,
+        Err(e) => {
+            let _ = socket
+                .send(Message::Text(json!({ "error": e }).to_string().into()))
+                .await;
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            _ = ctx.shutdown.cancelled() => break,
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => {}
+            },
+            line = stream.next_line() => {
+                let Ok(Some(line)) = line else { break };
+                let Some(parsed) = logcat::parse_logcat_line(&line) else { continue };
+                let Ok(text) = serde_json::to_string(&parsed) else { continue };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorized() {
+        let mut headers = HeaderMap::new();
+        assert!(!authorized("secret", &headers, None));
+        assert!(authorized("secret", &headers, Some("secret")));
+        assert!(!authorized("secret", &headers, Some("secreT")));
+        assert!(!authorized("secret", &headers, Some("secret2")));
+
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert!(authorized("secret", &headers, None));
+        // Never enabled: no token matches, not even an empty one
+        assert!(!authorized("", &headers, Some("")));
+    }
+
+    #[test]
+    fn test_rpc_response_shape() {
+        let ok = rpc_response(json!(7), Ok(json!(["PIXEL7"])));
+        assert_eq!(
+            ok,
+            json!({ "jsonrpc": "2.0", "id": 7, "result": ["PIXEL7"] })
+        );
+
+        let err = rpc_response(
+            json!("a"),
+            Err(RpcError::Command(AdbError::DeviceNotFound("X".to_string()))),
+        );
+        assert_eq!(err["error"]["code"], COMMAND_ERROR);
+        assert_eq!(err["error"]["data"]["code"], "DEVICE_NOT_FOUND");
+
+        let missing = arg::<String>(&json!({ "serial": 5 }), "serial").unwrap_err();
+        assert_eq!(
+            rpc_response(Value::Null, Err(missing))["error"]["code"],
+            INVALID_PARAMS
+        );
+        assert_eq!(arg::<Option<String>>(&json!({}), "opId").unwrap(), None);
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: ApiConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config, ApiConfig::default());
+        assert!(!config.enabled);
+        assert_eq!(config.port, DEFAULT_PORT);
+
+        let token = new_token().unwrap();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, new_token().unwrap());
+    }
+}
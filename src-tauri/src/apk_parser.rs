@@ -1,18 +1,20 @@
 use std::io::Read;
 
+use crate::error::AdbError;
+
 /// Extract the package name from an APK file by parsing AndroidManifest.xml binary XML.
-pub fn extract_package_name(apk_path: &str) -> Result<String, String> {
+pub fn extract_package_name(apk_path: &str) -> Result<String, AdbError> {
     let file =
-        std::fs::File::open(apk_path).map_err(|e| format!("无法打开 APK: {}", e))?;
-    let mut archive =
-        zip::ZipArchive::new(file).map_err(|e| format!("无效的 APK 文件: {}", e))?;
+        std::fs::File::open(apk_path).map_err(|e| AdbError::Io(format!("无法打开 APK: {}", e)))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| AdbError::InvalidApk(format!("无效的 APK 文件: {}", e)))?;
     let mut manifest = archive
         .by_name("AndroidManifest.xml")
-        .map_err(|_| "APK 中未找到 AndroidManifest.xml".to_string())?;
+        .map_err(|_| AdbError::InvalidApk("APK 中未找到 AndroidManifest.xml".to_string()))?;
     let mut buf = Vec::new();
     manifest
         .read_to_end(&mut buf)
-        .map_err(|e| format!("读取 Manifest 失败: {}", e))?;
+        .map_err(|e| AdbError::InvalidApk(format!("读取 Manifest 失败: {}", e)))?;
     parse_package_name(&buf)
 }
 
@@ -30,7 +32,7 @@ fn read_u32(d: &[u8], o: usize) -> u32 {
 }
 
 /// Parse the string pool chunk and return all strings.
-fn parse_string_pool(data: &[u8], cs: usize) -> Result<Vec<String>, String> {
+fn parse_string_pool(data: &[u8], cs: usize) -> Result<Vec<String>, AdbError> {
     let string_count = read_u32(data, cs + 8) as usize;
     let flags = read_u32(data, cs + 16);
     let strings_start = read_u32(data, cs + 20) as usize;
@@ -85,14 +87,14 @@ fn parse_string_pool(data: &[u8], cs: usize) -> Result<Vec<String>, String> {
 }
 
 /// Walk the binary XML to find `<manifest package="...">` and return the value.
-fn parse_package_name(data: &[u8]) -> Result<String, String> {
+fn parse_package_name(data: &[u8]) -> Result<String, AdbError> {
     if data.len() < 8 || read_u32(data, 0) != 0x0008_0003 {
-        return Err("非二进制 XML 格式".into());
+        return Err(AdbError::InvalidApk("非二进制 XML 格式".to_string()));
     }
 
     // String pool is the first chunk (right after the 8-byte file header)
     if read_u16(data, 8) != 0x0001 {
-        return Err("未找到字符串池".into());
+        return Err(AdbError::InvalidApk("未找到字符串池".to_string()));
     }
     let sp_chunk_size = read_u32(data, 12) as usize;
     let strings = parse_string_pool(data, 8)?;
@@ -131,10 +133,12 @@ fn parse_package_name(data: &[u8]) -> Result<String, String> {
                         }
                     }
                 }
-                return Err("manifest 元素未找到 package 属性".into());
+                return Err(AdbError::InvalidApk(
+                    "manifest 元素未找到 package 属性".to_string(),
+                ));
             }
         }
         pos += chunk_size;
     }
-    Err("未找到 manifest 元素".into())
+    Err(AdbError::InvalidApk("未找到 manifest 元素".to_string()))
 }
@@ -0,0 +1,3 @@
+fn main() {
+    std::process::exit(adb_tool_lib::cli::main())
+}
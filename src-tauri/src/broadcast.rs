@@ -0,0 +1,468 @@
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri::Emitter;
+use tauri::Manager;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::adb;
+use crate::apk_parser;
+use crate::device_registry;
+use crate::error::AdbError;
+use crate::op_log;
+use crate::operation;
+use crate::transport::AppTransport;
+
+// ---------------------------------------------------------------------------
+// Multi-device broadcast
+//
+// Runs the same operation on a list of devices, at most `parallelism` at a
+// time. Every device reports `broadcast-progress` events as it moves from
+// queued to running to done, gets its own op log entry, and ends up as one
+// row of the returned result table. A failing device never stops the others.
+// ---------------------------------------------------------------------------
+
+pub const DEFAULT_PARALLELISM: usize = 4;
+
+/// Operation to run on every device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BroadcastOp {
+    Install {
+        apk_path: String,
+        flags: Vec<String>,
+    },
+    Uninstall {
+        package_name: String,
+    },
+    ClearAppData {
+        package_name: String,
+    },
+    ForceStop {
+        package_name: String,
+    },
+    LaunchApp {
+        package_name: String,
+    },
+    PushFile {
+        local_path: String,
+        remote_path: String,
+    },
+    /// One PNG per device, named after its serial, in `local_dir`.
+    Screenshot {
+        local_dir: String,
+    },
+}
+
+/// Payload for the `broadcast-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastProgress {
+    pub broadcast_id: String,
+    pub serial: String,
+    pub status: String, // "queued" | "running" | "success" | "failed"
+    /// Devices finished so far, including this one.
+    pub completed: usize,
+    pub total: usize,
+    pub error: Option<AdbError>,
+}
+
+/// One row of the result table.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceResult {
+    pub serial: String,
+    pub success: bool,
+    pub output: String,
+    pub error: Option<AdbError>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastResult {
+    pub broadcast_id: String,
+    /// In the order the serials were given.
+    pub results: Vec<DeviceResult>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+// ---------------------------------------------------------------------------
+// Scheduling
+// ---------------------------------------------------------------------------
+
+/// Run `run(serial)` for every serial, at most `parallelism` at a time, and
+/// report each state change through `report`. Cancelling `cancel` fails the
+/// devices that are still queued or running with `AdbError::Cancelled`.
+pub async fn run_concurrent<F, Fut>(
+    broadcast_id: &str,
+    serials: &[String],
+    parallelism: usize,
+    cancel: &CancellationToken,
+    report: Arc<dyn Fn(BroadcastProgress) + Send + Sync>,
+    run: F,
+) -> BroadcastResult
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<String, AdbError>> + Send + 'static,
+{
+    let mut unique: Vec<String> = Vec::new();
+    for serial in serials {
+        if !unique.contains(serial) {
+            unique.push(serial.clone());
+        }
+    }
+    let total = unique.len();
+    let progress =
+        |serial: &str, status: &str, completed: usize, error: Option<AdbError>| BroadcastProgress {
+            broadcast_id: broadcast_id.to_string(),
+            serial: serial.to_string(),
+            status: status.to_string(),
+            completed,
+            total,
+            error,
+        };
+
+    for serial in &unique {
+        report(progress(serial, "queued", 0, None));
+    }
+
+    let semaphore = Arc::new(Semaphore::new(parallelism.max(1)));
+    let mut tasks = JoinSet::new();
+    for (index, serial) in unique.iter().enumerate() {
+        let fut = run(serial.clone());
+        let semaphore = semaphore.clone();
+        let cancel = cancel.clone();
+        let report = report.clone();
+        let running = progress(serial, "running", 0, None);
+        tasks.spawn(async move {
+            let permit = operation::cancellable(&cancel, async {
+                semaphore
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| AdbError::InvalidState(e.to_string()))
+            })
+            .await;
+            // Time spent waiting for a slot does not count
+            let started = Instant::now();
+            let result = match permit {
+                Ok(_permit) => {
+                    report(running);
+                    operation::cancellable(&cancel, fut).await
+                }
+                Err(e) => Err(e),
+            };
+            (index, result, started.elapsed().as_millis() as u64)
+        });
+    }
+
+    let mut rows: Vec<Option<DeviceResult>> = vec![None; total];
+    let mut completed = 0;
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((index, result, duration_ms)) = joined else {
+            continue;
+        };
+        completed += 1;
+        let serial = &unique[index];
+        let row = match result {
+            Ok(output) => {
+                report(progress(serial, "success", completed, None));
+                DeviceResult {
+                    serial: serial.clone(),
+                    success: true,
+                    output,
+                    error: None,
+                    duration_ms,
+                }
+            }
+            Err(e) => {
+                report(progress(serial, "failed", completed, Some(e.clone())));
+                DeviceResult {
+                    serial: serial.clone(),
+                    success: false,
+                    output: e.detail(),
+                    error: Some(e),
+                    duration_ms,
+                }
+            }
+        };
+        rows[index] = Some(row);
+    }
+
+    // A panicked task leaves a hole; report it rather than dropping the device
+    let results: Vec<DeviceResult> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(index, row)| {
+            row.unwrap_or_else(|| DeviceResult {
+                serial: unique[index].clone(),
+                success: false,
+                output: String::new(),
+                error: Some(AdbError::InvalidState("任务异常退出".to_string())),
+                duration_ms: 0,
+            })
+        })
+        .collect();
+    let succeeded = results.iter().filter(|r| r.success).count();
+    BroadcastResult {
+        broadcast_id: broadcast_id.to_string(),
+        failed: results.len() - succeeded,
+        results,
+        succeeded,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Operations
+// ---------------------------------------------------------------------------
+
+/// Run `op` on every device, emitting `broadcast-progress` events and writing
+/// one op log entry per device.
+pub async fn run(
+    app: &AppHandle,
+    broadcast_id: &str,
+    serials: &[String],
+    op: BroadcastOp,
+    parallelism: usize,
+    cancel: &CancellationToken,
+) -> BroadcastResult {
+    let emitter = app.clone();
+    let report: Arc<dyn Fn(BroadcastProgress) + Send + Sync> = Arc::new(move |progress| {
+        let _ = emitter.emit("broadcast-progress", &progress);
+    });
+
+    // Resolved once for all devices
+    let package_to_replace = match &op {
+        BroadcastOp::Install { apk_path, .. } => apk_parser::extract_package_name(apk_path).ok(),
+        _ => None,
+    };
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+
+    let result = run_concurrent(
+        broadcast_id,
+        serials,
+        parallelism,
+        cancel,
+        report,
+        |serial| {
+            let app = app.clone();
+            let op = op.clone();
+            let package_to_replace = package_to_replace.clone();
+            let transfer_id = format!("{}-{}", broadcast_id, serial);
+            let screenshot_name = format!(
+                "screenshot_{}_{}.png",
+                device_registry::file_safe(&serial),
+                timestamp
+            );
+            let cancel = cancel.clone();
+            async move {
+                let adb = AppTransport::new(&app);
+                match &op {
+                    BroadcastOp::Install { apk_path, flags } => {
+                        if let Some(package_name) = &package_to_replace {
+                            let _ = adb::uninstall_app(&adb, &serial, package_name).await;
+                        }
+                        let flags: Vec<&str> = flags.iter().map(String::as_str).collect();
+                        let result = adb::install_apk(&adb, &serial, apk_path, &flags).await?;
+                        match result.error_code {
+                            Some(code) if !result.success => Err(AdbError::InstallFailure { code }),
+                            _ => Ok(result.raw_output),
+                        }
+                    }
+                    BroadcastOp::Uninstall { package_name } => {
+                        adb::uninstall_app(&adb, &serial, package_name).await
+                    }
+                    BroadcastOp::ClearAppData { package_name } => {
+                        adb::clear_app_data(&adb, &serial, package_name).await
+                    }
+                    BroadcastOp::ForceStop { package_name } => {
+                        adb::force_stop_app(&adb, &serial, package_name).await
+                    }
+                    BroadcastOp::LaunchApp { package_name } => {
+                        adb::launch_app(&adb, &serial, package_name).await
+                    }
+                    BroadcastOp::PushFile {
+                        local_path,
+                        remote_path,
+                    } => {
+                        let transfer = adb::push_file(
+                            &adb,
+                            &serial,
+                            local_path,
+                            remote_path,
+                            &transfer_id,
+                            &cancel,
+                            adb::emit_progress(&app),
+                        )
+                        .await?;
+                        Ok(format!("{} bytes", transfer.bytes))
+                    }
+                    BroadcastOp::Screenshot { local_dir } => {
+                        let local_path = Path::new(local_dir).join(&screenshot_name);
+                        adb::screenshot(&adb, &serial, &local_path.to_string_lossy()).await
+                    }
+                }
+            }
+        },
+    )
+    .await;
+
+    if let Some(state) = app.try_state::<op_log::OpLogState>() {
+        for row in &result.results {
+            op_log::add_entry(&state, log_entry(&op, row));
+        }
+    }
+    result
+}
+
+/// Op log entry for one device, in the same shape the single-device commands write.
+fn log_entry(op: &BroadcastOp, row: &DeviceResult) -> op_log::OpLogEntry {
+    let serial = &row.serial;
+    let (op_type, detail, command) = match op {
+        BroadcastOp::Install { apk_path, flags } => {
+            let file_name = adb::file_name(apk_path);
+            let flags = flags.iter().map(|f| format!("{} ", f)).collect::<String>();
+            (
+                "install",
+                format!("批量安装 {}", file_name),
+                format!("adb -s {} install {}{}", serial, flags, file_name),
+            )
+        }
+        BroadcastOp::Uninstall { package_name } => (
+            "uninstall",
+            format!("批量卸载 {}", package_name),
+            format!("adb -s {} uninstall {}", serial, package_name),
+        ),
+        BroadcastOp::ClearAppData { package_name } => (
+            "clear_data",
+            format!("批量清除数据 {}", package_name),
+            format!("adb -s {} shell pm clear {}", serial, package_name),
+        ),
+        BroadcastOp::ForceStop { package_name } => (
+            "force_stop",
+            format!("批量强制停止 {}", package_name),
+            format!("adb -s {} shell am force-stop {}", serial, package_name),
+        ),
+        BroadcastOp::LaunchApp { package_name } => (
+            "launch",
+            format!("批量启动 {}", package_name),
+            format!("adb -s {} shell monkey -p {} 1", serial, package_name),
+        ),
+        BroadcastOp::PushFile {
+            local_path,
+            remote_path,
+        } => (
+            "upload",
+            format!("批量上传 {} → {}", adb::file_name(local_path), remote_path),
+            format!("adb -s {} push {} {}", serial, local_path, remote_path),
+        ),
+        BroadcastOp::Screenshot { local_dir } => (
+            "screenshot",
+            format!("批量截图 → {}", local_dir),
+            format!("adb -s {} exec-out screencap -p", serial),
+        ),
+    };
+    op_log::OpLogEntry {
+        error_message: row.error.as_ref().map(|e| e.to_string()),
+        command: Some(command),
+        raw_output: Some(row.output.clone()),
+        duration_ms: Some(row.duration_ms),
+        ..op_log::OpLogEntry::new(op_type, serial, detail, row.success)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    fn serials(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_parallelism_limit_and_result_order() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let report: Arc<dyn Fn(BroadcastProgress) + Send + Sync> = Arc::new(move |p| {
+            sink.lock().unwrap().push((p.serial, p.status));
+        });
+
+        let result = run_concurrent(
+            "b1",
+            &serials(&["A", "B", "C", "D", "E", "A"]),
+            2,
+            &CancellationToken::new(),
+            report,
+            |serial| {
+                let running = running.clone();
+                let peak = peak.clone();
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    if serial == "C" {
+                        Err(AdbError::Offline("device offline".to_string()))
+                    } else {
+                        Ok(format!("ok {}", serial))
+                    }
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        let order: Vec<&str> = result.results.iter().map(|r| r.serial.as_str()).collect();
+        assert_eq!(order, ["A", "B", "C", "D", "E"]);
+        assert_eq!((result.succeeded, result.failed), (4, 1));
+        assert_eq!(result.results[2].error.as_ref().unwrap().code(), "OFFLINE");
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.iter().filter(|(_, s)| s == "queued").count(), 5);
+        assert_eq!(events.iter().filter(|(_, s)| s == "running").count(), 5);
+        assert!(events.contains(&("C".to_string(), "failed".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_fails_remaining_devices() {
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        let report: Arc<dyn Fn(BroadcastProgress) + Send + Sync> = Arc::new(|_| {});
+        let result = run_concurrent(
+            "b2",
+            &serials(&["A", "B", "C"]),
+            1,
+            &cancel,
+            report,
+            |serial| {
+                let trigger = trigger.clone();
+                async move {
+                    if serial == "A" {
+                        trigger.cancel();
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                    }
+                    Ok(serial)
+                }
+            },
+        )
+        .await;
+        assert_eq!(result.succeeded, 0);
+        assert!(result
+            .results
+            .iter()
+            .all(|r| r.error == Some(AdbError::Cancelled)));
+    }
+}
@@ -0,0 +1,428 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+use crate::adb::{self, AdbDevice};
+use crate::apk_parser;
+use crate::device_registry::{self, DeviceRegistryState};
+use crate::error::AdbError;
+use crate::logcat::{self, LogcatStream};
+use crate::op_log::{self, OpLogEntry, OpLogState};
+use crate::operation::Timeouts;
+use crate::settings;
+use crate::transport::AppTransport;
+
+// ---------------------------------------------------------------------------
+// Headless command line (`adb-tool-cli`)
+//
+// Same adb layer, install error translation, logcat parser and op history as
+// the GUI, for machines without a display. Uses the saved adb settings and
+// writes to the same `~/AdbTool/op_history.json`.
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Parser)]
+#[command(name = "adb-tool-cli", version, about = "ADB Tool without the GUI")]
+pub struct Cli {
+    /// Target device serial; defaults to the only connected device
+    #[arg(short, long, global = true)]
+    pub serial: Option<String>,
+
+    /// Print JSON instead of text (one object per line for logcat)
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: CliCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// List connected devices
+    Devices,
+    /// Install an APK
+    Install {
+        apk: String,
+        /// Replace the existing app (adb install -r)
+        #[arg(short, long)]
+        replace: bool,
+        /// Allow a version downgrade (adb install -d)
+        #[arg(short, long)]
+        downgrade: bool,
+        /// Grant all runtime permissions (adb install -g)
+        #[arg(short, long)]
+        grant: bool,
+        /// Allow test-only APKs (adb install -t)
+        #[arg(short, long)]
+        test: bool,
+        /// Uninstall the installed package first, like the GUI does (drops its data)
+        #[arg(long)]
+        clean: bool,
+    },
+    /// Print the device log
+    Logcat {
+        /// logcat filterspecs such as `MyApp:D *:S`
+        #[arg(long, value_delimiter = ' ', value_parser = parse_filter)]
+        filter: Vec<String>,
+        /// Dump the current buffer and exit instead of following it
+        #[arg(short, long)]
+        dump: bool,
+    },
+    /// Save a screenshot as PNG
+    Screenshot {
+        /// Output file; defaults to screenshot_{serial}_{time}.png
+        output: Option<PathBuf>,
+    },
+    /// Show the operation history (filtered by --serial when given)
+    History {
+        /// Only entries of this type, e.g. install or screenshot
+        #[arg(long = "type")]
+        op_type: Option<String>,
+        /// Only the most recent N entries
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+}
+
+fn parse_filter(spec: &str) -> Result<String, String> {
+    logcat::parse_filter_spec(spec).map_err(|e| e.detail())
+}
+
+/// Entry point of the `adb-tool-cli` binary; returns the process exit code.
+pub fn main() -> i32 {
+    let cli = Cli::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("failed to start runtime: {e}");
+            return 1;
+        }
+    };
+    let json = cli.json;
+    match runtime.block_on(run(cli)) {
+        Ok(code) => code,
+        Err(e) => {
+            if json {
+                print_json(&e);
+            } else {
+                eprintln!("错误: {}", e);
+            }
+            1
+        }
+    }
+}
+
+struct Context {
+    adb: AppTransport,
+    serial: Option<String>,
+    json: bool,
+}
+
+impl Context {
+    /// `--serial`, or the only device that is online.
+    async fn serial(&self) -> Result<String, AdbError> {
+        if let Some(serial) = &self.serial {
+            return Ok(serial.clone());
+        }
+        pick_device(&adb::list_devices(&self.adb).await?)
+    }
+}
+
+async fn run(cli: Cli) -> Result<i32, AdbError> {
+    let settings = settings::load();
+    let ctx = Context {
+        adb: AppTransport::headless(&settings, Timeouts::default()),
+        serial: cli.serial,
+        json: cli.json,
+    };
+    match cli.command {
+        CliCommand::Devices => devices(&ctx).await,
+        CliCommand::Install {
+            apk,
+            replace,
+            downgrade,
+            grant,
+            test,
+            clean,
+        } => {
+            let flags: Vec<&str> = [
+                (replace, "-r"),
+                (downgrade, "-d"),
+                (grant, "-g"),
+                (test, "-t"),
+            ]
+            .into_iter()
+            .filter_map(|(on, flag)| on.then_some(flag))
+            .collect();
+            install(&ctx, &apk, &flags, clean).await
+        }
+        CliCommand::Logcat { filter, dump } => logcat(&ctx, &filter, dump).await,
+        CliCommand::Screenshot { output } => screenshot(&ctx, output).await,
+        CliCommand::History { op_type, limit } => history(&ctx, op_type.as_deref(), limit),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Subcommands
+// ---------------------------------------------------------------------------
+
+async fn devices(ctx: &Context) -> Result<i32, AdbError> {
+    let mut devices = adb::list_devices(&ctx.adb).await?;
+    DeviceRegistryState::new().annotate(&mut devices);
+    if ctx.json {
+        print_json(&devices);
+    } else {
+        for device in &devices {
+            let alias = device
+                .label
+                .as_ref()
+                .map(|l| l.alias.as_str())
+                .unwrap_or_default();
+            println!(
+                "{}\t{}\t{}\t{}",
+                device.serial, device.state, device.model, alias
+            );
+        }
+    }
+    Ok(0)
+}
+
+async fn install(
+    ctx: &Context,
+    apk_path: &str,
+    flags: &[&str],
+    clean: bool,
+) -> Result<i32, AdbError> {
+    let serial = ctx.serial().await?;
+    if clean {
+        let package_name = apk_parser::extract_package_name(apk_path)?;
+        let _ = adb::uninstall_app(&ctx.adb, &serial, &package_name).await;
+    }
+    let file_name = adb::file_name(apk_path);
+    let result = adb::install_apk(&ctx.adb, &serial, apk_path, flags).await;
+
+    let cmd = if flags.is_empty() {
+        format!("adb -s {} install {}", serial, file_name)
+    } else {
+        format!(
+            "adb -s {} install {} {}",
+            serial,
+            flags.join(" "),
+            file_name
+        )
+    };
+    let (success, error_message, raw) = match &result {
+        Ok(r) => (r.success, r.error_message_cn.clone(), r.raw_output.clone()),
+        Err(e) => (false, Some(e.to_string()), e.detail()),
+    };
+    log_entry(OpLogEntry {
+        error_message,
+        command: Some(cmd),
+        raw_output: Some(raw),
+        source: Some("cli".to_string()),
+        ..OpLogEntry::new("install", &serial, format!("安装 {}", file_name), success)
+    });
+
+    let result = result?;
+    if ctx.json {
+        print_json(&result);
+    } else if result.success {
+        println!("安装成功: {}", file_name);
+    } else {
+        println!(
+            "安装失败 [{}]: {}",
+            result.error_code.as_deref().unwrap_or("UNKNOWN"),
+            result.error_message_cn.as_deref().unwrap_or_default()
+        );
+        if let Some(suggestion) = &result.suggestion {
+            println!("建议: {}", suggestion);
+        }
+    }
+    Ok(if result.success { 0 } else { 1 })
+}
+
+async fn logcat(ctx: &Context, filters: &[String], dump: bool) -> Result<i32, AdbError> {
+    let serial = ctx.serial().await?;
+    let mut stdout = std::io::stdout().lock();
+    let mut print = |line: String| -> bool {
+        let written = if ctx.json {
+            match logcat::parse_logcat_line(&line) {
+                Some(parsed) => serde_json::to_string(&parsed)
+                    .map_err(std::io::Error::other)
+                    .and_then(|json| writeln!(stdout, "{}", json)),
+                None => Ok(()),
+            }
+        } else {
+            writeln!(stdout, "{}", line)
+        };
+        // Reader went away (`| head`)
+        written.is_ok()
+    };
+
+    let mut stream = LogcatStream::open(&ctx.adb, &serial, dump, filters).await?;
+    while let Some(line) = stream.next_line().await? {
+        if !print(line) {
+            break;
+        }
+    }
+    Ok(0)
+}
+
+async fn screenshot(ctx: &Context, output: Option<PathBuf>) -> Result<i32, AdbError> {
+    let serial = ctx.serial().await?;
+    let local_path = output
+        .unwrap_or_else(|| {
+            PathBuf::from(format!(
+                "screenshot_{}_{}.png",
+                device_registry::file_safe(&serial),
+                chrono::Local::now().format("%Y%m%d_%H%M%S")
+            ))
+        })
+        .to_string_lossy()
+        .to_string();
+
+    let result = adb::screenshot(&ctx.adb, &serial, &local_path).await;
+    let (success, error_message, raw) = match &result {
+        Ok(path) => (true, None, format!("保存至 {}", path)),
+        Err(e) => (false, Some(e.to_string()), e.detail()),
+    };
+    log_entry(OpLogEntry {
+        error_message,
+        command: Some(format!(
+            "adb -s {} exec-out screencap -p > {}",
+            serial, local_path
+        )),
+        raw_output: Some(raw),
+        source: Some("cli".to_string()),
+        ..OpLogEntry::new(
+            "screenshot",
+            &serial,
+            format!("截图 → {}", local_path),
+            success,
+        )
+    });
+
+    let path = result?;
+    if ctx.json {
+        print_json(&serde_json::json!({ "serial": serial, "path": path }));
+    } else {
+        println!("{}", path);
+    }
+    Ok(0)
+}
+
+fn history(ctx: &Context, op_type: Option<&str>, limit: Option<usize>) -> Result<i32, AdbError> {
+    let state = OpLogState::new();
+    let mut entries = op_log::get_entries(&state, op_type, ctx.serial.as_deref());
+    if let Some(limit) = limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+    if ctx.json {
+        print_json(&entries);
+    } else {
+        for entry in &entries {
+            println!(
+                "{}  {}  {:<10}  {}  {}",
+                entry.timestamp,
+                if entry.success { "OK  " } else { "FAIL" },
+                entry.op_type,
+                entry.device,
+                entry.detail
+            );
+        }
+    }
+    Ok(0)
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// The single online device when no serial was given.
+fn pick_device(devices: &[AdbDevice]) -> Result<String, AdbError> {
+    let online: Vec<&AdbDevice> = devices.iter().filter(|d| d.state == "device").collect();
+    match online.as_slice() {
+        [device] => Ok(device.serial.clone()),
+        [] => Err(AdbError::DeviceNotFound("no online device".to_string())),
+        _ => Err(AdbError::MultipleDevices(format!(
+            "{} devices online, pass --serial",
+            online.len()
+        ))),
+    }
+}
+
+/// Append to the shared op history; safe while the GUI is running, see
+/// [`op_log::add_entry`].
+fn log_entry(entry: OpLogEntry) {
+    op_log::add_entry(&OpLogState::new(), entry);
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("serialize: {e}"),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::device;
+
+    #[test]
+    fn test_parse_args() {
+        let cli =
+            Cli::try_parse_from(["adb-tool-cli", "--json", "install", "-rd", "app.apk"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(
+            cli.command,
+            CliCommand::Install {
+                replace: true,
+                downgrade: true,
+                grant: false,
+                clean: false,
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from([
+            "adb-tool-cli",
+            "logcat",
+            "--filter",
+            "MyApp:D *:S",
+            "-s",
+            "PIXEL7",
+        ])
+        .unwrap();
+        assert_eq!(cli.serial.as_deref(), Some("PIXEL7"));
+        match cli.command {
+            CliCommand::Logcat { filter, dump } => {
+                assert_eq!(filter, ["MyApp:D", "*:S"]);
+                assert!(!dump);
+            }
+            other => panic!("{other:?}"),
+        }
+
+        assert!(Cli::try_parse_from(["adb-tool-cli", "logcat", "--filter", "MyApp"]).is_err());
+        assert!(Cli::try_parse_from(["adb-tool-cli", "reboot"]).is_err());
+    }
+
+    #[test]
+    fn test_pick_device() {
+        let devices = vec![device("PIXEL7", "device"), device("LOCKED", "unauthorized")];
+        assert_eq!(pick_device(&devices).unwrap(), "PIXEL7");
+
+        assert!(matches!(pick_device(&[]), Err(AdbError::DeviceNotFound(_))));
+
+        let two = vec![device("A", "device"), device("B", "device")];
+        assert!(matches!(
+            pick_device(&two),
+            Err(AdbError::MultipleDevices(_))
+        ));
+    }
+}
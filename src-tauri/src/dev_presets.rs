@@ -0,0 +1,555 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::device_settings::{self, validate_key, SettingChange, SettingsNamespace};
+use crate::error::AdbError;
+use crate::operation::OpClass;
+use crate::shell;
+use crate::storage;
+use crate::transport::AdbTransport;
+
+// ---------------------------------------------------------------------------
+// Developer-option presets
+//
+// A preset is a list of steps (settings, system properties, broadcasts).
+// Applying one records how to undo every step; if a step fails the steps
+// already done are undone again and the preset's `revert` steps run, so a
+// device never ends up half-configured. Settings written either way are
+// reported one by one, for the op log.
+// Built-in presets can be overridden and extended in
+// `~/AdbTool/dev_presets.json`.
+// ---------------------------------------------------------------------------
+
+/// `SYSPROPS_TRANSACTION`: makes running apps re-read `debug.*` properties,
+/// which is what the developer options screen does after changing them.
+const SYSPROPS_POKE: &str = "service call activity 1599295570";
+
+const DEMO_ACTION: &str = "com.android.systemui.demo";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PresetStep {
+    /// `settings put`, or `settings delete` when `value` is null.
+    Setting {
+        namespace: SettingsNamespace,
+        key: String,
+        value: Option<String>,
+    },
+    /// `setprop`; an empty value clears the property.
+    Prop { key: String, value: String },
+    /// `am broadcast -a <action> -e <key> <value>...`. Not undoable by
+    /// itself; presets list the broadcasts that undo it in `revert`.
+    Broadcast {
+        action: String,
+        #[serde(default)]
+        extras: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DevPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<PresetStep>,
+    /// Extra steps run first on revert, for steps that cannot be undone by
+    /// restoring a value.
+    #[serde(default)]
+    pub revert: Vec<PresetStep>,
+    /// `false` for presets from the built-in list.
+    #[serde(default = "default_true")]
+    pub custom: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A preset applied to a device, with the steps that undo it in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedPreset {
+    pub serial: String,
+    pub name: String,
+    pub applied_at: String,
+    pub undo: Vec<PresetStep>,
+}
+
+// ---------------------------------------------------------------------------
+// Built-in and user presets
+// ---------------------------------------------------------------------------
+
+fn setting(namespace: SettingsNamespace, key: &str, value: &str) -> PresetStep {
+    PresetStep::Setting {
+        namespace,
+        key: key.to_string(),
+        value: Some(value.to_string()),
+    }
+}
+
+fn prop(key: &str, value: &str) -> PresetStep {
+    PresetStep::Prop {
+        key: key.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn demo(extras: &[(&str, &str)]) -> PresetStep {
+    PresetStep::Broadcast {
+        action: DEMO_ACTION.to_string(),
+        extras: extras
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    }
+}
+
+pub fn builtin_presets() -> Vec<DevPreset> {
+    use SettingsNamespace::*;
+    vec![
+        DevPreset {
+            name: "ui_test_mode".to_string(),
+            description: "关闭动画、充电时保持唤醒、跳过沉浸模式提示".to_string(),
+            steps: vec![
+                setting(Global, "window_animation_scale", "0"),
+                setting(Global, "transition_animation_scale", "0"),
+                setting(Global, "animator_duration_scale", "0"),
+                // AC | USB | wireless
+                setting(Global, "stay_on_while_plugged_in", "7"),
+                setting(Secure, "immersive_mode_confirmations", "confirmed"),
+            ],
+            revert: Vec::new(),
+            custom: false,
+        },
+        DevPreset {
+            name: "demo_mode".to_string(),
+            description: "SystemUI 演示模式：固定时间、满格信号和电量、隐藏通知".to_string(),
+            steps: vec![
+                setting(Global, "sysui_demo_allowed", "1"),
+                demo(&[("command", "enter")]),
+                demo(&[("command", "clock"), ("hhmm", "1200")]),
+                demo(&[
+                    ("command", "battery"),
+                    ("level", "100"),
+                    ("plugged", "false"),
+                ]),
+                demo(&[("command", "network"), ("wifi", "show"), ("level", "4")]),
+                demo(&[
+                    ("command", "network"),
+                    ("mobile", "show"),
+                    ("datatype", "none"),
+                    ("level", "4"),
+                ]),
+                demo(&[("command", "notifications"), ("visible", "false")]),
+            ],
+            revert: vec![demo(&[("command", "exit")])],
+            custom: false,
+        },
+        DevPreset {
+            name: "debug_overlay".to_string(),
+            description: "显示触摸、指针位置、布局边界和 GPU 过度绘制".to_string(),
+            steps: vec![
+                setting(System, "show_touches", "1"),
+                setting(System, "pointer_location", "1"),
+                prop("debug.layout", "true"),
+                prop("debug.hwui.overdraw", "show"),
+            ],
+            revert: Vec::new(),
+            custom: false,
+        },
+    ]
+}
+
+/// `~/AdbTool/dev_presets.json`, the user's own presets.
+const PRESETS_FILE: &str = "dev_presets.json";
+
+/// `~/AdbTool/dev_presets_applied.json`; a missing or unreadable file means
+/// nothing applied.
+const APPLIED_FILE: &str = "dev_presets_applied.json";
+
+/// User presets from the config file. Unlike the other config files a
+/// broken file is an error here: it is edited by hand and silently ignoring
+/// it would hide the typo.
+fn load_user_presets() -> Result<Vec<DevPreset>, AdbError> {
+    Ok(storage::read(PRESETS_FILE)?.unwrap_or_default())
+}
+
+/// Built-in presets followed by user presets; a user preset replaces the
+/// built-in one of the same name.
+pub fn list_presets() -> Result<Vec<DevPreset>, AdbError> {
+    let user: Vec<DevPreset> = load_user_presets()?
+        .into_iter()
+        .map(|p| DevPreset { custom: true, ..p })
+        .collect();
+    let mut presets: Vec<DevPreset> = builtin_presets()
+        .into_iter()
+        .filter(|b| !user.iter().any(|u| u.name == b.name))
+        .collect();
+    presets.extend(user);
+    Ok(presets)
+}
+
+pub fn find_preset(name: &str) -> Result<DevPreset, AdbError> {
+    list_presets()?
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| AdbError::InvalidConfig(format!("未知的预设: {}", name)))
+}
+
+// ---------------------------------------------------------------------------
+// Running steps
+// ---------------------------------------------------------------------------
+
+async fn get_prop(adb: &impl AdbTransport, serial: &str, key: &str) -> Result<String, AdbError> {
+    let output = adb
+        .exec_device(serial, &["shell", "getprop", key], OpClass::Fast)
+        .await?;
+    Ok(output.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Run one step and return the step that undoes it, if any. Settings
+/// writes are passed to `on_setting` as (attempted change, result).
+async fn run_step<F>(
+    adb: &impl AdbTransport,
+    serial: &str,
+    step: &PresetStep,
+    on_setting: &mut F,
+) -> Result<Option<PresetStep>, AdbError>
+where
+    F: FnMut(&SettingChange, &Result<SettingChange, AdbError>),
+{
+    match step {
+        PresetStep::Setting {
+            namespace,
+            key,
+            value,
+        } => {
+            let result = device_settings::set(adb, serial, *namespace, key, value.as_deref()).await;
+            let attempted = SettingChange {
+                namespace: *namespace,
+                key: key.clone(),
+                previous: None,
+                value: value.clone(),
+            };
+            on_setting(&attempted, &result);
+            let change = result?;
+            Ok(Some(PresetStep::Setting {
+                namespace: *namespace,
+                key: change.key,
+                value: change.previous,
+            }))
+        }
+        PresetStep::Prop { key, value } => {
+            let key = validate_key(key)?;
+            let previous = get_prop(adb, serial, key).await?;
+            let quoted = shell::quote(value);
+            adb.exec_device(serial, &["shell", "setprop", key, &quoted], OpClass::Fast)
+                .await?;
+            Ok(Some(PresetStep::Prop {
+                key: key.to_string(),
+                value: previous,
+            }))
+        }
+        PresetStep::Broadcast { action, extras } => {
+            let mut args = vec!["shell", "am", "broadcast", "-a", validate_key(action)?];
+            for (key, value) in extras {
+                args.extend(["-e", validate_key(key)?, validate_key(value)?]);
+            }
+            adb.exec_device(serial, &args, OpClass::Fast).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Run `steps` in order and return their undo steps, most recent first.
+/// On failure the steps already run are undone and the error is returned.
+async fn run_steps<F>(
+    adb: &impl AdbTransport,
+    serial: &str,
+    steps: &[PresetStep],
+    on_setting: &mut F,
+) -> Result<Vec<PresetStep>, AdbError>
+where
+    F: FnMut(&SettingChange, &Result<SettingChange, AdbError>),
+{
+    let mut undo = Vec::new();
+    for step in steps {
+        match run_step(adb, serial, step, on_setting).await {
+            Ok(Some(reverse)) => undo.insert(0, reverse),
+            Ok(None) => {}
+            Err(e) => {
+                let _ = run_undo(adb, serial, &undo, on_setting).await;
+                return Err(e);
+            }
+        }
+    }
+    poke_props(adb, serial, steps).await;
+    Ok(undo)
+}
+
+/// Run a preset's steps and return everything that undoes it, its `revert`
+/// steps first. A failed step also runs `revert`, since steps like the demo
+/// mode broadcasts are only undone by it.
+async fn run_preset<F>(
+    adb: &impl AdbTransport,
+    serial: &str,
+    preset: &DevPreset,
+    on_setting: &mut F,
+) -> Result<Vec<PresetStep>, AdbError>
+where
+    F: FnMut(&SettingChange, &Result<SettingChange, AdbError>),
+{
+    match run_steps(adb, serial, &preset.steps, on_setting).await {
+        Ok(undo) => Ok(preset.revert.iter().cloned().chain(undo).collect()),
+        Err(e) => {
+            let _ = run_undo(adb, serial, &preset.revert, on_setting).await;
+            Err(e)
+        }
+    }
+}
+
+/// Best-effort undo: runs every step and returns the ones that failed.
+async fn run_undo<F>(
+    adb: &impl AdbTransport,
+    serial: &str,
+    steps: &[PresetStep],
+    on_setting: &mut F,
+) -> Vec<(PresetStep, AdbError)>
+where
+    F: FnMut(&SettingChange, &Result<SettingChange, AdbError>),
+{
+    let mut failed = Vec::new();
+    for step in steps {
+        if let Err(e) = run_step(adb, serial, step, on_setting).await {
+            failed.push((step.clone(), e));
+        }
+    }
+    poke_props(adb, serial, steps).await;
+    failed
+}
+
+/// Property changes only show once apps are told to reload them.
+async fn poke_props(adb: &impl AdbTransport, serial: &str, steps: &[PresetStep]) {
+    if steps.iter().any(|s| matches!(s, PresetStep::Prop { .. })) {
+        let _ = adb.shell(serial, SYSPROPS_POKE, OpClass::Fast).await;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Applied presets
+// ---------------------------------------------------------------------------
+
+/// Managed state: presets currently applied, across devices.
+pub struct DevPresetState {
+    pub applied: Mutex<Vec<AppliedPreset>>,
+}
+
+impl DevPresetState {
+    /// Create state, pre-loading applied presets from disk.
+    pub fn new() -> Self {
+        Self {
+            applied: Mutex::new(storage::load_or_default(APPLIED_FILE)),
+        }
+    }
+
+    pub fn for_device(&self, serial: &str) -> Vec<AppliedPreset> {
+        self.applied
+            .lock()
+            .map(|a| a.iter().filter(|p| p.serial == serial).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn find(&self, serial: &str, name: &str) -> Option<AppliedPreset> {
+        self.for_device(serial).into_iter().find(|p| p.name == name)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Vec<AppliedPreset>)) -> Result<(), AdbError> {
+        let mut applied = self
+            .applied
+            .lock()
+            .map_err(|e| AdbError::InvalidState(e.to_string()))?;
+        f(&mut applied);
+        storage::write(APPLIED_FILE, &*applied)
+    }
+}
+
+impl Default for DevPresetState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply preset `name` to `serial`, all steps or none. Every settings
+/// write, including those undoing a failed apply, is passed to
+/// `on_setting` as (attempted change, result).
+pub async fn apply(
+    adb: &impl AdbTransport,
+    state: &DevPresetState,
+    serial: &str,
+    name: &str,
+    mut on_setting: impl FnMut(&SettingChange, &Result<SettingChange, AdbError>),
+) -> Result<AppliedPreset, AdbError> {
+    if state.find(serial, name).is_some() {
+        return Err(AdbError::InvalidState(format!("预设 {} 已应用", name)));
+    }
+    let preset = find_preset(name)?;
+    let undo = run_preset(adb, serial, &preset, &mut on_setting).await?;
+
+    let applied = AppliedPreset {
+        serial: serial.to_string(),
+        name: preset.name,
+        applied_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        undo,
+    };
+    state.update(|all| all.push(applied.clone()))?;
+    Ok(applied)
+}
+
+/// Undo preset `name` on `serial`. Steps that fail stay recorded so the
+/// revert can be retried; the error lists them. Settings writes go to
+/// `on_setting` as in [`apply`].
+pub async fn revert(
+    adb: &impl AdbTransport,
+    state: &DevPresetState,
+    serial: &str,
+    name: &str,
+    mut on_setting: impl FnMut(&SettingChange, &Result<SettingChange, AdbError>),
+) -> Result<(), AdbError> {
+    let applied = state
+        .find(serial, name)
+        .ok_or_else(|| AdbError::InvalidState(format!("预设 {} 未应用", name)))?;
+    let failed = run_undo(adb, serial, &applied.undo, &mut on_setting).await;
+
+    let remaining: Vec<PresetStep> = failed.iter().map(|(step, _)| step.clone()).collect();
+    state.update(|all| {
+        if remaining.is_empty() {
+            all.retain(|p| !(p.serial == serial && p.name == name));
+        } else if let Some(p) = all
+            .iter_mut()
+            .find(|p| p.serial == serial && p.name == name)
+        {
+            p.undo = remaining;
+        }
+    })?;
+
+    match failed.first() {
+        None => Ok(()),
+        Some((_, first)) => Err(AdbError::Command(format!(
+            "{} 个步骤还原失败: {}",
+            failed.len(),
+            first
+        ))),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_builtin_presets_and_config_shape() {
+        let names: Vec<String> = builtin_presets().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["ui_test_mode", "demo_mode", "debug_overlay"]);
+
+        let preset: DevPreset = serde_json::from_str(
+            r#"{
+                "name": "big_font",
+                "steps": [
+                    {"kind": "setting", "namespace": "system", "key": "font_scale", "value": "1.3"},
+                    {"kind": "prop", "key": "debug.hwui.profile", "value": "visual_bars"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(preset.custom);
+        assert_eq!(
+            preset.steps[0],
+            setting(SettingsNamespace::System, "font_scale", "1.3")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_steps_rolls_back_on_failure() {
+        let mock = MockTransport::from_transcript(
+            "$ adb -s SER shell settings get system show_touches\n\
+             0\n\
+             $ adb -s SER shell settings put system show_touches '1'\n\
+             $ adb -s SER shell getprop debug.layout\n\
+             \n\
+             $ adb -s SER shell setprop debug.layout 'true'\n\
+             $ adb -s SER shell getprop debug.hwui.overdraw\n\
+             ! getprop: permission denied\n\
+             [exit 1]\n\
+             $ adb -s SER shell settings put system show_touches '0'\n\
+             $ adb -s SER shell setprop debug.layout ''\n\
+             $ adb -s SER shell service call activity 1599295570\n\
+             Result: Parcel(00000000    '....')\n",
+        );
+        let steps = vec![
+            setting(SettingsNamespace::System, "show_touches", "1"),
+            prop("debug.layout", "true"),
+            prop("debug.hwui.overdraw", "show"),
+        ];
+        assert!(run_steps(&mock, "SER", &steps, &mut |_, _| {})
+            .await
+            .is_err());
+
+        let calls = mock.calls();
+        // Undone most recent first
+        let undo_prop = calls
+            .iter()
+            .position(|c| c == "-s SER shell setprop debug.layout ''")
+            .unwrap();
+        let undo_setting = calls
+            .iter()
+            .rposition(|c| c == "-s SER shell settings put system show_touches '0'")
+            .unwrap();
+        assert!(undo_prop < undo_setting);
+    }
+
+    #[tokio::test]
+    async fn test_failed_apply_runs_preset_revert() {
+        // No transcript for the clock broadcast: the third step fails
+        let mock = MockTransport::from_transcript(
+            "$ adb -s SER shell settings get global sysui_demo_allowed\n\
+             0\n\
+             $ adb -s SER shell settings put global sysui_demo_allowed '1'\n\
+             $ adb -s SER shell am broadcast -a com.android.systemui.demo -e command enter\n\
+             Broadcast completed: result=0\n\
+             $ adb -s SER shell settings put global sysui_demo_allowed '0'\n\
+             $ adb -s SER shell am broadcast -a com.android.systemui.demo -e command exit\n\
+             Broadcast completed: result=0\n",
+        );
+        let preset = builtin_presets()
+            .into_iter()
+            .find(|p| p.name == "demo_mode")
+            .unwrap();
+        let mut written = Vec::new();
+        let result = run_preset(&mock, "SER", &preset, &mut |_, r| {
+            written.push(r.as_ref().ok().cloned())
+        })
+        .await;
+        assert!(result.is_err());
+
+        let calls = mock.calls();
+        assert_eq!(
+            calls.last().map(String::as_str),
+            Some("-s SER shell am broadcast -a com.android.systemui.demo -e command exit")
+        );
+        assert!(
+            calls.contains(&"-s SER shell settings put global sysui_demo_allowed '0'".to_string())
+        );
+        // The write and its undo are both reported
+        let values: Vec<Option<String>> = written
+            .into_iter()
+            .map(|c| c.and_then(|c| c.value))
+            .collect();
+        assert_eq!(values, vec![Some("1".to_string()), Some("0".to_string())]);
+    }
+}
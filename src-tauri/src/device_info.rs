@@ -0,0 +1,505 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+// ---------------------------------------------------------------------------
+// Device inventory parsers
+//
+// `get_device_detail` reads everything in two round trips: one `getprop` dump
+// and one shell call that runs the cheap probes below back to back, each
+// preceded by a marker line. The parsers here turn that output into values;
+// anything missing (old Android, restricted shell) comes back empty/None.
+// ---------------------------------------------------------------------------
+
+/// Printed before each probe's output as `@@name`.
+const PROBE_MARKER: &str = "@@";
+
+/// `(name, command)` pairs run by [`probe_command`].
+pub const DETAIL_PROBES: &[(&str, &str)] = &[
+    ("df", "df"),
+    ("wm_size", "wm size"),
+    ("wm_density", "wm density"),
+    ("battery", "dumpsys battery"),
+    ("meminfo", "cat /proc/meminfo"),
+    ("uptime", "cat /proc/uptime"),
+    ("wifi", "ip -f inet addr show wlan0"),
+    ("su", "which su"),
+];
+
+/// Parse `getprop` output (`[key]: [value]` per line) into a map. Values that
+/// span lines are joined back with `\n`.
+pub fn parse_getprop(output: &str) -> BTreeMap<String, String> {
+    let mut props = BTreeMap::new();
+    let mut open: Option<(String, String)> = None;
+
+    for line in output.lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some((key, mut value)) = open.take() {
+            // Continuation of a multi-line value
+            value.push('\n');
+            match line.strip_suffix(']') {
+                Some(rest) => {
+                    value.push_str(rest);
+                    props.insert(key, value);
+                }
+                None => {
+                    value.push_str(line);
+                    open = Some((key, value));
+                }
+            }
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix('[') else {
+            continue;
+        };
+        let Some((key, value)) = rest.split_once("]: [") else {
+            continue;
+        };
+        match value.strip_suffix(']') {
+            Some(value) => {
+                props.insert(key.to_string(), value.to_string());
+            }
+            None => open = Some((key.to_string(), value.to_string())),
+        }
+    }
+    props
+}
+
+/// One shell command line running every probe; stderr is dropped so legacy
+/// `shell:` output stays clean.
+pub fn probe_command(probes: &[(&str, &str)]) -> String {
+    probes
+        .iter()
+        .map(|(name, command)| format!("echo {}{}; {} 2>/dev/null", PROBE_MARKER, name, command))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Split [`probe_command`] output back into `name → output`.
+pub fn split_probes(output: &str) -> HashMap<String, String> {
+    let mut sections = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in output.lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some(name) = line.strip_prefix(PROBE_MARKER) {
+            if let Some((name, body)) = current.take() {
+                sections.insert(name, body);
+            }
+            current = Some((name.trim().to_string(), String::new()));
+        } else if let Some((_, body)) = current.as_mut() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    if let Some((name, body)) = current {
+        sections.insert(name, body);
+    }
+    sections
+}
+
+/// `wm size` / `wm density` print a `Physical` value and, when changed in
+/// developer options, an `Override` one. The override is what apps see.
+pub fn parse_wm_value(output: &str) -> Option<String> {
+    let mut physical = None;
+    let mut overridden = None;
+    for line in output.lines() {
+        if let Some(v) = line.trim().strip_prefix("Physical size:") {
+            physical = Some(v.trim().to_string());
+        } else if let Some(v) = line.trim().strip_prefix("Physical density:") {
+            physical = Some(v.trim().to_string());
+        } else if let Some(v) = line.trim().strip_prefix("Override size:") {
+            overridden = Some(v.trim().to_string());
+        } else if let Some(v) = line.trim().strip_prefix("Override density:") {
+            overridden = Some(v.trim().to_string());
+        }
+    }
+    overridden.or(physical).filter(|v| !v.is_empty())
+}
+
+/// Battery level (0-100) and status from `dumpsys battery`.
+pub fn parse_battery(output: &str) -> (Option<u8>, String) {
+    let mut level = None;
+    let mut status = String::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        match key.trim() {
+            "level" => level = value.trim().parse().ok(),
+            // BatteryManager.BATTERY_STATUS_*
+            "status" => {
+                status = match value.trim() {
+                    "2" => "charging",
+                    "3" => "discharging",
+                    "4" => "not_charging",
+                    "5" => "full",
+                    _ => "unknown",
+                }
+                .to_string()
+            }
+            _ => {}
+        }
+    }
+    (level, status)
+}
+
+/// Battery temperature in °C; `dumpsys battery` reports tenths of a degree.
+pub fn parse_battery_temperature(output: &str) -> Option<f32> {
+    output.lines().find_map(|line| {
+        let tenths: f32 = line
+            .trim()
+            .strip_prefix("temperature:")?
+            .trim()
+            .parse()
+            .ok()?;
+        Some(tenths / 10.0)
+    })
+}
+
+/// `Thermal Status: N` from `dumpsys thermalservice` (Android 10+), where 0 is
+/// none and 3 and above is severe or worse.
+pub fn parse_thermal_status(output: &str) -> Option<u8> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Thermal Status:")?
+            .trim()
+            .parse()
+            .ok()
+    })
+}
+
+/// `MemTotal` and `MemAvailable` from `/proc/meminfo`, in MB. Kernels before
+/// 3.14 have no `MemAvailable`; free + cached approximates it there.
+pub fn parse_meminfo(output: &str) -> (u64, u64) {
+    let mut fields: HashMap<&str, u64> = HashMap::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let kb = value
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        fields.insert(key.trim(), kb);
+    }
+    let total = fields.get("MemTotal").copied().unwrap_or(0);
+    let available = fields.get("MemAvailable").copied().unwrap_or_else(|| {
+        fields.get("MemFree").copied().unwrap_or(0) + fields.get("Cached").copied().unwrap_or(0)
+    });
+    (total / 1024, available / 1024)
+}
+
+/// Seconds since boot from `/proc/uptime` (`12345.67 45678.90`).
+pub fn parse_uptime(output: &str) -> u64 {
+    output
+        .split_whitespace()
+        .next()
+        .and_then(|v| v.parse::<f64>().ok())
+        .map(|secs| secs as u64)
+        .unwrap_or(0)
+}
+
+/// First IPv4 address in `ip -f inet addr show` or `ifconfig` output
+/// (`inet 10.0.0.5/24`, `inet addr:10.0.0.5`).
+pub fn parse_inet_addr(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("inet ")?;
+        let addr = rest.split_whitespace().next()?;
+        let addr = addr.strip_prefix("addr:").unwrap_or(addr);
+        Some(addr.split('/').next().unwrap_or(addr).to_string())
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Storage (`df`)
+//
+// Variants seen in the wild: toybox `df` (1K-blocks), toybox `df -h` and
+// vendor builds with K/M/G suffixes, pre-7.0 toolbox (`Filesystem Size Used
+// Free Blksize` with the mount point first), and busybox wrapping a long
+// filesystem name onto its own line.
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageMount {
+    /// `data`, `shared`, `system`, `adopted` or `portable`
+    pub kind: String,
+    pub filesystem: String,
+    pub mount_point: String,
+    pub total_mb: u64,
+    pub used_mb: u64,
+    pub free_mb: u64,
+    pub used_percent: u8,
+}
+
+/// Parse `df` output into the mounts worth showing, in `df` order. Pseudo
+/// filesystems, APEX images and the like are dropped.
+pub fn parse_df(output: &str) -> Vec<StorageMount> {
+    let mut lines = output.lines().map(|l| l.trim_end_matches('\r'));
+    let Some(header) = lines.find(|l| l.trim_start().starts_with("Filesystem")) else {
+        return Vec::new();
+    };
+    let header: Vec<&str> = header.split_whitespace().collect();
+    // Old toolbox: the first column is the mount point, there is no Use%
+    let mount_first = header.contains(&"Blksize");
+    let block_bytes = header
+        .get(1)
+        .and_then(|col| col.strip_suffix("-blocks"))
+        .and_then(|size| parse_size(size, 1))
+        .unwrap_or(1);
+
+    let mut mounts: Vec<StorageMount> = Vec::new();
+    let mut wrapped: Option<String> = None;
+    for line in lines {
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() == 1 && !mount_first {
+            wrapped = Some(tokens[0].to_string());
+            continue;
+        }
+        if let Some(name) = wrapped.as_deref() {
+            tokens.insert(0, name);
+        }
+        let parsed = parse_df_row(&tokens, mount_first, block_bytes);
+        wrapped = None;
+
+        let Some(mut mount) = parsed else {
+            continue;
+        };
+        let Some(kind) = mount_kind(&mount.mount_point) else {
+            continue;
+        };
+        if mount.total_mb == 0 || mounts.iter().any(|m| m.mount_point == mount.mount_point) {
+            continue;
+        }
+        mount.kind = kind.to_string();
+        mounts.push(mount);
+    }
+
+    // Before system-as-root `/` is the ramdisk
+    if mounts.iter().any(|m| m.mount_point == "/system") {
+        mounts.retain(|m| m.mount_point != "/");
+    }
+    mounts
+}
+
+fn parse_df_row(tokens: &[&str], mount_first: bool, block_bytes: u64) -> Option<StorageMount> {
+    let (filesystem, mount_point, sizes) = if mount_first {
+        (*tokens.first()?, *tokens.first()?, tokens.get(1..4)?)
+    } else {
+        if tokens.len() < 5 {
+            return None;
+        }
+        (tokens[0], *tokens.last()?, &tokens[1..4])
+    };
+    let total = parse_size(sizes[0], block_bytes)?;
+    let used = parse_size(sizes[1], block_bytes)?;
+    let free = parse_size(sizes[2], block_bytes)?;
+    let used_percent = tokens
+        .iter()
+        .find_map(|t| t.strip_suffix('%')?.parse::<u8>().ok())
+        .unwrap_or_else(|| {
+            // `-h` values are rounded, so only compute when df didn't say
+            let capacity = used + free;
+            (used * 100 + capacity / 2)
+                .checked_div(capacity)
+                .unwrap_or(0) as u8
+        });
+
+    const MB: u64 = 1024 * 1024;
+    Some(StorageMount {
+        kind: String::new(),
+        filesystem: filesystem.to_string(),
+        mount_point: mount_point.to_string(),
+        total_mb: total / MB,
+        used_mb: used / MB,
+        free_mb: free / MB,
+        used_percent,
+    })
+}
+
+/// `1234`, `1.5G`, `710M`, `0.0K`: bytes, with bare numbers counted in
+/// `block_bytes` units. Suffixes are powers of 1024 like df uses.
+fn parse_size(value: &str, block_bytes: u64) -> Option<u64> {
+    let value = value.trim().trim_end_matches(['B', 'i']);
+    let (number, multiplier) = match value.chars().last()? {
+        'K' | 'k' => (&value[..value.len() - 1], 1u64 << 10),
+        'M' | 'm' => (&value[..value.len() - 1], 1 << 20),
+        'G' | 'g' => (&value[..value.len() - 1], 1 << 30),
+        'T' | 't' => (&value[..value.len() - 1], 1 << 40),
+        _ => (value, block_bytes),
+    };
+    if let Ok(whole) = number.parse::<u64>() {
+        return Some(whole * multiplier);
+    }
+    let fractional = number.parse::<f64>().ok()?;
+    Some((fractional * multiplier as f64) as u64)
+}
+
+/// Which of the mounts a user cares about `mount_point` is, if any.
+fn mount_kind(mount_point: &str) -> Option<&'static str> {
+    match mount_point {
+        "/data" => return Some("data"),
+        "/storage/emulated" | "/storage/emulated/0" | "/mnt/shell/emulated" | "/sdcard" => {
+            return Some("shared")
+        }
+        "/system" | "/" => return Some("system"),
+        _ => {}
+    }
+    if let Some(uuid) = mount_point.strip_prefix("/mnt/expand/") {
+        return (!uuid.contains('/')).then_some("adopted");
+    }
+    // Portable SD cards and USB drives: /storage/ABCD-1234 (sdcard1 pre-6.0)
+    let name = mount_point.strip_prefix("/storage/")?;
+    (!name.contains('/') && !matches!(name, "emulated" | "self" | "sdcard0")).then_some("portable")
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_getprop() {
+        let output = "[ro.product.model]: [Pixel 8]\n\
+                      [ro.build.version.sdk]: [34]\n\
+                      [persist.sys.empty]: []\n\
+                      [ro.multi]: [first\n\
+                      second]\n\
+                      garbage line\n\
+                      [ro.build.type]: [user]\r\n";
+        let props = parse_getprop(output);
+        assert_eq!(props["ro.product.model"], "Pixel 8");
+        assert_eq!(props["persist.sys.empty"], "");
+        assert_eq!(props["ro.multi"], "first\nsecond");
+        assert_eq!(props["ro.build.type"], "user");
+        assert_eq!(props.len(), 5);
+    }
+
+    #[test]
+    fn test_probe_round_trip() {
+        let command = probe_command(&[("a", "wm size"), ("b", "which su")]);
+        assert_eq!(
+            command,
+            "echo @@a; wm size 2>/dev/null; echo @@b; which su 2>/dev/null"
+        );
+
+        let sections = split_probes("@@a\nPhysical size: 1080x2400\n@@b\n");
+        assert_eq!(sections["a"], "Physical size: 1080x2400\n");
+        assert_eq!(sections["b"], "");
+    }
+
+    #[test]
+    fn test_parse_probe_values() {
+        assert_eq!(
+            parse_wm_value("Physical size: 1440x3120\nOverride size: 1080x2340\n").as_deref(),
+            Some("1080x2340")
+        );
+        assert_eq!(
+            parse_wm_value("Physical density: 420\n").as_deref(),
+            Some("420")
+        );
+        assert_eq!(parse_wm_value(""), None);
+
+        let battery = "Current Battery Service state:\n  AC powered: false\n  USB powered: true\n  status: 2\n  health: 2\n  level: 87\n  scale: 100\n";
+        assert_eq!(parse_battery(battery), (Some(87), "charging".to_string()));
+        assert_eq!(
+            parse_battery_temperature("  temperature: 287\n"),
+            Some(28.7)
+        );
+        assert_eq!(
+            parse_thermal_status("IsStatusOverride: false\nThermal Status: 2\n"),
+            Some(2)
+        );
+        assert_eq!(parse_thermal_status(""), None);
+
+        let meminfo = "MemTotal:        7818464 kB\nMemFree:          301236 kB\nMemAvailable:    3145728 kB\n";
+        assert_eq!(parse_meminfo(meminfo), (7635, 3072));
+        // Pre-3.14 kernel
+        let old = "MemTotal: 1885344 kB\nMemFree: 102400 kB\nCached: 512000 kB\n";
+        assert_eq!(parse_meminfo(old).1, 600);
+
+        assert_eq!(parse_uptime("93784.12 301122.55\n"), 93784);
+        let ip = "31: wlan0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500\n    inet 192.168.1.42/24 brd 192.168.1.255 scope global wlan0\n";
+        assert_eq!(parse_inet_addr(ip).as_deref(), Some("192.168.1.42"));
+        assert_eq!(parse_inet_addr(""), None);
+    }
+
+    macro_rules! df_fixture {
+        ($name:literal) => {
+            include_str!(concat!("../tests/fixtures/df/", $name, ".txt"))
+        };
+    }
+
+    fn summary(mounts: &[StorageMount]) -> Vec<(&str, &str, u64, u8)> {
+        mounts
+            .iter()
+            .map(|m| {
+                (
+                    m.kind.as_str(),
+                    m.mount_point.as_str(),
+                    m.total_mb,
+                    m.used_percent,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_df_human_units() {
+        let mounts = parse_df(df_fixture!("toybox-h-android11"));
+        assert_eq!(
+            summary(&mounts),
+            vec![
+                ("system", "/", 3174, 100),
+                ("data", "/data", 109568, 29),
+                ("shared", "/storage/emulated", 109568, 29),
+                ("portable", "/storage/6A3C-1B0E", 60416, 21),
+            ]
+        );
+        assert_eq!((mounts[1].used_mb, mounts[1].free_mb), (30720, 78848));
+
+        // Toolbox puts the mount point first and has no Use% column
+        let mounts = parse_df(df_fixture!("toolbox-android5"));
+        assert_eq!(
+            summary(&mounts),
+            vec![
+                ("system", "/system", 1024, 85),
+                ("data", "/data", 12800, 26),
+                ("shared", "/mnt/shell/emulated", 12800, 26),
+                ("portable", "/storage/sdcard1", 30412, 34),
+            ]
+        );
+        assert_eq!((mounts[1].used_mb, mounts[1].free_mb), (3276, 9523));
+    }
+
+    #[test]
+    fn test_parse_df_wrapped_and_adopted() {
+        let mounts = parse_df(df_fixture!("busybox-wrapped"));
+        assert_eq!(
+            summary(&mounts),
+            vec![
+                ("system", "/system", 1511, 72),
+                ("data", "/data", 12567, 35),
+                ("shared", "/storage/emulated", 12567, 35),
+            ]
+        );
+        assert_eq!(
+            mounts[1].filesystem,
+            "/dev/block/platform/msm_sdcc.1/by-name/userdata"
+        );
+        assert_eq!(mounts[1].free_mb, 8351120 / 1024);
+
+        let mounts = parse_df(df_fixture!("adopted-android9"));
+        let kinds: Vec<&str> = mounts.iter().map(|m| m.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["system", "data", "adopted", "shared"]);
+        assert_eq!(mounts[2].total_mb, 60901376 / 1024);
+        assert_eq!(mounts[2].used_percent, 17);
+
+        assert!(parse_df("").is_empty());
+        assert!(parse_df("df: /data: Permission denied\n").is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::adb::{AdbDevice, DeviceDetail};
+use crate::error::AdbError;
+use crate::storage;
+
+// ---------------------------------------------------------------------------
+// Data types
+// ---------------------------------------------------------------------------
+
+/// What the user knows about a device that adb does not: a readable name, a
+/// color for the device list, free-form tags and notes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceLabel {
+    #[serde(default)]
+    pub alias: String,
+    /// `#RRGGBB` / `#RGB`, or empty for the default.
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl DeviceLabel {
+    pub fn is_empty(&self) -> bool {
+        self.alias.is_empty()
+            && self.color.is_empty()
+            && self.tags.is_empty()
+            && self.notes.is_empty()
+    }
+}
+
+/// Managed state: labels keyed by serial.
+pub struct DeviceRegistryState {
+    pub labels: Mutex<BTreeMap<String, DeviceLabel>>,
+}
+
+// ---------------------------------------------------------------------------
+// Persistence helpers
+// ---------------------------------------------------------------------------
+
+/// `~/AdbTool/devices.json`, next to `op_history.json`; a missing or
+/// unreadable file means no labels.
+const REGISTRY_FILE: &str = "devices.json";
+
+// ---------------------------------------------------------------------------
+// Public API
+// ---------------------------------------------------------------------------
+
+impl DeviceRegistryState {
+    /// Create state, pre-loading saved labels from disk.
+    pub fn new() -> Self {
+        Self {
+            labels: Mutex::new(storage::load_or_default(REGISTRY_FILE)),
+        }
+    }
+
+    pub fn all(&self) -> BTreeMap<String, DeviceLabel> {
+        self.labels.lock().map(|l| l.clone()).unwrap_or_default()
+    }
+
+    pub fn get(&self, serial: &str) -> Option<DeviceLabel> {
+        self.labels.lock().ok()?.get(serial).cloned()
+    }
+
+    /// Non-empty alias for `serial`.
+    pub fn alias(&self, serial: &str) -> Option<String> {
+        self.get(serial)
+            .map(|label| label.alias)
+            .filter(|alias| !alias.is_empty())
+    }
+
+    /// Store the label for `serial`; an empty label removes the entry.
+    pub fn set(&self, serial: &str, label: DeviceLabel) -> Result<DeviceLabel, AdbError> {
+        let label = normalize(label)?;
+        let mut labels = self
+            .labels
+            .lock()
+            .map_err(|e| AdbError::InvalidState(e.to_string()))?;
+        if label.is_empty() {
+            labels.remove(serial);
+        } else {
+            labels.insert(serial.to_string(), label.clone());
+        }
+        storage::write(REGISTRY_FILE, &*labels)?;
+        Ok(label)
+    }
+
+    pub fn remove(&self, serial: &str) -> Result<(), AdbError> {
+        self.set(serial, DeviceLabel::default()).map(|_| ())
+    }
+
+    /// Attach saved labels to a device listing.
+    pub fn annotate(&self, devices: &mut [AdbDevice]) {
+        let Ok(labels) = self.labels.lock() else {
+            return;
+        };
+        for device in devices {
+            device.label = labels.get(&device.serial).cloned();
+        }
+    }
+
+    pub fn annotate_detail(&self, detail: &mut DeviceDetail) {
+        detail.label = self.get(&detail.serial);
+    }
+}
+
+impl Default for DeviceRegistryState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Alias for `serial` straight from disk, for callers without the managed
+/// state (the CLI); the app uses [`DeviceRegistryState::alias`].
+pub fn alias_for(serial: &str) -> Option<String> {
+    storage::load_or_default::<BTreeMap<String, DeviceLabel>>(REGISTRY_FILE)
+        .remove(serial)
+        .map(|label| label.alias)
+        .filter(|alias| !alias.is_empty())
+}
+
+/// `alias_serial`, or just the serial, made safe for a file name.
+pub fn file_label(serial: &str) -> String {
+    match alias_for(serial) {
+        Some(alias) => file_safe(&format!("{}_{}", alias, serial)),
+        None => file_safe(serial),
+    }
+}
+
+/// Replace everything but letters, digits, `-`, `_` and `.` with `_`.
+/// Wireless serials contain `:`, which is not allowed in Windows file names.
+pub fn file_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Trim fields, drop empty/duplicate tags and check the color.
+fn normalize(label: DeviceLabel) -> Result<DeviceLabel, AdbError> {
+    let color = label.color.trim().to_string();
+    if !color.is_empty() {
+        let hex = color.strip_prefix('#').unwrap_or_default();
+        if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AdbError::InvalidConfig(format!(
+                "颜色格式应为 #RRGGBB: {}",
+                color
+            )));
+        }
+    }
+    let mut tags: Vec<String> = Vec::new();
+    for tag in label.tags {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    Ok(DeviceLabel {
+        alias: label.alias.trim().to_string(),
+        color,
+        tags,
+        notes: label.notes.trim_end().to_string(),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_safe() {
+        assert_eq!(file_safe("192.168.1.5:5555"), "192.168.1.5_5555");
+        assert_eq!(file_safe("emulator-5554"), "emulator-5554");
+        assert_eq!(file_safe("QA Pixel/7"), "QA_Pixel_7");
+    }
+
+    #[test]
+    fn test_normalize_label() {
+        let label = normalize(DeviceLabel {
+            alias: "  QA Pixel 7 #3 ".to_string(),
+            color: "#1e88E5".to_string(),
+            tags: vec![
+                "release-QA".to_string(),
+                " rooted ".to_string(),
+                "".to_string(),
+                "release-QA".to_string(),
+            ],
+            notes: "cracked screen\n".to_string(),
+        })
+        .unwrap();
+        assert_eq!(label.alias, "QA Pixel 7 #3");
+        assert_eq!(label.tags, vec!["release-QA", "rooted"]);
+        assert_eq!(label.notes, "cracked screen");
+
+        for bad in ["red", "#12345", "#GGGGGG"] {
+            let label = DeviceLabel {
+                color: bad.to_string(),
+                ..Default::default()
+            };
+            assert!(
+                matches!(normalize(label), Err(AdbError::InvalidConfig(_))),
+                "{bad}"
+            );
+        }
+        assert!(normalize(DeviceLabel::default()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AdbError;
+use crate::op_log::OpLogEntry;
+use crate::operation::OpClass;
+use crate::shell;
+use crate::transport::AdbTransport;
+
+// ---------------------------------------------------------------------------
+// Android settings provider (`settings list/get/put/delete`)
+//
+// Every put/delete made through the app is logged with the value it
+// replaced (`OpLogEntry::setting`). Reverting walks those entries back to
+// the first recorded value of each key since the device's last revert.
+// Reverted keys are logged the same way, so a partial revert leaves only
+// the keys that failed pending.
+// ---------------------------------------------------------------------------
+
+/// Op log type of a single settings change.
+pub const OP_TYPE: &str = "setting";
+/// Op log type of a revert; earlier changes are no longer pending after it.
+pub const OP_TYPE_REVERT: &str = "setting_revert";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingsNamespace {
+    Global,
+    Secure,
+    System,
+}
+
+impl SettingsNamespace {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SettingsNamespace::Global => "global",
+            SettingsNamespace::Secure => "secure",
+            SettingsNamespace::System => "system",
+        }
+    }
+}
+
+/// One settings change; `None` means the key did not exist (or was deleted).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingChange {
+    pub namespace: SettingsNamespace,
+    pub key: String,
+    pub previous: Option<String>,
+    pub value: Option<String>,
+}
+
+impl SettingChange {
+    pub fn describe(&self) -> String {
+        let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "(未设置)".to_string());
+        format!(
+            "{} {}: {} → {}",
+            self.namespace.as_str(),
+            self.key,
+            show(&self.previous),
+            show(&self.value)
+        )
+    }
+}
+
+/// Setting keys are identifiers like `adb_wifi_enabled` or
+/// `transition_animation_scale`; anything else is refused rather than quoted.
+pub(crate) fn validate_key(key: &str) -> Result<&str, AdbError> {
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | ':'));
+    if !valid {
+        return Err(AdbError::InvalidConfig(format!("无效的设置项: {:?}", key)));
+    }
+    Ok(key)
+}
+
+/// Parse `settings list <namespace>` (`key=value` per line). Values may
+/// contain `=`; lines without one continue the previous value.
+pub fn parse_settings_list(output: &str) -> BTreeMap<String, String> {
+    let mut settings = BTreeMap::new();
+    let mut last: Option<String> = None;
+    for line in output.lines() {
+        let line = line.trim_end_matches('\r');
+        match line.split_once('=') {
+            Some((key, value)) if validate_key(key).is_ok() => {
+                settings.insert(key.to_string(), value.to_string());
+                last = Some(key.to_string());
+            }
+            _ => {
+                if let Some(value) = last.as_ref().and_then(|k| settings.get_mut(k)) {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+    settings
+}
+
+// ---------------------------------------------------------------------------
+// Reading and writing
+// ---------------------------------------------------------------------------
+
+pub async fn list(
+    adb: &impl AdbTransport,
+    serial: &str,
+    namespace: SettingsNamespace,
+) -> Result<BTreeMap<String, String>, AdbError> {
+    let output = adb
+        .exec_device(
+            serial,
+            &["shell", "settings", "list", namespace.as_str()],
+            OpClass::Fast,
+        )
+        .await?;
+    Ok(parse_settings_list(&output))
+}
+
+/// Current value, `None` when unset (`settings get` prints `null`).
+pub async fn get(
+    adb: &impl AdbTransport,
+    serial: &str,
+    namespace: SettingsNamespace,
+    key: &str,
+) -> Result<Option<String>, AdbError> {
+    let output = adb
+        .exec_device(
+            serial,
+            &[
+                "shell",
+                "settings",
+                "get",
+                namespace.as_str(),
+                validate_key(key)?,
+            ],
+            OpClass::Fast,
+        )
+        .await?;
+    let value = output.trim_end_matches(['\r', '\n']);
+    Ok((value != "null").then(|| value.to_string()))
+}
+
+/// Set `key`, or delete it when `value` is `None`. Returns the change with
+/// the value it replaced.
+pub async fn set(
+    adb: &impl AdbTransport,
+    serial: &str,
+    namespace: SettingsNamespace,
+    key: &str,
+    value: Option<&str>,
+) -> Result<SettingChange, AdbError> {
+    let previous = get(adb, serial, namespace, key).await?;
+    let quoted = value.map(shell::quote);
+    let args = match &quoted {
+        Some(quoted) => vec!["shell", "settings", "put", namespace.as_str(), key, quoted],
+        None => vec!["shell", "settings", "delete", namespace.as_str(), key],
+    };
+    adb.exec_device(serial, &args, OpClass::Fast).await?;
+    Ok(SettingChange {
+        namespace,
+        key: key.to_string(),
+        previous,
+        value: value.map(str::to_string),
+    })
+}
+
+/// `adb shell` command line of a change, for the op log.
+pub fn command_line(serial: &str, change: &SettingChange) -> String {
+    match &change.value {
+        Some(value) => format!(
+            "adb -s {} shell settings put {} {} {}",
+            serial,
+            change.namespace.as_str(),
+            change.key,
+            shell::quote(value)
+        ),
+        None => format!(
+            "adb -s {} shell settings delete {} {}",
+            serial,
+            change.namespace.as_str(),
+            change.key
+        ),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Reverting
+// ---------------------------------------------------------------------------
+
+/// Changes that undo everything logged for `serial` since its last revert,
+/// one per key in first-changed order: `previous` is the value we last set,
+/// `value` the original to restore. Keys already back at the original are
+/// left out.
+pub fn pending_reverts(entries: &[OpLogEntry], serial: &str) -> Vec<SettingChange> {
+    let mut reverts: Vec<SettingChange> = Vec::new();
+    for entry in entries.iter().filter(|e| e.device == serial && e.success) {
+        if entry.op_type == OP_TYPE_REVERT {
+            reverts.clear();
+            continue;
+        }
+        let Some(change) = entry.setting.as_ref().filter(|_| entry.op_type == OP_TYPE) else {
+            continue;
+        };
+        match reverts
+            .iter_mut()
+            .find(|r| r.namespace == change.namespace && r.key == change.key)
+        {
+            Some(revert) => revert.previous = change.value.clone(),
+            None => reverts.push(SettingChange {
+                namespace: change.namespace,
+                key: change.key.clone(),
+                previous: change.value.clone(),
+                value: change.previous.clone(),
+            }),
+        }
+    }
+    reverts.retain(|r| r.previous != r.value);
+    reverts
+}
+
+/// Apply `reverts` one key at a time; a failure does not stop the rest.
+/// Returns each key's result in order, as the change actually made.
+pub async fn revert(
+    adb: &impl AdbTransport,
+    serial: &str,
+    reverts: &[SettingChange],
+) -> Vec<Result<SettingChange, AdbError>> {
+    let mut results = Vec::with_capacity(reverts.len());
+    for change in reverts {
+        let result = set(
+            adb,
+            serial,
+            change.namespace,
+            &change.key,
+            change.value.as_deref(),
+        )
+        .await;
+        results.push(result);
+    }
+    results
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    fn logged(op_type: &str, serial: &str, change: Option<SettingChange>) -> OpLogEntry {
+        OpLogEntry {
+            setting: change,
+            ..OpLogEntry::new(op_type, serial, "", true)
+        }
+    }
+
+    fn change(key: &str, previous: Option<&str>, value: Option<&str>) -> SettingChange {
+        SettingChange {
+            namespace: SettingsNamespace::Global,
+            key: key.to_string(),
+            previous: previous.map(str::to_string),
+            value: value.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_settings_list() {
+        let settings = parse_settings_list(
+            "adb_enabled=1\n\
+             airplane_mode_on=0\n\
+             device_name=QA Pixel=7\n\
+             wifi_networks_available_notification_on=1\n\
+             lock_screen_owner_info=line one\n\
+             line two\n",
+        );
+        assert_eq!(settings.len(), 5);
+        assert_eq!(settings["device_name"], "QA Pixel=7");
+        assert_eq!(settings["lock_screen_owner_info"], "line one\nline two");
+    }
+
+    #[test]
+    fn test_pending_reverts() {
+        let entries = vec![
+            logged(OP_TYPE, "SER", Some(change("old", Some("1"), Some("2")))),
+            logged(OP_TYPE_REVERT, "SER", None),
+            logged(
+                OP_TYPE,
+                "SER",
+                Some(change("window_animation_scale", Some("1.0"), Some("0"))),
+            ),
+            logged(
+                OP_TYPE,
+                "OTHER",
+                Some(change("adb_enabled", Some("1"), Some("0"))),
+            ),
+            logged(
+                OP_TYPE,
+                "SER",
+                Some(change("stay_on_while_plugged_in", None, Some("7"))),
+            ),
+            logged(
+                OP_TYPE,
+                "SER",
+                Some(change("window_animation_scale", Some("0"), Some("0.5"))),
+            ),
+            logged(
+                OP_TYPE,
+                "SER",
+                Some(change("airplane_mode_on", Some("0"), Some("1"))),
+            ),
+            logged(
+                OP_TYPE,
+                "SER",
+                Some(change("airplane_mode_on", Some("1"), Some("0"))),
+            ),
+        ];
+        assert_eq!(
+            pending_reverts(&entries, "SER"),
+            vec![
+                change("window_animation_scale", Some("0.5"), Some("1.0")),
+                change("stay_on_while_plugged_in", Some("7"), None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_partial_revert_leaves_failed_keys_pending() {
+        let mut entries = vec![
+            logged(
+                OP_TYPE,
+                "SER",
+                Some(change("adb_enabled", Some("1"), Some("0"))),
+            ),
+            logged(
+                OP_TYPE,
+                "SER",
+                Some(change("http_proxy", None, Some("10.0.0.2:8888"))),
+            ),
+        ];
+        // No transcript for `http_proxy`: that key fails
+        let mock = MockTransport::from_transcript(
+            "$ adb -s SER shell settings get global adb_enabled\n\
+             0\n\
+             $ adb -s SER shell settings put global adb_enabled '1'\n",
+        );
+        let reverts = pending_reverts(&entries, "SER");
+        let results = revert(&mock, "SER", &reverts).await;
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+
+        // Logged the way `revert_device_settings` logs them
+        for result in results {
+            let success = result.is_ok();
+            let mut entry = logged(OP_TYPE, "SER", result.ok());
+            entry.success = success;
+            entries.push(entry);
+        }
+        assert_eq!(
+            pending_reverts(&entries, "SER"),
+            vec![change("http_proxy", Some("10.0.0.2:8888"), None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_records_previous_value() {
+        let mock = MockTransport::from_transcript(
+            "$ adb -s SER shell settings get system screen_off_timeout\n\
+             30000\n\
+             $ adb -s SER shell settings put system screen_off_timeout '1800000'\n\
+             $ adb -s SER shell settings get global http_proxy\n\
+             null\n\
+             $ adb -s SER shell settings put global http_proxy 'it'\\''s:8888'\n",
+        );
+        let changed = set(
+            &mock,
+            "SER",
+            SettingsNamespace::System,
+            "screen_off_timeout",
+            Some("1800000"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(changed.previous.as_deref(), Some("30000"));
+
+        let changed = set(
+            &mock,
+            "SER",
+            SettingsNamespace::Global,
+            "http_proxy",
+            Some("it's:8888"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(changed.previous, None);
+        assert!(get(&mock, "SER", SettingsNamespace::Global, "a b")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri::Emitter;
+use tauri::Manager;
+use tokio::sync::Notify;
+
+use crate::adb::{self, AdbDevice};
+use crate::adb_client::{self, FeatureCache};
+use crate::device_registry::DeviceRegistryState;
+use crate::forward;
+use crate::health;
+use crate::transport;
+
+// ---------------------------------------------------------------------------
+// Structs
+// ---------------------------------------------------------------------------
+
+/// Payload for the `device-state-changed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceStateChange {
+    pub serial: String,
+    pub old_state: String,
+    pub new_state: String,
+    pub device: AdbDevice,
+}
+
+#[derive(Debug, Clone)]
+pub enum DeviceEvent {
+    Attached(AdbDevice),
+    Detached(AdbDevice),
+    StateChanged(DeviceStateChange),
+}
+
+impl DeviceEvent {
+    /// Serial of a device that just became usable (`device` state), whether
+    /// newly attached or after e.g. authorization.
+    pub fn came_online(&self) -> Option<&str> {
+        match self {
+            DeviceEvent::Attached(device) if device.state == "device" => Some(&device.serial),
+            DeviceEvent::StateChanged(change) if change.new_state == "device" => {
+                Some(&change.serial)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Managed state: the device list as last reported by the tracker.
+pub struct DeviceTrackerState {
+    pub devices: Mutex<Vec<AdbDevice>>,
+    /// Signalled when the adb server settings change, so the tracker drops
+    /// its connection and reconnects to the new server.
+    pub reconnect: Notify,
+}
+
+impl DeviceTrackerState {
+    pub fn new() -> Self {
+        Self {
+            devices: Mutex::new(Vec::new()),
+            reconnect: Notify::new(),
+        }
+    }
+
+    /// Store a new listing and return what changed since the last one.
+    pub fn update(&self, devices: Vec<AdbDevice>) -> Vec<DeviceEvent> {
+        let Ok(mut known) = self.devices.lock() else {
+            return Vec::new();
+        };
+        let events = diff_devices(&known, &devices);
+        *known = devices;
+        events
+    }
+
+    /// The tracking connection is gone. Every known device is reported
+    /// detached, so it is attached afresh (and its presets re-applied) once
+    /// the server lists it again.
+    pub fn connection_lost(&self) -> Vec<DeviceEvent> {
+        self.update(Vec::new())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Diffing
+// ---------------------------------------------------------------------------
+
+/// Compare two device snapshots and return what changed, keyed by serial.
+pub fn diff_devices(old: &[AdbDevice], new: &[AdbDevice]) -> Vec<DeviceEvent> {
+    let mut events = Vec::new();
+
+    for device in new {
+        match old.iter().find(|d| d.serial == device.serial) {
+            None => events.push(DeviceEvent::Attached(device.clone())),
+            Some(prev) if prev.state != device.state => {
+                events.push(DeviceEvent::StateChanged(DeviceStateChange {
+                    serial: device.serial.clone(),
+                    old_state: prev.state.clone(),
+                    new_state: device.state.clone(),
+                    device: device.clone(),
+                }));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for device in old {
+        if !new.iter().any(|d| d.serial == device.serial) {
+            events.push(DeviceEvent::Detached(device.clone()));
+        }
+    }
+
+    events
+}
+
+// ---------------------------------------------------------------------------
+// Background watcher
+// ---------------------------------------------------------------------------
+
+/// Spawn the tracker task. It keeps a `host:track-devices-l` connection open,
+/// emits `device-attached` / `device-detached` / `device-state-changed` and
+/// `devices-changed` (full list), and reconnects when the adb server restarts
+/// or the server settings change. Saved Wi-Fi endpoints are reconnected once
+/// a lost server connection is back.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<DeviceTrackerState>();
+        let mut backoff = Duration::from_secs(1);
+        // Wi-Fi connections die with the server; launch reconnects on its own
+        let mut reconnect_wireless = false;
+
+        loop {
+            let stream = match transport::native_client(&app) {
+                Ok(client) => client.track_devices().await,
+                Err(e) => Err(e),
+            };
+            match stream {
+                Ok(mut stream) => {
+                    backoff = Duration::from_secs(1);
+                    if std::mem::take(&mut reconnect_wireless) {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            crate::reconnect_saved_endpoints(&app).await;
+                        });
+                    }
+                    let settings_changed = loop {
+                        let listing = tokio::select! {
+                            _ = state.reconnect.notified() => break true,
+                            listing = adb_client::read_length_prefixed(&mut stream) => listing,
+                        };
+                        let Ok(listing) = listing else {
+                            break false;
+                        };
+                        apply_update(&app, adb::parse_devices_output(&listing));
+                    };
+                    apply_events(&app, state.connection_lost(), &[]);
+                    reconnect_wireless = true;
+                    if settings_changed {
+                        continue;
+                    }
+                    eprintln!("[device_tracker] connection to adb server lost, reconnecting");
+                }
+                Err(e) => eprintln!("[device_tracker] {e}"),
+            }
+
+            tokio::select! {
+                _ = state.reconnect.notified() => backoff = Duration::from_secs(1),
+                _ = tokio::time::sleep(backoff) => {
+                    backoff = (backoff * 2).min(Duration::from_secs(10));
+                }
+            }
+        }
+    });
+}
+
+/// Diff against the previous snapshot, store the new one and emit events.
+fn apply_update(app: &AppHandle, mut devices: Vec<AdbDevice>) {
+    app.state::<DeviceRegistryState>().annotate(&mut devices);
+    let events = app.state::<DeviceTrackerState>().update(devices.clone());
+    apply_events(app, events, &devices);
+}
+
+/// Emit `events` and the resulting full list.
+fn apply_events(app: &AppHandle, events: Vec<DeviceEvent>, devices: &[AdbDevice]) {
+    if events.is_empty() {
+        return;
+    }
+    health::sync_devices(app, devices);
+
+    let features = app.state::<FeatureCache>();
+    for event in events {
+        // Features come with the device's connection and can change with it
+        match &event {
+            DeviceEvent::Detached(device) => features.forget(&device.serial),
+            DeviceEvent::StateChanged(change) => features.forget(&change.serial),
+            DeviceEvent::Attached(_) => {}
+        }
+        // Forwards and reverses do not survive a reconnect
+        if let Some(serial) = event.came_online() {
+            forward::reapply_preset(app, serial);
+        }
+        let _ = match event {
+            DeviceEvent::Attached(device) => app.emit("device-attached", &device),
+            DeviceEvent::Detached(device) => app.emit("device-detached", &device),
+            DeviceEvent::StateChanged(change) => app.emit("device-state-changed", &change),
+        };
+    }
+    let _ = app.emit("devices-changed", devices);
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use fake_adb_server::{FakeAdbServer, FakeDevice};
+
+    use super::*;
+    use crate::adb_client::AdbClient;
+    use crate::error::AdbError;
+    use crate::test_utils::device;
+
+    #[test]
+    fn test_diff_attached_and_detached() {
+        let old = vec![device("A", "device"), device("B", "device")];
+        let new = vec![device("B", "device"), device("C", "device")];
+        let events = diff_devices(&old, &new);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], DeviceEvent::Attached(d) if d.serial == "C"));
+        assert!(matches!(&events[1], DeviceEvent::Detached(d) if d.serial == "A"));
+    }
+
+    #[test]
+    fn test_diff_state_changed() {
+        let old = vec![device("A", "unauthorized")];
+        let new = vec![device("A", "device")];
+        let events = diff_devices(&old, &new);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            DeviceEvent::StateChanged(change) => {
+                assert_eq!(change.old_state, "unauthorized");
+                assert_eq!(change.new_state, "device");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let list = vec![device("A", "device")];
+        assert!(diff_devices(&list, &list).is_empty());
+    }
+
+    async fn next_listing(stream: &mut tokio::net::TcpStream) -> Result<Vec<AdbDevice>, AdbError> {
+        let listing = adb_client::read_length_prefixed(stream).await?;
+        Ok(adb::parse_devices_output(&listing))
+    }
+
+    #[tokio::test]
+    async fn test_reattach_after_tracking_connection_drops() {
+        let mut server = FakeAdbServer::start(vec![FakeDevice::new("PIXEL7")])
+            .await
+            .unwrap();
+        let client = AdbClient::new("127.0.0.1", server.port());
+        let state = DeviceTrackerState::new();
+
+        let mut stream = client.track_devices().await.unwrap();
+        let events = state.update(next_listing(&mut stream).await.unwrap());
+        assert!(matches!(&events[..], [DeviceEvent::Attached(d)] if d.serial == "PIXEL7"));
+
+        server.restart().await.unwrap();
+        assert!(next_listing(&mut stream).await.is_err());
+        let events = state.connection_lost();
+        assert!(matches!(&events[..], [DeviceEvent::Detached(d)] if d.serial == "PIXEL7"));
+        assert!(state.devices.lock().unwrap().is_empty());
+
+        let mut stream = client.track_devices().await.unwrap();
+        let events = state.update(next_listing(&mut stream).await.unwrap());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].came_online(), Some("PIXEL7"));
+    }
+}
@@ -0,0 +1,399 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::error::AdbError;
+
+// ---------------------------------------------------------------------------
+// Emulator console
+//
+// Every emulator listens on 127.0.0.1:<console port>, the number in its
+// `emulator-<port>` serial. The protocol is line based: a banner ending in
+// `OK`, then one command per line, each answered with output lines and a
+// final `OK` or `KO: <reason>`. Since emulator 27 the console asks for the
+// token in `~/.emulator_console_auth_token` first; an empty file disables
+// that.
+// ---------------------------------------------------------------------------
+
+const CONSOLE_HOST: &str = "127.0.0.1";
+const CONSOLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Console port of an `emulator-5554`-style serial.
+pub fn console_port(serial: &str) -> Option<u16> {
+    serial.strip_prefix("emulator-")?.parse().ok()
+}
+
+/// Returns `~/.emulator_console_auth_token`.
+fn get_auth_token_path() -> PathBuf {
+    let home = dirs::home_dir().expect("cannot resolve home directory");
+    home.join(".emulator_console_auth_token")
+}
+
+/// The console auth token; `None` when the file is missing or empty.
+pub fn read_auth_token() -> Option<String> {
+    std::fs::read_to_string(get_auth_token_path())
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Commands QA uses to simulate conditions; see the emulator's `help`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmulatorCommand {
+    GeoFix {
+        longitude: f64,
+        latitude: f64,
+        #[serde(default)]
+        altitude: Option<f64>,
+    },
+    SmsSend {
+        sender: String,
+        text: String,
+    },
+    GsmCall {
+        number: String,
+    },
+    GsmCancel {
+        number: String,
+    },
+    /// Battery level, 0–100.
+    PowerCapacity {
+        percent: u8,
+    },
+    PowerAc {
+        connected: bool,
+    },
+    /// `gsm`, `edge`, `umts`, `lte`, `full`, … or `<up>:<down>` in kbps.
+    NetworkSpeed {
+        speed: String,
+    },
+    /// `gprs`, `edge`, `umts`, `none` or `<min>:<max>` in ms.
+    NetworkDelay {
+        delay: String,
+    },
+    SnapshotSave {
+        name: String,
+    },
+    SnapshotLoad {
+        name: String,
+    },
+    SnapshotDelete {
+        name: String,
+    },
+    SnapshotList,
+}
+
+/// A single console token: no whitespace, so it cannot run into the next
+/// argument or command.
+fn token<'a>(what: &str, value: &'a str) -> Result<&'a str, AdbError> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().any(char::is_whitespace) {
+        return Err(AdbError::InvalidConfig(format!(
+            "无效的{}: {:?}",
+            what, value
+        )));
+    }
+    Ok(value)
+}
+
+/// `<name>` or `<a>:<b>` with numbers, as taken by `network speed/delay`.
+fn profile<'a>(what: &str, value: &'a str) -> Result<&'a str, AdbError> {
+    let value = token(what, value)?;
+    let valid = match value.split_once(':') {
+        Some((a, b)) => a.parse::<u32>().is_ok() && b.parse::<u32>().is_ok(),
+        None => value.chars().all(|c| c.is_ascii_alphanumeric()),
+    };
+    if !valid {
+        return Err(AdbError::InvalidConfig(format!(
+            "无效的{}: {}",
+            what, value
+        )));
+    }
+    Ok(value)
+}
+
+impl EmulatorCommand {
+    /// The console line for this command.
+    pub fn to_line(&self) -> Result<String, AdbError> {
+        Ok(match self {
+            EmulatorCommand::GeoFix {
+                longitude,
+                latitude,
+                altitude,
+            } => {
+                if !(-180.0..=180.0).contains(longitude) || !(-90.0..=90.0).contains(latitude) {
+                    return Err(AdbError::InvalidConfig(format!(
+                        "坐标超出范围: {}, {}",
+                        longitude, latitude
+                    )));
+                }
+                match altitude {
+                    Some(altitude) => format!("geo fix {} {} {}", longitude, latitude, altitude),
+                    None => format!("geo fix {} {}", longitude, latitude),
+                }
+            }
+            EmulatorCommand::SmsSend { sender, text } => {
+                // The text is the rest of the line, so only newlines matter
+                if text.is_empty() || text.contains(['\r', '\n']) {
+                    return Err(AdbError::InvalidConfig("短信内容不能为空或包含换行".into()));
+                }
+                format!("sms send {} {}", token("号码", sender)?, text)
+            }
+            EmulatorCommand::GsmCall { number } => format!("gsm call {}", token("号码", number)?),
+            EmulatorCommand::GsmCancel { number } => {
+                format!("gsm cancel {}", token("号码", number)?)
+            }
+            EmulatorCommand::PowerCapacity { percent } => {
+                if *percent > 100 {
+                    return Err(AdbError::InvalidConfig(format!(
+                        "电量超出范围: {}",
+                        percent
+                    )));
+                }
+                format!("power capacity {}", percent)
+            }
+            EmulatorCommand::PowerAc { connected } => {
+                format!("power ac {}", if *connected { "on" } else { "off" })
+            }
+            EmulatorCommand::NetworkSpeed { speed } => {
+                format!("network speed {}", profile("网速", speed)?)
+            }
+            EmulatorCommand::NetworkDelay { delay } => {
+                format!("network delay {}", profile("延迟", delay)?)
+            }
+            EmulatorCommand::SnapshotSave { name } => {
+                format!("avd snapshot save {}", token("快照名", name)?)
+            }
+            EmulatorCommand::SnapshotLoad { name } => {
+                format!("avd snapshot load {}", token("快照名", name)?)
+            }
+            EmulatorCommand::SnapshotDelete { name } => {
+                format!("avd snapshot delete {}", token("快照名", name)?)
+            }
+            EmulatorCommand::SnapshotList => "avd snapshot list".to_string(),
+        })
+    }
+}
+
+/// Snapshot tags from `avd snapshot list`:
+///
+/// ```text
+/// List of snapshots present on all disks:
+/// ID        TAG                 VM SIZE                DATE       VM CLOCK
+/// --        default_boot           124M 2024-01-05 10:12:33   00:02:41.118
+/// ```
+pub fn parse_snapshot_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let id = parts.next()?;
+            if id != "--" && id.parse::<u32>().is_err() {
+                return None;
+            }
+            parts.next().map(str::to_string)
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Console connection
+// ---------------------------------------------------------------------------
+
+pub struct EmulatorConsole {
+    stream: BufReader<TcpStream>,
+}
+
+impl EmulatorConsole {
+    /// Connect to `host:port`, read the banner and authenticate with `token`
+    /// if the console asks for it.
+    pub async fn connect(host: &str, port: u16, token: Option<&str>) -> Result<Self, AdbError> {
+        let stream = tokio::time::timeout(CONSOLE_TIMEOUT, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| AdbError::Timeout(format!("连接模拟器控制台 {}:{} 超时", host, port)))?
+            .map_err(|e| {
+                AdbError::DeviceNotFound(format!("模拟器控制台 {}:{}: {}", host, port, e))
+            })?;
+        let mut console = Self {
+            stream: BufReader::new(stream),
+        };
+
+        let banner = console.read_reply().await?;
+        if banner.contains("Authentication required") {
+            let token = token.ok_or_else(|| {
+                AdbError::Unauthorized(format!(
+                    "模拟器控制台需要 {} 中的令牌",
+                    get_auth_token_path().display()
+                ))
+            })?;
+            console
+                .command(&format!("auth {}", token))
+                .await
+                .map_err(|e| AdbError::Unauthorized(e.to_string()))?;
+        }
+        Ok(console)
+    }
+
+    /// Send one command line and return its output without the final `OK`.
+    pub async fn command(&mut self, line: &str) -> Result<String, AdbError> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{}\n", line).as_bytes())
+            .await
+            .map_err(|e| AdbError::Io(format!("emulator console: {e}")))?;
+        self.read_reply().await
+    }
+
+    /// Read lines up to `OK` or `KO: <reason>`.
+    async fn read_reply(&mut self) -> Result<String, AdbError> {
+        let mut output = String::new();
+        loop {
+            let mut line = String::new();
+            let read = tokio::time::timeout(CONSOLE_TIMEOUT, self.stream.read_line(&mut line))
+                .await
+                .map_err(|_| AdbError::Timeout("模拟器控制台无响应".into()))?
+                .map_err(|e| AdbError::Io(format!("emulator console: {e}")))?;
+            if read == 0 {
+                return Err(AdbError::Protocol("模拟器控制台连接已关闭".into()));
+            }
+            let line = line.trim_end();
+            if line == "OK" {
+                return Ok(output);
+            }
+            if let Some(reason) = line.strip_prefix("KO:") {
+                return Err(AdbError::Command(reason.trim().to_string()));
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+}
+
+/// Run `command` on the emulator behind `serial`.
+pub async fn run_command(serial: &str, command: &EmulatorCommand) -> Result<String, AdbError> {
+    let line = command.to_line()?;
+    let port = console_port(serial)
+        .ok_or_else(|| AdbError::InvalidState(format!("{} 不是模拟器", serial)))?;
+    let token = read_auth_token();
+    let mut console = EmulatorConsole::connect(CONSOLE_HOST, port, token.as_deref()).await?;
+    console.command(&line).await
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// A console stand-in requiring `secret`, echoing each command back.
+    async fn fake_console() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            stream
+                .get_mut()
+                .write_all(
+                    b"Android Console: Authentication required\r\n\
+                      Android Console: type 'auth <auth_token>' to authenticate\r\n\
+                      OK\r\n",
+                )
+                .await
+                .unwrap();
+            let mut line = String::new();
+            while stream.read_line(&mut line).await.unwrap() > 0 {
+                let reply = match line.trim_end() {
+                    "auth secret" => "Android Console: type 'help' for a list of commands\r\nOK\r\n".to_string(),
+                    "auth" | "auth wrong" => "KO: authentication token does not match\r\n".to_string(),
+                    "avd snapshot list" => "List of snapshots present on all disks:\r\n\
+                                            ID        TAG                 VM SIZE                DATE       VM CLOCK\r\n\
+                                            --        default_boot           124M 2024-01-05 10:12:33   00:02:41.118\r\n\
+                                            --        low_battery            131M 2024-01-06 09:00:01   00:05:10.002\r\n\
+                                            OK\r\n"
+                        .to_string(),
+                    "power capacity 5" => "OK\r\n".to_string(),
+                    other => format!("KO: unknown command '{}'\r\n", other),
+                };
+                stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+                line.clear();
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_command_lines() {
+        assert_eq!(console_port("emulator-5556"), Some(5556));
+        assert_eq!(console_port("R58M123ABC"), None);
+
+        let line = |c: EmulatorCommand| c.to_line();
+        assert_eq!(
+            line(EmulatorCommand::GeoFix {
+                longitude: -122.084,
+                latitude: 37.422,
+                altitude: None
+            })
+            .unwrap(),
+            "geo fix -122.084 37.422"
+        );
+        assert_eq!(
+            line(EmulatorCommand::SmsSend {
+                sender: "+15551234".into(),
+                text: "Your code is 1234".into()
+            })
+            .unwrap(),
+            "sms send +15551234 Your code is 1234"
+        );
+        assert_eq!(
+            line(EmulatorCommand::NetworkDelay {
+                delay: "200:800".into()
+            })
+            .unwrap(),
+            "network delay 200:800"
+        );
+        assert!(line(EmulatorCommand::GsmCall {
+            number: "555\nkill".into()
+        })
+        .is_err());
+        assert!(line(EmulatorCommand::NetworkSpeed {
+            speed: "lte;quit".into()
+        })
+        .is_err());
+        assert!(line(EmulatorCommand::PowerCapacity { percent: 101 }).is_err());
+
+        let command: EmulatorCommand =
+            serde_json::from_str(r#"{"power_ac":{"connected":false}}"#).unwrap();
+        assert_eq!(command.to_line().unwrap(), "power ac off");
+    }
+
+    #[tokio::test]
+    async fn test_console_session() {
+        let port = fake_console().await;
+        let mut console = EmulatorConsole::connect("127.0.0.1", port, Some("secret"))
+            .await
+            .unwrap();
+        let output = console.command("avd snapshot list").await.unwrap();
+        assert_eq!(
+            parse_snapshot_list(&output),
+            vec!["default_boot", "low_battery"]
+        );
+        assert_eq!(console.command("power capacity 5").await.unwrap(), "");
+        let err = console.command("geo nmea").await.unwrap_err();
+        assert!(matches!(err, AdbError::Command(ref m) if m.contains("unknown command")));
+
+        let port = fake_console().await;
+        let err = EmulatorConsole::connect("127.0.0.1", port, Some("wrong"))
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, AdbError::Unauthorized(_)));
+    }
+}
@@ -0,0 +1,243 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::error_codes;
+
+// ---------------------------------------------------------------------------
+// Error type shared by the adb, logcat, op_log and apk_parser modules
+// ---------------------------------------------------------------------------
+
+/// Errors surfaced to the frontend. Each variant has a stable `code()` to
+/// branch on and a localized `message()`; the payload keeps the raw detail
+/// (adb stderr, path, io error) for the op log and for debugging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdbError {
+    DeviceNotFound(String),
+    Unauthorized(String),
+    Offline(String),
+    MultipleDevices(String),
+    PermissionDenied(String),
+    NoSuchPath(String),
+    Timeout(String),
+    Cancelled,
+    SpawnFailed(String),
+    InstallFailure {
+        code: String,
+    },
+    /// The adb server could not be reached over TCP.
+    ServerUnavailable(String),
+    /// Unexpected or truncated response on the adb wire protocol.
+    Protocol(String),
+    /// Local file system error.
+    Io(String),
+    InvalidApk(String),
+    /// A setting value was rejected.
+    InvalidConfig(String),
+    /// Request does not fit the current state (stream already running, ...).
+    InvalidState(String),
+    /// adb reported an error we do not classify further.
+    Command(String),
+}
+
+impl AdbError {
+    /// Classify an error message printed by adb or the adb server.
+    pub fn from_adb_message(message: &str) -> Self {
+        let detail = message.trim().to_string();
+        let lower = detail.to_lowercase();
+
+        if let Some(code) = extract_failure_code(&detail) {
+            AdbError::InstallFailure { code }
+        } else if lower.contains("unauthorized") {
+            AdbError::Unauthorized(detail)
+        } else if lower.contains("offline") {
+            AdbError::Offline(detail)
+        } else if lower.contains("more than one device") || lower.contains("more than one emulator")
+        {
+            AdbError::MultipleDevices(detail)
+        } else if (lower.contains("not found") && lower.contains("device"))
+            || lower.contains("no devices/emulators found")
+        {
+            AdbError::DeviceNotFound(detail)
+        } else if lower.contains("timed out") {
+            AdbError::Timeout(detail)
+        } else if lower.contains("permission denied") {
+            AdbError::PermissionDenied(detail)
+        } else if lower.contains("no such file or directory") {
+            AdbError::NoSuchPath(detail)
+        } else {
+            AdbError::Command(detail)
+        }
+    }
+
+    /// Stable identifier for the frontend.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AdbError::DeviceNotFound(_) => "DEVICE_NOT_FOUND",
+            AdbError::Unauthorized(_) => "UNAUTHORIZED",
+            AdbError::Offline(_) => "OFFLINE",
+            AdbError::MultipleDevices(_) => "MULTIPLE_DEVICES",
+            AdbError::PermissionDenied(_) => "PERMISSION_DENIED",
+            AdbError::NoSuchPath(_) => "NO_SUCH_PATH",
+            AdbError::Timeout(_) => "TIMEOUT",
+            AdbError::Cancelled => "CANCELLED",
+            AdbError::SpawnFailed(_) => "SPAWN_FAILED",
+            AdbError::InstallFailure { .. } => "INSTALL_FAILURE",
+            AdbError::ServerUnavailable(_) => "SERVER_UNAVAILABLE",
+            AdbError::Protocol(_) => "PROTOCOL_ERROR",
+            AdbError::Io(_) => "IO_ERROR",
+            AdbError::InvalidApk(_) => "INVALID_APK",
+            AdbError::InvalidConfig(_) => "INVALID_CONFIG",
+            AdbError::InvalidState(_) => "INVALID_STATE",
+            AdbError::Command(_) => "COMMAND_FAILED",
+        }
+    }
+
+    /// Localized (Chinese) message for display.
+    pub fn message(&self) -> String {
+        match self {
+            AdbError::DeviceNotFound(_) => "设备未找到，请检查连接".to_string(),
+            AdbError::Unauthorized(_) => "设备未授权，请在手机上允许 USB 调试".to_string(),
+            AdbError::Offline(_) => "设备离线，请重新连接".to_string(),
+            AdbError::MultipleDevices(_) => "连接了多台设备，请指定目标设备".to_string(),
+            AdbError::PermissionDenied(_) => "权限不足".to_string(),
+            AdbError::NoSuchPath(_) => "文件或目录不存在".to_string(),
+            AdbError::Timeout(_) => "操作超时".to_string(),
+            AdbError::Cancelled => "操作已取消".to_string(),
+            AdbError::SpawnFailed(_) => "无法启动 adb".to_string(),
+            AdbError::InstallFailure { code } => error_codes::translate_error(code).0,
+            AdbError::ServerUnavailable(_) => "无法连接 adb 服务".to_string(),
+            AdbError::Protocol(_) => "与 adb 服务通信失败".to_string(),
+            AdbError::Io(_) => "本地文件读写失败".to_string(),
+            AdbError::InvalidApk(_) => "APK 文件无效".to_string(),
+            AdbError::InvalidConfig(_) => "设置无效".to_string(),
+            AdbError::InvalidState(msg) => msg.clone(),
+            AdbError::Command(_) => "adb 命令执行失败".to_string(),
+        }
+    }
+
+    /// Raw detail behind the error (adb output, path, io error text).
+    pub fn detail(&self) -> String {
+        match self {
+            AdbError::Cancelled | AdbError::InvalidState(_) => String::new(),
+            AdbError::InstallFailure { code } => code.clone(),
+            AdbError::DeviceNotFound(d)
+            | AdbError::Unauthorized(d)
+            | AdbError::Offline(d)
+            | AdbError::MultipleDevices(d)
+            | AdbError::PermissionDenied(d)
+            | AdbError::NoSuchPath(d)
+            | AdbError::Timeout(d)
+            | AdbError::SpawnFailed(d)
+            | AdbError::ServerUnavailable(d)
+            | AdbError::Protocol(d)
+            | AdbError::Io(d)
+            | AdbError::InvalidApk(d)
+            | AdbError::InvalidConfig(d)
+            | AdbError::Command(d) => d.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for AdbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let detail = self.detail();
+        if detail.is_empty() {
+            write!(f, "{}", self.message())
+        } else {
+            write!(f, "{}: {}", self.message(), detail)
+        }
+    }
+}
+
+impl std::error::Error for AdbError {}
+
+/// Serialized to the frontend as `{ "code", "message", "detail" }`.
+impl Serialize for AdbError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AdbError", 3)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.message())?;
+        s.serialize_field("detail", &self.detail())?;
+        s.end()
+    }
+}
+
+/// Pull `CODE` out of "Failure [CODE]" / "Failure [CODE: detail]".
+pub fn extract_failure_code(output: &str) -> Option<String> {
+    let start = output.find("Failure [")?;
+    let after = &output[start + 9..]; // skip "Failure ["
+    let end = after.find(']')?;
+    // Handle "CODE: detail" format — take only the code part
+    Some(after[..end].split(':').next()?.trim().to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_adb_messages() {
+        assert_eq!(
+            AdbError::from_adb_message("adb: device 'XYZ' not found").code(),
+            "DEVICE_NOT_FOUND"
+        );
+        assert_eq!(
+            AdbError::from_adb_message(
+                "error: device unauthorized.\nThis adb server's $ADB_VENDOR_KEYS is not set"
+            )
+            .code(),
+            "UNAUTHORIZED"
+        );
+        assert_eq!(
+            AdbError::from_adb_message("error: device offline").code(),
+            "OFFLINE"
+        );
+        assert_eq!(
+            AdbError::from_adb_message("error: more than one device/emulator").code(),
+            "MULTIPLE_DEVICES"
+        );
+        assert_eq!(
+            AdbError::from_adb_message("rm: /system/app: Permission denied").code(),
+            "PERMISSION_DENIED"
+        );
+        assert_eq!(
+            AdbError::from_adb_message("ls: /sdcard/nope: No such file or directory").code(),
+            "NO_SUCH_PATH"
+        );
+        assert_eq!(
+            AdbError::from_adb_message(
+                "failed to connect to '10.0.0.5:5555': Connection timed out"
+            )
+            .code(),
+            "TIMEOUT"
+        );
+        assert_eq!(
+            AdbError::from_adb_message("something odd").code(),
+            "COMMAND_FAILED"
+        );
+    }
+
+    #[test]
+    fn test_install_failure_code() {
+        let err = AdbError::from_adb_message("Failure [DELETE_FAILED_INTERNAL_ERROR]");
+        assert_eq!(
+            err,
+            AdbError::InstallFailure {
+                code: "DELETE_FAILED_INTERNAL_ERROR".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_serialize_structured() {
+        let err = AdbError::NoSuchPath("/sdcard/x".to_string());
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "NO_SUCH_PATH");
+        assert_eq!(json["message"], "文件或目录不存在");
+        assert_eq!(json["detail"], "/sdcard/x");
+    }
+}
@@ -0,0 +1,506 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AdbError;
+use crate::op_log::{self, OpLogEntry, OpLogState};
+use crate::operation::OpClass;
+use crate::storage;
+use crate::transport::{AdbTransport, AppTransport};
+
+// ---------------------------------------------------------------------------
+// Port forwarding (`adb forward` / `adb reverse`)
+//
+// Forwards live in the adb server and disappear with the device's transport;
+// reverses live in adbd and disappear when it restarts. Both are therefore
+// re-applied from the device's preset whenever it comes back online.
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardDirection {
+    /// Host socket → device socket (`adb forward`).
+    Forward,
+    /// Device socket → host socket (`adb reverse`).
+    Reverse,
+}
+
+impl ForwardDirection {
+    pub fn command(self) -> &'static str {
+        match self {
+            ForwardDirection::Forward => "forward",
+            ForwardDirection::Reverse => "reverse",
+        }
+    }
+}
+
+/// One rule, independent of direction: `host` is always the computer side and
+/// `device` the phone side, e.g. Metro is `reverse` `tcp:8081` ↔ `tcp:8081`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRule {
+    pub direction: ForwardDirection,
+    pub host: String,
+    pub device: String,
+}
+
+impl PortRule {
+    /// Arguments after `adb -s SERIAL`; adb puts the listening side first.
+    fn add_args(&self) -> Vec<&str> {
+        match self.direction {
+            ForwardDirection::Forward => vec!["forward", &self.host, &self.device],
+            ForwardDirection::Reverse => vec!["reverse", &self.device, &self.host],
+        }
+    }
+
+    /// The listening side, which is what `--remove` takes.
+    fn listener(&self) -> &str {
+        match self.direction {
+            ForwardDirection::Forward => &self.host,
+            ForwardDirection::Reverse => &self.device,
+        }
+    }
+
+    /// The `adb` command line that adds (or removes) this rule, for the op log.
+    pub fn command_line(&self, serial: &str, remove: bool) -> String {
+        let args = if remove {
+            vec![self.direction.command(), "--remove", self.listener()]
+        } else {
+            self.add_args()
+        };
+        format!("adb -s {} {}", serial, args.join(" "))
+    }
+
+    pub fn describe(&self) -> String {
+        match self.direction {
+            ForwardDirection::Forward => format!("forward {} → {}", self.host, self.device),
+            ForwardDirection::Reverse => format!("reverse {} → {}", self.device, self.host),
+        }
+    }
+}
+
+/// Socket kinds adb accepts on a listening side.
+const LISTEN_KINDS: &[&str] = &["tcp", "localabstract", "localreserved", "localfilesystem"];
+
+/// Check a socket spec such as `tcp:8081`, `localabstract:chrome_devtools_remote`
+/// or `jdwp:1234`. `jdwp:`, `vsock:` and `dev:` only exist on the device and
+/// are never a listening side.
+fn validate_spec(spec: &str, listening: bool, on_device: bool) -> Result<(), AdbError> {
+    let invalid = || AdbError::InvalidConfig(format!("无效的端口规则: {}", spec));
+    let (kind, value) = spec.split_once(':').ok_or_else(invalid)?;
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    let allowed = LISTEN_KINDS.contains(&kind)
+        || (!listening && on_device && matches!(kind, "jdwp" | "vsock" | "dev"));
+    if !allowed {
+        return Err(invalid());
+    }
+    match kind {
+        // `tcp:0` lets adb pick a free port to listen on
+        "tcp" => match value.parse::<u16>() {
+            Ok(0) if !listening => Err(invalid()),
+            Ok(_) => Ok(()),
+            Err(_) => Err(invalid()),
+        },
+        "jdwp" if value.parse::<u32>().is_err() => Err(invalid()),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_rule(rule: &PortRule) -> Result<(), AdbError> {
+    let forward = rule.direction == ForwardDirection::Forward;
+    validate_spec(&rule.host, forward, false)?;
+    validate_spec(&rule.device, !forward, true)
+}
+
+// ---------------------------------------------------------------------------
+// Listing
+// ---------------------------------------------------------------------------
+
+/// Parse `adb forward --list` (`SERIAL host device`, all devices) keeping
+/// `serial`'s rules.
+pub fn parse_forward_list(output: &str, serial: &str) -> Vec<PortRule> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                [s, host, device] if *s == serial => Some(PortRule {
+                    direction: ForwardDirection::Forward,
+                    host: host.to_string(),
+                    device: device.to_string(),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parse `adb reverse --list` (`TRANSPORT device host`, one device).
+pub fn parse_reverse_list(output: &str) -> Vec<PortRule> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                [_, device, host] => Some(PortRule {
+                    direction: ForwardDirection::Reverse,
+                    host: host.to_string(),
+                    device: device.to_string(),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Active forwards and reverses of `serial`, forwards first.
+pub async fn list_rules(adb: &impl AdbTransport, serial: &str) -> Result<Vec<PortRule>, AdbError> {
+    let forwards = adb.exec(&["forward", "--list"], OpClass::Fast).await?;
+    let reverses = adb
+        .exec_device(serial, &["reverse", "--list"], OpClass::Fast)
+        .await?;
+    let mut rules = parse_forward_list(&forwards, serial);
+    rules.extend(parse_reverse_list(&reverses));
+    Ok(rules)
+}
+
+// ---------------------------------------------------------------------------
+// Adding and removing
+// ---------------------------------------------------------------------------
+
+/// adb reports forward/reverse errors on stdout/stderr with exit status 1.
+async fn run_checked(
+    adb: &impl AdbTransport,
+    serial: &str,
+    args: &[&str],
+) -> Result<String, AdbError> {
+    let mut full_args = vec!["-s", serial];
+    full_args.extend_from_slice(args);
+    let output = adb.run(&full_args, OpClass::Fast).await?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.success() && !stdout.starts_with("error:") {
+        return Ok(stdout);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let message = if stderr.is_empty() { stdout } else { stderr };
+    Err(AdbError::from_adb_message(&message))
+}
+
+/// Set up `rule`, replacing an existing rule on the same listening socket.
+/// Returns adb's output, which is the allocated port for `tcp:0` forwards.
+pub async fn add_rule(
+    adb: &impl AdbTransport,
+    serial: &str,
+    rule: &PortRule,
+) -> Result<String, AdbError> {
+    validate_rule(rule)?;
+    run_checked(adb, serial, &rule.add_args()).await
+}
+
+pub async fn remove_rule(
+    adb: &impl AdbTransport,
+    serial: &str,
+    rule: &PortRule,
+) -> Result<(), AdbError> {
+    let args = [rule.direction.command(), "--remove", rule.listener()];
+    run_checked(adb, serial, &args).await.map(|_| ())
+}
+
+/// Remove every rule of `serial` in `direction`, or in both when `None`.
+/// `adb forward --remove-all` would drop other devices' forwards too, so
+/// forwards are removed one by one.
+pub async fn remove_all(
+    adb: &impl AdbTransport,
+    serial: &str,
+    direction: Option<ForwardDirection>,
+) -> Result<usize, AdbError> {
+    let rules = list_rules(adb, serial).await?;
+    let mut removed = 0;
+    if direction != Some(ForwardDirection::Reverse) {
+        for rule in rules
+            .iter()
+            .filter(|r| r.direction == ForwardDirection::Forward)
+        {
+            remove_rule(adb, serial, rule).await?;
+            removed += 1;
+        }
+    }
+    if direction != Some(ForwardDirection::Forward) {
+        run_checked(adb, serial, &["reverse", "--remove-all"]).await?;
+        removed += rules
+            .iter()
+            .filter(|r| r.direction == ForwardDirection::Reverse)
+            .count();
+    }
+    Ok(removed)
+}
+
+// ---------------------------------------------------------------------------
+// Presets
+// ---------------------------------------------------------------------------
+
+/// Managed state: rules per serial, re-applied on reconnect.
+pub struct ForwardState {
+    pub presets: Mutex<BTreeMap<String, Vec<PortRule>>>,
+}
+
+/// `~/AdbTool/forwards.json`; a missing or unreadable file means no presets.
+const PRESETS_FILE: &str = "forwards.json";
+
+impl ForwardState {
+    /// Create state, pre-loading saved presets from disk.
+    pub fn new() -> Self {
+        Self {
+            presets: Mutex::new(storage::load_or_default(PRESETS_FILE)),
+        }
+    }
+
+    pub fn all(&self) -> BTreeMap<String, Vec<PortRule>> {
+        self.presets.lock().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    pub fn get(&self, serial: &str) -> Vec<PortRule> {
+        self.presets
+            .lock()
+            .ok()
+            .and_then(|p| p.get(serial).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Replace the preset of `serial`; an empty list removes it.
+    pub fn set(&self, serial: &str, rules: Vec<PortRule>) -> Result<(), AdbError> {
+        for rule in &rules {
+            validate_rule(rule)?;
+        }
+        let mut presets = self
+            .presets
+            .lock()
+            .map_err(|e| AdbError::InvalidState(e.to_string()))?;
+        if rules.is_empty() {
+            presets.remove(serial);
+        } else {
+            presets.insert(serial.to_string(), rules);
+        }
+        storage::write(PRESETS_FILE, &*presets)
+    }
+
+    /// Add `rule` to the preset, replacing one on the same listening socket.
+    pub fn remember(&self, serial: &str, rule: &PortRule) -> Result<(), AdbError> {
+        let mut rules = self.get(serial);
+        rules.retain(|r| !(r.direction == rule.direction && r.listener() == rule.listener()));
+        rules.push(rule.clone());
+        self.set(serial, rules)
+    }
+
+    pub fn forget(&self, serial: &str, rule: &PortRule) -> Result<(), AdbError> {
+        let mut rules = self.get(serial);
+        rules.retain(|r| !(r.direction == rule.direction && r.listener() == rule.listener()));
+        self.set(serial, rules)
+    }
+}
+
+impl Default for ForwardState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply `rules` to `serial`, returning the ones that failed with the error.
+pub async fn apply_rules(
+    adb: &impl AdbTransport,
+    serial: &str,
+    rules: &[PortRule],
+) -> Vec<(PortRule, AdbError)> {
+    let mut failed = Vec::new();
+    for rule in rules {
+        if let Err(e) = add_rule(adb, serial, rule).await {
+            failed.push((rule.clone(), e));
+        }
+    }
+    failed
+}
+
+/// Re-apply the saved preset of `serial`. Returns the op log entry for the
+/// attempt, or `None` when the device has no preset.
+pub async fn reapply_rules(
+    adb: &impl AdbTransport,
+    state: &ForwardState,
+    serial: &str,
+) -> Option<OpLogEntry> {
+    let rules = state.get(serial);
+    if rules.is_empty() {
+        return None;
+    }
+    let failed = apply_rules(adb, serial, &rules).await;
+    let error_message = (!failed.is_empty()).then(|| {
+        failed
+            .iter()
+            .map(|(rule, e)| format!("{}: {}", rule.describe(), e))
+            .collect::<Vec<_>>()
+            .join("; ")
+    });
+    Some(OpLogEntry {
+        error_message,
+        ..OpLogEntry::new(
+            "forward",
+            serial,
+            format!(
+                "重新应用端口规则 {}/{}",
+                rules.len() - failed.len(),
+                rules.len()
+            ),
+            failed.is_empty(),
+        )
+    })
+}
+
+/// Re-apply the saved preset of a device that just came online. Called by
+/// the device tracker; failures end up in the op log.
+pub fn reapply_preset(app: &AppHandle, serial: &str) {
+    let app = app.clone();
+    let serial = serial.to_string();
+    tauri::async_runtime::spawn(async move {
+        let adb = AppTransport::new(&app);
+        if let Some(entry) = reapply_rules(&adb, &app.state::<ForwardState>(), &serial).await {
+            op_log::add_entry(&app.state::<OpLogState>(), entry);
+        }
+    });
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use fake_adb_server::{FakeAdbServer, FakeDevice};
+
+    use super::*;
+    use crate::adb_client::AdbClient;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::NativeTransport;
+
+    fn rule(direction: ForwardDirection, host: &str, device: &str) -> PortRule {
+        PortRule {
+            direction,
+            host: host.to_string(),
+            device: device.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_rule() {
+        use ForwardDirection::*;
+        for ok in [
+            rule(Reverse, "tcp:8081", "tcp:8081"),
+            rule(Forward, "tcp:0", "localabstract:chrome_devtools_remote"),
+            rule(Forward, "tcp:8700", "jdwp:4242"),
+            rule(Forward, "localfilesystem:/tmp/dbg", "localreserved:debug"),
+        ] {
+            assert!(validate_rule(&ok).is_ok(), "{}", ok.describe());
+        }
+        for bad in [
+            rule(Reverse, "tcp:8081", "jdwp:4242"),
+            rule(Reverse, "tcp:0", "tcp:8081"),
+            rule(Forward, "tcp:8081", "tcp:0"),
+            rule(Forward, "tcp:70000", "tcp:80"),
+            rule(Forward, "jdwp:1", "tcp:80"),
+            rule(Forward, "tcp:8700", "jdwp:app"),
+            rule(Forward, "8080", "tcp:8080"),
+        ] {
+            assert!(validate_rule(&bad).is_err(), "{}", bad.describe());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_rules() {
+        let mock = MockTransport::from_transcript(
+            "$ adb forward --list\n\
+             SER tcp:8700 jdwp:4242\n\
+             OTHER tcp:9222 localabstract:chrome_devtools_remote\n\
+             SER tcp:9222 localabstract:chrome_devtools_remote\n\
+             $ adb -s SER reverse --list\n\
+             UsbFfs tcp:8081 tcp:8081\n",
+        );
+        let rules = list_rules(&mock, "SER").await.unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                rule(ForwardDirection::Forward, "tcp:8700", "jdwp:4242"),
+                rule(
+                    ForwardDirection::Forward,
+                    "tcp:9222",
+                    "localabstract:chrome_devtools_remote"
+                ),
+                rule(ForwardDirection::Reverse, "tcp:8081", "tcp:8081"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_all() {
+        let mock = MockTransport::from_transcript(
+            "$ adb -s SER reverse tcp:8081 tcp:8082\n\
+             $ adb -s SER forward tcp:0 tcp:5000\n\
+             41337\n\
+             $ adb -s SER forward tcp:8700 jdwp:1\n\
+             error: cannot bind listener: Address already in use\n\
+             [exit 1]\n\
+             $ adb forward --list\n\
+             SER tcp:41337 tcp:5000\n\
+             $ adb -s SER reverse --list\n\
+             UsbFfs tcp:8081 tcp:8082\n\
+             $ adb -s SER forward --remove tcp:41337\n\
+             $ adb -s SER reverse --remove-all\n",
+        );
+        let metro = rule(ForwardDirection::Reverse, "tcp:8082", "tcp:8081");
+        assert_eq!(add_rule(&mock, "SER", &metro).await.unwrap(), "");
+        let any_port = rule(ForwardDirection::Forward, "tcp:0", "tcp:5000");
+        assert_eq!(add_rule(&mock, "SER", &any_port).await.unwrap(), "41337");
+        let taken = rule(ForwardDirection::Forward, "tcp:8700", "jdwp:1");
+        assert!(add_rule(&mock, "SER", &taken)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("Address already in use"));
+
+        assert_eq!(remove_all(&mock, "SER", None).await.unwrap(), 2);
+        assert!(mock
+            .calls()
+            .contains(&"-s SER forward --remove tcp:41337".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_reapply_rules_after_server_restart() {
+        let mut server = FakeAdbServer::start(vec![FakeDevice::new("PIXEL7")])
+            .await
+            .unwrap();
+        let adb = NativeTransport::new(AdbClient::new("127.0.0.1", server.port()));
+        let devtools = rule(
+            ForwardDirection::Forward,
+            "tcp:9222",
+            "localabstract:chrome_devtools_remote",
+        );
+        let state = ForwardState {
+            presets: Mutex::new(BTreeMap::from([(
+                "PIXEL7".to_string(),
+                vec![devtools.clone()],
+            )])),
+        };
+        assert!(reapply_rules(&adb, &state, "OTHER").await.is_none());
+
+        let entry = reapply_rules(&adb, &state, "PIXEL7").await.unwrap();
+        assert!(entry.success);
+        assert_eq!(server.forwards().len(), 1);
+
+        // Forwards die with the server
+        server.restart().await.unwrap();
+        assert!(server.forwards().is_empty());
+        let entry = reapply_rules(&adb, &state, "PIXEL7").await.unwrap();
+        assert!(entry.success, "{:?}", entry.error_message);
+        assert_eq!(list_rules(&adb, "PIXEL7").await.unwrap(), vec![devtools]);
+    }
+}
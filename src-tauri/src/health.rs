@@ -0,0 +1,481 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio_util::sync::CancellationToken;
+
+use crate::adb::AdbDevice;
+use crate::device_info;
+use crate::device_tracker::DeviceTrackerState;
+use crate::error::AdbError;
+use crate::op_log::{self, OpLogEntry, OpLogState};
+use crate::operation::OpClass;
+use crate::storage;
+use crate::transport::{AdbTransport, AppTransport};
+
+// ---------------------------------------------------------------------------
+// Health monitor
+//
+// While enabled, every online device gets a sampler task that runs the probes
+// below in one shell call per interval, keeps a bounded time series and emits
+// `device-health-{serial}`. Crossing a threshold emits `device-health-alert`
+// and writes a failed `health_alert` op log entry, once per crossing; the
+// alert re-arms when the value recovers. The tracker starts and stops
+// samplers as devices come and go.
+// ---------------------------------------------------------------------------
+
+const HEALTH_PROBES: &[(&str, &str)] = &[
+    ("battery", "dumpsys battery"),
+    ("df", "df /data"),
+    ("meminfo", "cat /proc/meminfo"),
+    ("thermal", "dumpsys thermalservice"),
+];
+
+/// Shortest sampling interval accepted by [`set_config`].
+const MIN_INTERVAL_SECS: u64 = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_interval")]
+    pub interval_secs: u64,
+    /// Samples kept per device.
+    #[serde(default = "default_history_len")]
+    pub history_len: usize,
+    #[serde(default = "default_min_free_storage")]
+    pub min_free_storage_mb: u64,
+    #[serde(default = "default_max_battery_temp")]
+    pub max_battery_temp_c: f32,
+    #[serde(default = "default_min_available_ram")]
+    pub min_available_ram_mb: u64,
+    /// Alert at this thermal status or above (3 = severe).
+    #[serde(default = "default_thermal_alert_status")]
+    pub thermal_alert_status: u8,
+}
+
+fn default_interval() -> u64 {
+    30
+}
+
+fn default_history_len() -> usize {
+    120
+}
+
+fn default_min_free_storage() -> u64 {
+    500
+}
+
+fn default_max_battery_temp() -> f32 {
+    45.0
+}
+
+fn default_min_available_ram() -> u64 {
+    200
+}
+
+fn default_thermal_alert_status() -> u8 {
+    3
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_interval(),
+            history_len: default_history_len(),
+            min_free_storage_mb: default_min_free_storage(),
+            max_battery_temp_c: default_max_battery_temp(),
+            min_available_ram_mb: default_min_available_ram(),
+            thermal_alert_status: default_thermal_alert_status(),
+        }
+    }
+}
+
+/// One reading. Fields are `None` when the device did not report them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthSample {
+    pub timestamp_ms: i64,
+    pub battery_level: Option<u8>,
+    pub battery_temp_c: Option<f32>,
+    pub storage_free_mb: Option<u64>,
+    pub ram_available_mb: Option<u64>,
+    pub thermal_status: Option<u8>,
+}
+
+/// Payload for `device-health-{serial}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthUpdate {
+    pub serial: String,
+    pub sample: HealthSample,
+    /// Oldest first, including `sample`.
+    pub series: Vec<HealthSample>,
+    /// Kinds of the thresholds currently crossed.
+    pub active_alerts: Vec<String>,
+}
+
+/// Payload for `device-health-alert`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthAlert {
+    pub serial: String,
+    /// `low_storage`, `high_temperature`, `low_memory` or `thermal`
+    pub kind: String,
+    pub message: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub timestamp_ms: i64,
+}
+
+struct Monitor {
+    cancel: CancellationToken,
+    history: Arc<Mutex<VecDeque<HealthSample>>>,
+}
+
+/// Managed state: saved config and the running samplers by serial.
+pub struct HealthState {
+    pub config: Mutex<HealthConfig>,
+    monitors: Mutex<HashMap<String, Monitor>>,
+}
+
+impl HealthState {
+    /// Create state, pre-loading the saved config from disk.
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(storage::load_or_default(CONFIG_FILE)),
+            monitors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> HealthConfig {
+        self.config.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// Samples collected for `serial` since its sampler started.
+    pub fn history(&self, serial: &str) -> Vec<HealthSample> {
+        let Ok(monitors) = self.monitors.lock() else {
+            return Vec::new();
+        };
+        monitors
+            .get(serial)
+            .and_then(|m| m.history.lock().ok().map(|h| h.iter().cloned().collect()))
+            .unwrap_or_default()
+    }
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Persistence helpers
+// ---------------------------------------------------------------------------
+
+/// `~/AdbTool/health.json`; a missing or unreadable config means the monitor
+/// stays off.
+const CONFIG_FILE: &str = "health.json";
+
+// ---------------------------------------------------------------------------
+// Sampling and thresholds
+// ---------------------------------------------------------------------------
+
+/// Take one reading with a single shell call.
+pub async fn sample_device(
+    adb: &impl AdbTransport,
+    serial: &str,
+) -> Result<HealthSample, AdbError> {
+    let output = adb
+        .shell(
+            serial,
+            &device_info::probe_command(HEALTH_PROBES),
+            OpClass::Fast,
+        )
+        .await?;
+    let probes = device_info::split_probes(&output.stdout);
+    let probe = |name: &str| probes.get(name).map(String::as_str).unwrap_or_default();
+
+    let storage_free_mb = device_info::parse_df(probe("df"))
+        .into_iter()
+        .find(|m| m.kind == "data")
+        .map(|m| m.free_mb);
+    let ram_available_mb = match device_info::parse_meminfo(probe("meminfo")) {
+        (0, _) => None,
+        (_, available) => Some(available),
+    };
+    Ok(HealthSample {
+        timestamp_ms: chrono::Local::now().timestamp_millis(),
+        battery_level: device_info::parse_battery(probe("battery")).0,
+        battery_temp_c: device_info::parse_battery_temperature(probe("battery")),
+        storage_free_mb,
+        ram_available_mb,
+        thermal_status: device_info::parse_thermal_status(probe("thermal")),
+    })
+}
+
+/// Every threshold `sample` crosses, as `(kind, message, value, threshold)`.
+pub fn check_thresholds(
+    config: &HealthConfig,
+    sample: &HealthSample,
+) -> Vec<(&'static str, String, f64, f64)> {
+    let mut breaches = Vec::new();
+    if let Some(free) = sample
+        .storage_free_mb
+        .filter(|&f| f < config.min_free_storage_mb)
+    {
+        breaches.push((
+            "low_storage",
+            format!("存储空间不足: 剩余 {} MB", free),
+            free as f64,
+            config.min_free_storage_mb as f64,
+        ));
+    }
+    if let Some(temp) = sample
+        .battery_temp_c
+        .filter(|&t| t > config.max_battery_temp_c)
+    {
+        breaches.push((
+            "high_temperature",
+            format!("电池温度过高: {:.1}°C", temp),
+            temp as f64,
+            config.max_battery_temp_c as f64,
+        ));
+    }
+    if let Some(ram) = sample
+        .ram_available_mb
+        .filter(|&r| r < config.min_available_ram_mb)
+    {
+        breaches.push((
+            "low_memory",
+            format!("可用内存不足: {} MB", ram),
+            ram as f64,
+            config.min_available_ram_mb as f64,
+        ));
+    }
+    if let Some(status) = sample
+        .thermal_status
+        .filter(|&s| s >= config.thermal_alert_status)
+    {
+        breaches.push((
+            "thermal",
+            format!("设备过热: thermal status {}", status),
+            status as f64,
+            config.thermal_alert_status as f64,
+        ));
+    }
+    breaches
+}
+
+// ---------------------------------------------------------------------------
+// Sampler lifecycle
+// ---------------------------------------------------------------------------
+
+/// Start samplers for online devices that lack one and stop the rest; stop
+/// everything when the monitor is disabled.
+pub fn sync_devices(app: &AppHandle, devices: &[AdbDevice]) {
+    let state = app.state::<HealthState>();
+    let enabled = state.config().enabled;
+    let Ok(mut monitors) = state.monitors.lock() else {
+        return;
+    };
+
+    let online: HashSet<&str> = devices
+        .iter()
+        .filter(|d| enabled && d.state == "device")
+        .map(|d| d.serial.as_str())
+        .collect();
+    monitors.retain(|serial, monitor| {
+        let keep = online.contains(serial.as_str());
+        if !keep {
+            monitor.cancel.cancel();
+        }
+        keep
+    });
+    for serial in online {
+        if !monitors.contains_key(serial) {
+            monitors.insert(
+                serial.to_string(),
+                spawn_sampler(app.clone(), serial.to_string()),
+            );
+        }
+    }
+}
+
+fn spawn_sampler(app: AppHandle, serial: String) -> Monitor {
+    let cancel = CancellationToken::new();
+    let history = Arc::new(Mutex::new(VecDeque::new()));
+    let monitor = Monitor {
+        cancel: cancel.clone(),
+        history: history.clone(),
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let transport = AppTransport::new(&app);
+        let event_name = format!("device-health-{}", serial);
+        let mut active: HashSet<&'static str> = HashSet::new();
+        // Logged on change only; a device that stops answering would
+        // otherwise print every interval
+        let mut failing = false;
+        loop {
+            let config = app.state::<HealthState>().config();
+            let sample = tokio::select! {
+                _ = cancel.cancelled() => break,
+                sample = sample_device(&transport, &serial) => sample,
+            };
+            match sample {
+                Ok(sample) => {
+                    if std::mem::take(&mut failing) {
+                        eprintln!("[health] {serial}: sampling recovered");
+                    }
+                    let series: Vec<HealthSample> = {
+                        let Ok(mut history) = history.lock() else {
+                            break;
+                        };
+                        history.push_back(sample.clone());
+                        while history.len() > config.history_len.max(1) {
+                            history.pop_front();
+                        }
+                        history.iter().cloned().collect()
+                    };
+
+                    let breaches = check_thresholds(&config, &sample);
+                    for (kind, message, value, threshold) in &breaches {
+                        if !active.contains(kind) {
+                            raise_alert(&app, &serial, kind, message, *value, *threshold);
+                        }
+                    }
+                    active = breaches.iter().map(|(kind, ..)| *kind).collect();
+
+                    let _ = app.emit(
+                        &event_name,
+                        HealthUpdate {
+                            serial: serial.clone(),
+                            sample,
+                            series,
+                            active_alerts: active.iter().map(|k| k.to_string()).collect(),
+                        },
+                    );
+                }
+                Err(e) => {
+                    if !std::mem::replace(&mut failing, true) {
+                        eprintln!("[health] {serial}: sampling failed: {e}");
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(Duration::from_secs(config.interval_secs)) => {}
+            }
+        }
+    });
+    monitor
+}
+
+fn raise_alert(
+    app: &AppHandle,
+    serial: &str,
+    kind: &str,
+    message: &str,
+    value: f64,
+    threshold: f64,
+) {
+    let alert = HealthAlert {
+        serial: serial.to_string(),
+        kind: kind.to_string(),
+        message: message.to_string(),
+        value,
+        threshold,
+        timestamp_ms: chrono::Local::now().timestamp_millis(),
+    };
+    let _ = app.emit("device-health-alert", &alert);
+    op_log::add_entry(
+        &app.state::<OpLogState>(),
+        OpLogEntry {
+            error_message: Some(format!("{}: {} (阈值 {})", kind, value, threshold)),
+            ..OpLogEntry::new("health_alert", serial, message, false)
+        },
+    );
+}
+
+/// Validate and save the config, then start or stop samplers to match.
+pub fn set_config(app: &AppHandle, config: HealthConfig) -> Result<HealthConfig, AdbError> {
+    if config.interval_secs < MIN_INTERVAL_SECS {
+        return Err(AdbError::InvalidConfig(format!(
+            "采样间隔不能小于 {} 秒",
+            MIN_INTERVAL_SECS
+        )));
+    }
+    storage::write(CONFIG_FILE, &config)?;
+    let state = app.state::<HealthState>();
+    if let Ok(mut current) = state.config.lock() {
+        *current = config.clone();
+    }
+    let devices = app
+        .state::<DeviceTrackerState>()
+        .devices
+        .lock()
+        .map(|d| d.clone())
+        .unwrap_or_default();
+    sync_devices(app, &devices);
+    Ok(config)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[tokio::test]
+    async fn test_sample_device() {
+        let command = device_info::probe_command(HEALTH_PROBES);
+        let transcript = format!(
+            "$ adb -s SER shell {}\n\
+             @@battery\n  status: 3\n  level: 41\n  temperature: 462\n\
+             @@df\n\
+             Filesystem     1K-blocks     Used Available Use% Mounted on\n\
+             /dev/block/dm-8 112337152 111876352  460800 100% /data\n\
+             @@meminfo\nMemTotal: 5810652 kB\nMemAvailable: 2765432 kB\n\
+             @@thermal\nIsStatusOverride: false\nThermal Status: 1\n",
+            command
+        );
+        let mock = MockTransport::from_transcript(&transcript);
+        let sample = sample_device(&mock, "SER").await.unwrap();
+        assert_eq!(sample.battery_level, Some(41));
+        assert_eq!(sample.battery_temp_c, Some(46.2));
+        assert_eq!(sample.storage_free_mb, Some(450));
+        assert_eq!(sample.ram_available_mb, Some(2700));
+        assert_eq!(sample.thermal_status, Some(1));
+
+        let kinds: Vec<&str> = check_thresholds(&HealthConfig::default(), &sample)
+            .into_iter()
+            .map(|(kind, ..)| kind)
+            .collect();
+        assert_eq!(kinds, vec!["low_storage", "high_temperature"]);
+    }
+
+    #[test]
+    fn test_check_thresholds_ignores_missing_values() {
+        let config = HealthConfig::default();
+        assert!(check_thresholds(&config, &HealthSample::default()).is_empty());
+
+        let sample = HealthSample {
+            ram_available_mb: Some(150),
+            thermal_status: Some(3),
+            storage_free_mb: Some(500),
+            battery_temp_c: Some(45.0),
+            ..Default::default()
+        };
+        let kinds: Vec<&str> = check_thresholds(&config, &sample)
+            .into_iter()
+            .map(|(kind, ..)| kind)
+            .collect();
+        assert_eq!(kinds, vec!["low_memory", "thermal"]);
+    }
+}
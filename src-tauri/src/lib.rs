@@ -1,64 +1,111 @@
-mod adb;
-mod apk_parser;
-mod error_codes;
-mod logcat;
-mod op_log;
+pub mod adb;
+pub mod adb_client;
+pub mod api;
+pub mod apk_parser;
+pub mod broadcast;
+pub mod cli;
+pub mod dev_presets;
+pub mod device_info;
+pub mod device_registry;
+pub mod device_settings;
+mod device_tracker;
+pub mod emulator;
+pub mod error;
+pub mod error_codes;
+pub mod forward;
+pub mod health;
+pub mod logcat;
+pub mod op_log;
+pub mod operation;
+pub mod props;
+pub mod reboot;
+pub mod settings;
+pub mod shell;
+pub mod storage;
+pub mod sync;
+#[cfg(test)]
+mod test_utils;
+pub mod transport;
+pub mod wireless;
 
 use tauri::Emitter;
+use tauri::Manager;
 use tauri_plugin_updater::UpdaterExt;
 
+use error::AdbError;
+use transport::AppTransport;
+
 // ---------------------------------------------------------------------------
 // Tauri commands — thin wrappers around adb module functions
 // ---------------------------------------------------------------------------
 
 #[tauri::command]
-async fn adb_version(app: tauri::AppHandle) -> Result<String, String> {
-    adb::check_server(&app).await
+async fn adb_version(app: tauri::AppHandle) -> Result<String, AdbError> {
+    adb::check_server(&AppTransport::new(&app)).await
 }
 
 #[tauri::command]
-async fn get_devices(app: tauri::AppHandle) -> Result<Vec<adb::AdbDevice>, String> {
-    adb::list_devices(&app).await
+async fn get_devices(
+    app: tauri::AppHandle,
+    registry: tauri::State<'_, device_registry::DeviceRegistryState>,
+) -> Result<Vec<adb::AdbDevice>, AdbError> {
+    let mut devices = adb::list_devices(&AppTransport::new(&app)).await?;
+    registry.annotate(&mut devices);
+    Ok(devices)
 }
 
 #[tauri::command]
 async fn get_device_detail(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
+    registry: tauri::State<'_, device_registry::DeviceRegistryState>,
     serial: String,
-) -> Result<adb::DeviceDetail, String> {
-    adb::get_device_detail(&app, &serial).await
+    op_id: Option<String>,
+) -> Result<adb::DeviceDetail, AdbError> {
+    let transport = AppTransport::new(&app);
+    let mut result = ops.run(op_id.as_deref(), adb::get_device_detail(&transport, &serial)).await;
+    log_if_interrupted(&state, &result, "device_detail", &serial, "读取设备信息");
+    if let Ok(detail) = result.as_mut() {
+        registry.annotate_detail(detail);
+    }
+    result
 }
 
 #[tauri::command]
 async fn install_apk(
     app: tauri::AppHandle,
     state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     apk_path: String,
     flags: Vec<String>,
-) -> Result<adb::InstallResult, String> {
-    // Uninstall existing app before install to avoid signature conflicts
-    if let Ok(package_name) = apk_parser::extract_package_name(&apk_path) {
-        let _ = adb::uninstall_app(&app, &serial, &package_name).await;
-    }
+    op_id: Option<String>,
+) -> Result<adb::InstallResult, AdbError> {
+    let transport = AppTransport::new(&app);
+    let install = async {
+        // Uninstall existing app before install to avoid signature conflicts
+        if let Ok(package_name) = apk_parser::extract_package_name(&apk_path) {
+            let _ = adb::uninstall_app(&transport, &serial, &package_name).await;
+        }
 
-    let flag_refs: Vec<&str> = flags.iter().map(|s| s.as_str()).collect();
-    let result = adb::install_apk(&app, &serial, &apk_path, &flag_refs).await?;
-    let file_name = apk_path.rsplit('/').next().or_else(|| apk_path.rsplit('\\').next()).unwrap_or(&apk_path);
+        let flag_refs: Vec<&str> = flags.iter().map(|s| s.as_str()).collect();
+        adb::install_apk(&transport, &serial, &apk_path, &flag_refs).await
+    };
+    let file_name = adb::file_name(&apk_path);
+    let result = ops.run(op_id.as_deref(), install).await;
+    log_if_interrupted(&state, &result, "install", &serial, &format!("安装 {}", file_name));
+    let result = result?;
     let cmd = if flags.is_empty() {
         format!("adb -s {} install {}", serial, file_name)
     } else {
         format!("adb -s {} install {} {}", serial, flags.join(" "), file_name)
     };
     op_log::add_entry(&state, op_log::OpLogEntry {
-        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-        op_type: "install".to_string(),
-        device: serial.clone(),
-        detail: format!("安装 {}", file_name),
-        success: result.success,
         error_message: result.error_message_cn.clone(),
         command: Some(cmd),
         raw_output: Some(result.raw_output.clone()),
+        ..op_log::OpLogEntry::new("install", &serial, format!("安装 {}", file_name), result.success)
     });
     Ok(result)
 }
@@ -69,21 +116,17 @@ async fn uninstall_app(
     state: tauri::State<'_, op_log::OpLogState>,
     serial: String,
     package_name: String,
-) -> Result<String, String> {
-    let result = adb::uninstall_app(&app, &serial, &package_name).await;
+) -> Result<String, AdbError> {
+    let result = adb::uninstall_app(&AppTransport::new(&app), &serial, &package_name).await;
     let (success, error_msg, raw) = match &result {
         Ok(output) => (true, None, output.clone()),
-        Err(e) => (false, Some(e.clone()), e.clone()),
+        Err(e) => (false, Some(e.to_string()), e.detail()),
     };
     op_log::add_entry(&state, op_log::OpLogEntry {
-        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-        op_type: "uninstall".to_string(),
-        device: serial.clone(),
-        detail: format!("卸载 {}", package_name),
-        success,
         error_message: error_msg,
         command: Some(format!("adb -s {} uninstall {}", serial, package_name)),
         raw_output: Some(raw),
+        ..op_log::OpLogEntry::new("uninstall", &serial, format!("卸载 {}", package_name), success)
     });
     result
 }
@@ -91,60 +134,91 @@ async fn uninstall_app(
 #[tauri::command]
 async fn get_packages(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     include_system: bool,
-) -> Result<Vec<adb::InstalledApp>, String> {
-    adb::list_packages(&app, &serial, include_system).await
+    op_id: Option<String>,
+) -> Result<Vec<adb::InstalledApp>, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops
+        .run(op_id.as_deref(), adb::list_packages(&transport, &serial, include_system))
+        .await;
+    log_if_interrupted(&state, &result, "packages", &serial, "读取应用列表");
+    result
 }
 
 #[tauri::command]
 async fn clear_app_data(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     package_name: String,
-) -> Result<String, String> {
-    adb::clear_app_data(&app, &serial, &package_name).await
+    op_id: Option<String>,
+) -> Result<String, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops
+        .run(op_id.as_deref(), adb::clear_app_data(&transport, &serial, &package_name))
+        .await;
+    log_if_interrupted(&state, &result, "clear_data", &serial, &format!("清除数据 {}", package_name));
+    result
 }
 
 #[tauri::command]
 async fn force_stop(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     package_name: String,
-) -> Result<String, String> {
-    adb::force_stop_app(&app, &serial, &package_name).await
+    op_id: Option<String>,
+) -> Result<String, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops
+        .run(op_id.as_deref(), adb::force_stop_app(&transport, &serial, &package_name))
+        .await;
+    log_if_interrupted(&state, &result, "force_stop", &serial, &format!("强制停止 {}", package_name));
+    result
 }
 
 #[tauri::command]
 async fn launch_app(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     package_name: String,
-) -> Result<String, String> {
-    adb::launch_app(&app, &serial, &package_name).await
+    op_id: Option<String>,
+) -> Result<String, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops
+        .run(op_id.as_deref(), adb::launch_app(&transport, &serial, &package_name))
+        .await;
+    log_if_interrupted(&state, &result, "launch", &serial, &format!("启动 {}", package_name));
+    result
 }
 
 #[tauri::command]
 async fn take_screenshot(
     app: tauri::AppHandle,
     state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     local_path: String,
-) -> Result<String, String> {
-    let result = adb::screenshot(&app, &serial, &local_path).await;
+    op_id: Option<String>,
+) -> Result<String, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops.run(op_id.as_deref(), adb::screenshot(&transport, &serial, &local_path)).await;
     let (success, error_msg, raw) = match &result {
         Ok(path) => (true, None, format!("保存至 {}", path)),
-        Err(e) => (false, Some(e.clone()), e.clone()),
+        Err(e) => (false, Some(e.to_string()), e.detail()),
     };
     op_log::add_entry(&state, op_log::OpLogEntry {
-        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-        op_type: "screenshot".to_string(),
-        device: serial.clone(),
-        detail: format!("截图 → {}", local_path),
-        success,
         error_message: error_msg,
         command: Some(format!("adb -s {} exec-out screencap -p > {}", serial, local_path)),
         raw_output: Some(raw),
+        ..op_log::OpLogEntry::new("screenshot", &serial, format!("截图 → {}", local_path), success)
     });
     result
 }
@@ -153,25 +227,29 @@ async fn take_screenshot(
 async fn push_file(
     app: tauri::AppHandle,
     state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     local_path: String,
     remote_path: String,
-) -> Result<String, String> {
-    let result = adb::push_file(&app, &serial, &local_path, &remote_path).await;
-    let file_name = local_path.rsplit('/').next().or_else(|| local_path.rsplit('\\').next()).unwrap_or(&local_path);
-    let (success, error_msg, raw) = match &result {
-        Ok(output) => (true, None, output.clone()),
-        Err(e) => (false, Some(e.clone()), e.clone()),
+    transfer_id: Option<String>,
+) -> Result<sync::TransferResult, AdbError> {
+    let transfer_id = transfer_id.unwrap_or_else(|| new_transfer_id(&serial));
+    let cancel = ops.register(&transfer_id);
+    let started = std::time::Instant::now();
+    let result = adb::push_file(&AppTransport::new(&app), &serial, &local_path, &remote_path, &transfer_id, &cancel, adb::emit_progress(&app)).await;
+    ops.finish(&transfer_id);
+    let file_name = adb::file_name(&local_path);
+    let (success, error_msg, raw, size) = match &result {
+        Ok(r) => (true, None, transfer_summary(r), Some(r.bytes)),
+        Err(e) => (false, Some(e.to_string()), e.detail(), None),
     };
     op_log::add_entry(&state, op_log::OpLogEntry {
-        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-        op_type: "upload".to_string(),
-        device: serial.clone(),
-        detail: format!("上传 {} → {}", file_name, remote_path),
-        success,
         error_message: error_msg,
         command: Some(format!("adb -s {} push {} {}", serial, local_path, remote_path)),
         raw_output: Some(raw),
+        size_bytes: size,
+        duration_ms: Some(started.elapsed().as_millis() as u64),
+        ..op_log::OpLogEntry::new("upload", &serial, format!("上传 {} → {}", file_name, remote_path), success)
     });
     result
 }
@@ -180,81 +258,1009 @@ async fn push_file(
 async fn pull_file(
     app: tauri::AppHandle,
     state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     remote_path: String,
     local_path: String,
-) -> Result<String, String> {
-    let result = adb::pull_file(&app, &serial, &remote_path, &local_path).await;
+    transfer_id: Option<String>,
+) -> Result<sync::TransferResult, AdbError> {
+    let transfer_id = transfer_id.unwrap_or_else(|| new_transfer_id(&serial));
+    let cancel = ops.register(&transfer_id);
+    let started = std::time::Instant::now();
+    let result = adb::pull_file(&AppTransport::new(&app), &serial, &remote_path, &local_path, &transfer_id, &cancel, adb::emit_progress(&app)).await;
+    ops.finish(&transfer_id);
     let file_name = remote_path.rsplit('/').next().unwrap_or(&remote_path);
-    let (success, error_msg, raw) = match &result {
-        Ok(output) => (true, None, output.clone()),
-        Err(e) => (false, Some(e.clone()), e.clone()),
+    let (success, error_msg, raw, size) = match &result {
+        Ok(r) => (true, None, transfer_summary(r), Some(r.bytes)),
+        Err(e) => (false, Some(e.to_string()), e.detail(), None),
     };
     op_log::add_entry(&state, op_log::OpLogEntry {
-        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-        op_type: "download".to_string(),
-        device: serial.clone(),
-        detail: format!("下载 {} → {}", file_name, local_path),
-        success,
         error_message: error_msg,
         command: Some(format!("adb -s {} pull {} {}", serial, remote_path, local_path)),
         raw_output: Some(raw),
+        size_bytes: size,
+        duration_ms: Some(started.elapsed().as_millis() as u64),
+        ..op_log::OpLogEntry::new("download", &serial, format!("下载 {} → {}", file_name, local_path), success)
     });
     result
 }
 
+#[tauri::command]
+async fn stat_remote_file(
+    app: tauri::AppHandle,
+    serial: String,
+    remote_path: String,
+) -> Result<sync::RemoteStat, AdbError> {
+    adb::stat_file(&AppTransport::new(&app), &serial, &remote_path).await
+}
+
+#[tauri::command]
+async fn list_remote_dir(
+    app: tauri::AppHandle,
+    serial: String,
+    remote_dir: String,
+) -> Result<Vec<sync::RemoteEntry>, AdbError> {
+    adb::list_dir(&AppTransport::new(&app), &serial, &remote_dir).await
+}
+
+/// Default transfer id when the frontend does not supply one.
+fn new_transfer_id(serial: &str) -> String {
+    format!("{}-{}", serial, chrono::Local::now().timestamp_millis())
+}
+
+/// One-line summary for the op log, similar to what `adb push/pull` prints.
+fn transfer_summary(result: &sync::TransferResult) -> String {
+    let secs = result.duration_ms as f64 / 1000.0;
+    let rate_mb = if secs > 0.0 {
+        result.bytes as f64 / secs / (1024.0 * 1024.0)
+    } else {
+        0.0
+    };
+    format!("{} bytes in {:.1}s ({:.1} MB/s)", result.bytes, secs, rate_mb)
+}
+
 #[tauri::command]
 async fn list_remote_files(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     remote_dir: String,
-) -> Result<Vec<String>, String> {
-    adb::list_files(&app, &serial, &remote_dir).await
+    op_id: Option<String>,
+) -> Result<Vec<String>, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops.run(op_id.as_deref(), adb::list_files(&transport, &serial, &remote_dir)).await;
+    log_if_interrupted(&state, &result, "list_files", &serial, &format!("浏览 {}", remote_dir));
+    result
 }
 
 #[tauri::command]
 async fn delete_remote_file(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     serial: String,
     remote_path: String,
-) -> Result<String, String> {
-    adb::delete_file(&app, &serial, &remote_path).await
+    op_id: Option<String>,
+) -> Result<String, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops.run(op_id.as_deref(), adb::delete_file(&transport, &serial, &remote_path)).await;
+    log_if_interrupted(&state, &result, "delete", &serial, &format!("删除 {}", remote_path));
+    result
 }
 
 #[tauri::command]
 async fn connect_wifi(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
     address: String,
-) -> Result<String, String> {
-    adb::connect_wifi(&app, &address).await
+    op_id: Option<String>,
+) -> Result<String, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops.run(op_id.as_deref(), adb::connect_wifi(&transport, &address)).await;
+    log_if_interrupted(&state, &result, "connect", &address, &format!("连接 {}", address));
+    result
 }
 
 #[tauri::command]
 async fn disconnect_wifi(
     app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
+    address: String,
+    op_id: Option<String>,
+) -> Result<String, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = ops.run(op_id.as_deref(), adb::disconnect_wifi(&transport, &address)).await;
+    log_if_interrupted(&state, &result, "disconnect", &address, &format!("断开 {}", address));
+    result
+}
+
+/// Pair with a phone showing "Pair device with pairing code" (Android 11+).
+#[tauri::command]
+async fn pair_wifi(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    address: String,
+    code: String,
+) -> Result<String, AdbError> {
+    let result = wireless::pair(&AppTransport::new(&app), &address, &code).await;
+    op_log::add_entry(&state, op_log::OpLogEntry {
+        error_message: result.as_ref().err().map(|e| e.to_string()),
+        command: Some(format!("adb pair {} ******", address)),
+        raw_output: result.as_ref().ok().cloned(),
+        ..op_log::OpLogEntry::new("pair", &address, format!("配对 {}", address), result.is_ok())
+    });
+    result
+}
+
+/// Phones advertising wireless debugging or an open pairing dialog.
+#[tauri::command]
+async fn discover_wireless_devices(
+    app: tauri::AppHandle,
+) -> Result<Vec<wireless::WirelessEndpoint>, AdbError> {
+    wireless::discover(&AppTransport::new(&app)).await
+}
+
+/// Default for `timeout_secs` of the QR pairing flow.
+const QR_PAIRING_TIMEOUT_SECS: u64 = 120;
+
+/// New QR code for "Pair device with QR code".
+#[tauri::command]
+fn start_qr_pairing() -> Result<wireless::QrPairing, AdbError> {
+    wireless::new_qr_pairing()
+}
+
+/// Wait for the phone to scan the code from [`start_qr_pairing`], then pair
+/// and connect. Emits `qr-pairing-progress`; the tracker emits
+/// `device-attached` once the device is listed.
+#[tauri::command]
+async fn complete_qr_pairing(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
+    service_name: String,
+    password: String,
+    timeout_secs: Option<u64>,
+    op_id: Option<String>,
+) -> Result<adb::AdbDevice, AdbError> {
+    let transport = AppTransport::new(&app);
+    let timeout = std::time::Duration::from_secs(timeout_secs.unwrap_or(QR_PAIRING_TIMEOUT_SECS));
+    let started = std::time::Instant::now();
+    let pairing = wireless::complete_qr_pairing(&transport, &service_name, &password, timeout, |phase, address| {
+        let _ = app.emit("qr-pairing-progress", wireless::QrPairingProgress {
+            service_name: service_name.clone(),
+            phase,
+            address: address.map(str::to_string),
+        });
+    });
+    let result = ops.run(op_id.as_deref(), pairing).await;
+
+    op_log::add_entry(&state, op_log::OpLogEntry {
+        error_message: result.as_ref().err().map(|e| e.to_string()),
+        duration_ms: Some(started.elapsed().as_millis() as u64),
+        ..op_log::OpLogEntry::new(
+            "pair",
+            result.as_ref().map(|d| d.serial.as_str()).unwrap_or_default(),
+            format!("二维码配对 {}", service_name),
+            result.is_ok(),
+        )
+    });
+    result
+}
+
+#[tauri::command]
+async fn kill_server(app: tauri::AppHandle) -> Result<String, AdbError> {
+    adb::kill_server(&AppTransport::new(&app)).await
+}
+
+/// Saved Wi-Fi endpoints are reconnected by the device tracker once it
+/// reaches the new server.
+#[tauri::command]
+async fn start_server(app: tauri::AppHandle) -> Result<String, AdbError> {
+    adb::start_server(&AppTransport::new(&app)).await
+}
+
+#[tauri::command]
+async fn start_logcat(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, logcat::LogcatState>,
+    serial: String,
+) -> Result<u32, AdbError> {
+    logcat::start_stream(&AppTransport::new(&app), &state, &serial, logcat::emit_lines(&app, &serial)).await
+}
+
+#[tauri::command]
+async fn stop_logcat(state: tauri::State<'_, logcat::LogcatState>, serial: String) -> Result<(), AdbError> {
+    logcat::stop_stream(&state, &serial)
+}
+
+// ---------------------------------------------------------------------------
+// USB → Wi-Fi handoff and saved endpoints
+// ---------------------------------------------------------------------------
+
+/// Default for `timeout_secs` of the handoff; covers the adbd restart.
+const HANDOFF_TIMEOUT_SECS: u64 = 30;
+
+/// Move a USB device to Wi-Fi (`tcpip` + `connect`) and remember the
+/// endpoint for automatic reconnects.
+#[tauri::command]
+async fn wifi_handoff(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    wireless_state: tauri::State<'_, wireless::WirelessState>,
+    serial: String,
+    port: Option<u16>,
+    timeout_secs: Option<u64>,
+) -> Result<adb::AdbDevice, AdbError> {
+    let transport = AppTransport::new(&app);
+    let port = port.unwrap_or(wireless::DEFAULT_TCPIP_PORT);
+    let timeout = std::time::Duration::from_secs(timeout_secs.unwrap_or(HANDOFF_TIMEOUT_SECS));
+    let started = std::time::Instant::now();
+    let result = wireless::handoff_to_wifi(&transport, &serial, port, timeout).await;
+
+    if let Ok(device) = &result {
+        wireless_state.save(wireless::SavedEndpoint {
+            address: device.serial.clone(),
+            serial: serial.clone(),
+            model: device.model.clone(),
+            saved_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            auto_reconnect: true,
+        })?;
+    }
+    let detail = match &result {
+        Ok(device) => format!("USB → Wi-Fi {}", device.serial),
+        Err(_) => "USB → Wi-Fi".to_string(),
+    };
+    op_log::add_entry(&state, op_log::OpLogEntry {
+        error_message: result.as_ref().err().map(|e| e.to_string()),
+        command: Some(format!("adb -s {} tcpip {}", serial, port)),
+        duration_ms: Some(started.elapsed().as_millis() as u64),
+        ..op_log::OpLogEntry::new("wifi_handoff", &serial, detail, result.is_ok())
+    });
+    result
+}
+
+#[tauri::command]
+fn list_wireless_endpoints(
+    state: tauri::State<'_, wireless::WirelessState>,
+) -> Vec<wireless::SavedEndpoint> {
+    state.list()
+}
+
+#[tauri::command]
+fn save_wireless_endpoint(
+    state: tauri::State<'_, wireless::WirelessState>,
+    endpoint: wireless::SavedEndpoint,
+) -> Result<(), AdbError> {
+    state.save(endpoint)
+}
+
+#[tauri::command]
+fn remove_wireless_endpoint(
+    state: tauri::State<'_, wireless::WirelessState>,
     address: String,
-) -> Result<String, String> {
-    adb::disconnect_wifi(&app, &address).await
+) -> Result<(), AdbError> {
+    state.remove(&address)
+}
+
+/// Reconnect saved Wi-Fi endpoints, log each attempt and emit
+/// `wireless-reconnected` with the results. Runs at launch and whenever the
+/// device tracker gets its server back, which covers `start_server`.
+async fn reconnect_saved_endpoints(app: &tauri::AppHandle) -> Vec<wireless::ReconnectResult> {
+    let endpoints = app.state::<wireless::WirelessState>().list();
+    let results = wireless::reconnect_saved(&AppTransport::new(app), &endpoints).await;
+    let state = app.state::<op_log::OpLogState>();
+    for r in &results {
+        op_log::add_entry(&state, op_log::OpLogEntry {
+            error_message: if r.success { None } else { Some(r.message.clone()) },
+            command: Some(format!("adb connect {}", r.address)),
+            raw_output: Some(r.message.clone()),
+            ..op_log::OpLogEntry::new("reconnect", &r.address, format!("自动重连 {}", r.address), r.success)
+        });
+    }
+    if !results.is_empty() {
+        let _ = app.emit("wireless-reconnected", &results);
+    }
+    results
+}
+
+#[tauri::command]
+async fn reconnect_wireless_endpoints(app: tauri::AppHandle) -> Vec<wireless::ReconnectResult> {
+    reconnect_saved_endpoints(&app).await
+}
+
+// ---------------------------------------------------------------------------
+// Port forwarding — forward/reverse rules and per-device presets
+// ---------------------------------------------------------------------------
+
+/// One op log entry for a forward/reverse change.
+fn log_port_rule(
+    state: &op_log::OpLogState,
+    serial: &str,
+    detail: String,
+    command: String,
+    result: Result<(), &AdbError>,
+) {
+    op_log::add_entry(state, op_log::OpLogEntry {
+        error_message: result.err().map(|e| e.to_string()),
+        command: Some(command),
+        ..op_log::OpLogEntry::new("forward", serial, detail, result.is_ok())
+    });
+}
+
+#[tauri::command]
+async fn list_port_rules(
+    app: tauri::AppHandle,
+    serial: String,
+) -> Result<Vec<forward::PortRule>, AdbError> {
+    forward::list_rules(&AppTransport::new(&app), &serial).await
+}
+
+/// Apply `rule` and add it to the device's preset. Returns adb's output (the
+/// allocated port for `tcp:0`).
+#[tauri::command]
+async fn add_port_rule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    presets: tauri::State<'_, forward::ForwardState>,
+    serial: String,
+    rule: forward::PortRule,
+) -> Result<String, AdbError> {
+    let result = forward::add_rule(&AppTransport::new(&app), &serial, &rule).await;
+    let command = rule.command_line(&serial, false);
+    log_port_rule(&state, &serial, rule.describe(), command, result.as_ref().map(|_| ()));
+    let output = result?;
+    presets.remember(&serial, &rule)?;
+    Ok(output)
+}
+
+#[tauri::command]
+async fn remove_port_rule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    presets: tauri::State<'_, forward::ForwardState>,
+    serial: String,
+    rule: forward::PortRule,
+) -> Result<(), AdbError> {
+    let result = forward::remove_rule(&AppTransport::new(&app), &serial, &rule).await;
+    let command = rule.command_line(&serial, true);
+    log_port_rule(&state, &serial, format!("移除 {}", rule.describe()), command, result.as_ref().copied());
+    result?;
+    presets.forget(&serial, &rule)
+}
+
+/// Remove all rules of one direction (or both) and clear them from the
+/// preset. Returns how many were removed.
+#[tauri::command]
+async fn remove_all_port_rules(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    presets: tauri::State<'_, forward::ForwardState>,
+    serial: String,
+    direction: Option<forward::ForwardDirection>,
+) -> Result<usize, AdbError> {
+    let result = forward::remove_all(&AppTransport::new(&app), &serial, direction).await;
+    let scope = direction.map_or("forward + reverse", |d| d.command());
+    let command = format!("adb -s {} {} --remove-all", serial, scope);
+    log_port_rule(&state, &serial, format!("移除全部 {}", scope), command, result.as_ref().map(|_| ()));
+    let removed = result?;
+    let kept = presets
+        .get(&serial)
+        .into_iter()
+        .filter(|r| direction.is_some_and(|d| r.direction != d))
+        .collect();
+    presets.set(&serial, kept)?;
+    Ok(removed)
+}
+
+#[tauri::command]
+fn get_port_presets(
+    presets: tauri::State<'_, forward::ForwardState>,
+) -> std::collections::BTreeMap<String, Vec<forward::PortRule>> {
+    presets.all()
+}
+
+/// Replace a device's preset without touching the live rules; it is applied
+/// the next time the device comes online.
+#[tauri::command]
+fn set_port_preset(
+    presets: tauri::State<'_, forward::ForwardState>,
+    serial: String,
+    rules: Vec<forward::PortRule>,
+) -> Result<(), AdbError> {
+    presets.set(&serial, rules)
+}
+
+// ---------------------------------------------------------------------------
+// Device settings — global/secure/system with revert
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+async fn list_device_settings(
+    app: tauri::AppHandle,
+    serial: String,
+    namespace: device_settings::SettingsNamespace,
+) -> Result<std::collections::BTreeMap<String, String>, AdbError> {
+    device_settings::list(&AppTransport::new(&app), &serial, namespace).await
+}
+
+#[tauri::command]
+async fn get_device_setting(
+    app: tauri::AppHandle,
+    serial: String,
+    namespace: device_settings::SettingsNamespace,
+    key: String,
+) -> Result<Option<String>, AdbError> {
+    device_settings::get(&AppTransport::new(&app), &serial, namespace, &key).await
+}
+
+/// One op log entry per settings change. A successful change carries the
+/// replaced value in `setting`, which is what reverts are computed from.
+fn log_setting_change(
+    state: &op_log::OpLogState,
+    serial: &str,
+    attempted: &device_settings::SettingChange,
+    result: &Result<device_settings::SettingChange, AdbError>,
+) {
+    let change = result.as_ref().unwrap_or(attempted);
+    op_log::add_entry(state, op_log::OpLogEntry {
+        error_message: result.as_ref().err().map(|e| e.to_string()),
+        command: Some(device_settings::command_line(serial, change)),
+        setting: result.as_ref().ok().cloned(),
+        ..op_log::OpLogEntry::new(device_settings::OP_TYPE, serial, change.describe(), result.is_ok())
+    });
+}
+
+/// Put `value`, or delete the key when `value` is null. The replaced value
+/// goes into the op log for [`revert_device_settings`].
+#[tauri::command]
+async fn set_device_setting(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    serial: String,
+    namespace: device_settings::SettingsNamespace,
+    key: String,
+    value: Option<String>,
+) -> Result<device_settings::SettingChange, AdbError> {
+    let transport = AppTransport::new(&app);
+    let result = device_settings::set(&transport, &serial, namespace, &key, value.as_deref()).await;
+    let attempted = device_settings::SettingChange { namespace, key, previous: None, value };
+    log_setting_change(&state, &serial, &attempted, &result);
+    result
+}
+
+/// What [`revert_device_settings`] would do for this device.
+#[tauri::command]
+fn pending_setting_reverts(
+    state: tauri::State<'_, op_log::OpLogState>,
+    serial: String,
+) -> Vec<device_settings::SettingChange> {
+    device_settings::pending_reverts(&op_log::get_entries(&state, None, Some(&serial)), &serial)
+}
+
+/// Restore every setting changed on this device since its last revert. Each
+/// key is logged as a change of its own, so after a partial failure only the
+/// failed keys are still pending; the first error is returned.
+#[tauri::command]
+async fn revert_device_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    serial: String,
+) -> Result<Vec<device_settings::SettingChange>, AdbError> {
+    let entries = op_log::get_entries(&state, None, Some(&serial));
+    let reverts = device_settings::pending_reverts(&entries, &serial);
+    if reverts.is_empty() {
+        return Ok(reverts);
+    }
+    let results = device_settings::revert(&AppTransport::new(&app), &serial, &reverts).await;
+    let mut failed = Vec::new();
+    for (revert, result) in reverts.iter().zip(&results) {
+        log_setting_change(&state, &serial, revert, result);
+        if let Err(e) = result {
+            failed.push(format!("{}: {}", revert.key, e));
+        }
+    }
+    let lines: Vec<String> = reverts.iter().map(|c| c.describe()).collect();
+    op_log::add_entry(&state, op_log::OpLogEntry {
+        error_message: (!failed.is_empty()).then(|| failed.join("; ")),
+        raw_output: Some(lines.join("\n")),
+        ..op_log::OpLogEntry::new(
+            device_settings::OP_TYPE_REVERT,
+            &serial,
+            format!("还原 {}/{} 项设置", reverts.len() - failed.len(), reverts.len()),
+            failed.is_empty(),
+        )
+    });
+    match results.into_iter().find_map(Result::err) {
+        Some(e) => Err(e),
+        None => Ok(reverts),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Developer-option presets
+// ---------------------------------------------------------------------------
+
+fn log_dev_preset(
+    state: &op_log::OpLogState,
+    serial: &str,
+    detail: String,
+    result: Result<(), &AdbError>,
+) {
+    op_log::add_entry(state, op_log::OpLogEntry {
+        error_message: result.err().map(|e| e.to_string()),
+        ..op_log::OpLogEntry::new("dev_preset", serial, detail, result.is_ok())
+    });
+}
+
+/// Built-in presets plus those from `~/AdbTool/dev_presets.json`.
+#[tauri::command]
+fn list_dev_presets() -> Result<Vec<dev_presets::DevPreset>, AdbError> {
+    dev_presets::list_presets()
+}
+
+#[tauri::command]
+fn get_applied_dev_presets(
+    presets: tauri::State<'_, dev_presets::DevPresetState>,
+    serial: String,
+) -> Vec<dev_presets::AppliedPreset> {
+    presets.for_device(&serial)
+}
+
+#[tauri::command]
+async fn apply_dev_preset(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    presets: tauri::State<'_, dev_presets::DevPresetState>,
+    serial: String,
+    name: String,
+) -> Result<dev_presets::AppliedPreset, AdbError> {
+    let result = dev_presets::apply(&AppTransport::new(&app), &presets, &serial, &name, |attempted, result| {
+        log_setting_change(&state, &serial, attempted, result)
+    })
+    .await;
+    log_dev_preset(&state, &serial, format!("应用预设 {}", name), result.as_ref().map(|_| ()));
+    result
+}
+
+#[tauri::command]
+async fn revert_dev_preset(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    presets: tauri::State<'_, dev_presets::DevPresetState>,
+    serial: String,
+    name: String,
+) -> Result<(), AdbError> {
+    let result = dev_presets::revert(&AppTransport::new(&app), &presets, &serial, &name, |attempted, result| {
+        log_setting_change(&state, &serial, attempted, result)
+    })
+    .await;
+    log_dev_preset(&state, &serial, format!("还原预设 {}", name), result.as_ref().copied());
+    result
+}
+
+// ---------------------------------------------------------------------------
+// Emulator console — geo, sms, gsm, power, network and snapshots
+// ---------------------------------------------------------------------------
+
+/// Run one console command on an `emulator-*` device and return its output.
+#[tauri::command]
+async fn emulator_console(
+    state: tauri::State<'_, op_log::OpLogState>,
+    serial: String,
+    command: emulator::EmulatorCommand,
+) -> Result<String, AdbError> {
+    let line = command.to_line()?;
+    let result = emulator::run_command(&serial, &command).await;
+    op_log::add_entry(&state, op_log::OpLogEntry {
+        error_message: result.as_ref().err().map(|e| e.to_string()),
+        command: Some(line.clone()),
+        raw_output: result.as_ref().ok().cloned(),
+        ..op_log::OpLogEntry::new("emulator", &serial, line, result.is_ok())
+    });
+    result
+}
+
+#[tauri::command]
+async fn list_emulator_snapshots(serial: String) -> Result<Vec<String>, AdbError> {
+    let output = emulator::run_command(&serial, &emulator::EmulatorCommand::SnapshotList).await?;
+    Ok(emulator::parse_snapshot_list(&output))
+}
+
+// ---------------------------------------------------------------------------
+// Reboot — reboot modes and boot-complete detection
+// ---------------------------------------------------------------------------
+
+/// Default for `timeout_secs` of the boot wait; cold boots of older devices
+/// and emulators take a few minutes.
+const BOOT_TIMEOUT_SECS: u64 = 300;
+
+/// Wait for `serial` to boot, emitting `reboot-progress-{serial}` per phase.
+async fn wait_with_progress(
+    app: &tauri::AppHandle,
+    transport: &AppTransport,
+    serial: &str,
+    timeout_secs: Option<u64>,
+    expect_down: bool,
+) -> Result<u64, AdbError> {
+    let event_name = format!("reboot-progress-{}", serial);
+    let timeout = std::time::Duration::from_secs(timeout_secs.unwrap_or(BOOT_TIMEOUT_SECS));
+    reboot::wait_for_boot(transport, serial, timeout, expect_down, |phase, elapsed_ms| {
+        let _ = app.emit(&event_name, reboot::BootProgress {
+            serial: serial.to_string(),
+            phase,
+            elapsed_ms,
+        });
+    })
+    .await
+}
+
+/// `timeout_secs` bounds the boot wait after a normal reboot; `0` returns
+/// right after the reboot request.
+#[tauri::command]
+async fn reboot_device(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    ops: tauri::State<'_, operation::OperationState>,
+    serial: String,
+    mode: reboot::RebootMode,
+    timeout_secs: Option<u64>,
+    op_id: Option<String>,
+) -> Result<reboot::RebootResult, AdbError> {
+    let transport = AppTransport::new(&app);
+    // Only a normal reboot comes back as `device`
+    let wait = timeout_secs != Some(0) && mode == reboot::RebootMode::System;
+    let result = ops
+        .run(op_id.as_deref(), async {
+            reboot::reboot(&transport, &serial, mode).await?;
+            let boot_ms = if wait {
+                Some(wait_with_progress(&app, &transport, &serial, timeout_secs, true).await?)
+            } else {
+                None
+            };
+            Ok(reboot::RebootResult { mode, boot_ms })
+        })
+        .await;
+
+    let target = match mode.target() {
+        "" => "system",
+        target => target,
+    };
+    let (success, error_msg, detail) = match &result {
+        Ok(r) => match r.boot_ms {
+            Some(ms) => (true, None, format!("重启 → {}，启动耗时 {:.1}s", target, ms as f64 / 1000.0)),
+            None => (true, None, format!("重启 → {}", target)),
+        },
+        Err(e) => (false, Some(e.to_string()), format!("重启 → {}", target)),
+    };
+    op_log::add_entry(&state, op_log::OpLogEntry {
+        error_message: error_msg,
+        command: Some(format!("adb -s {} reboot {}", serial, mode.target()).trim_end().to_string()),
+        duration_ms: result.as_ref().ok().and_then(|r| r.boot_ms),
+        ..op_log::OpLogEntry::new("reboot", &serial, detail, success)
+    });
+    result
+}
+
+/// Wait for a device that is already rebooting (or booted) without
+/// rebooting it. Returns how long it took.
+#[tauri::command]
+async fn wait_for_boot(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serial: String,
+    timeout_secs: Option<u64>,
+    op_id: Option<String>,
+) -> Result<u64, AdbError> {
+    let transport = AppTransport::new(&app);
+    ops.run(
+        op_id.as_deref(),
+        wait_with_progress(&app, &transport, &serial, timeout_secs, false),
+    )
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// Device registry — aliases, colors, tags and notes per serial
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+fn get_device_labels(
+    registry: tauri::State<'_, device_registry::DeviceRegistryState>,
+) -> std::collections::BTreeMap<String, device_registry::DeviceLabel> {
+    registry.all()
+}
+
+#[tauri::command]
+fn set_device_label(
+    registry: tauri::State<'_, device_registry::DeviceRegistryState>,
+    serial: String,
+    label: device_registry::DeviceLabel,
+) -> Result<device_registry::DeviceLabel, AdbError> {
+    registry.set(&serial, label)
+}
+
+#[tauri::command]
+fn remove_device_label(
+    registry: tauri::State<'_, device_registry::DeviceRegistryState>,
+    serial: String,
+) -> Result<(), AdbError> {
+    registry.remove(&serial)
+}
+
+// ---------------------------------------------------------------------------
+// Health monitor — periodic samples and threshold alerts per device
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+fn get_health_config(state: tauri::State<'_, health::HealthState>) -> health::HealthConfig {
+    state.config()
+}
+
+#[tauri::command]
+fn set_health_config(
+    app: tauri::AppHandle,
+    config: health::HealthConfig,
+) -> Result<health::HealthConfig, AdbError> {
+    health::set_config(&app, config)
+}
+
+#[tauri::command]
+fn get_health_history(
+    state: tauri::State<'_, health::HealthState>,
+    serial: String,
+) -> Vec<health::HealthSample> {
+    state.history(&serial)
+}
+
+// ---------------------------------------------------------------------------
+// Device properties — full getprop, snapshots and diffs
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+async fn get_device_props(
+    app: tauri::AppHandle,
+    serial: String,
+) -> Result<std::collections::BTreeMap<String, String>, AdbError> {
+    props::get_props(&AppTransport::new(&app), &serial).await
+}
+
+#[tauri::command]
+async fn diff_device_props(
+    app: tauri::AppHandle,
+    left: props::PropSource,
+    right: props::PropSource,
+) -> Result<props::PropDiff, AdbError> {
+    let transport = AppTransport::new(&app);
+    let (left_label, left_props) = props::load_source(&transport, &left).await?;
+    let (right_label, right_props) = props::load_source(&transport, &right).await?;
+    Ok(props::diff_props(&left_label, &left_props, &right_label, &right_props))
+}
+
+#[tauri::command]
+async fn save_prop_snapshot(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, op_log::OpLogState>,
+    serial: String,
+    name: Option<String>,
+) -> Result<props::PropSnapshot, AdbError> {
+    let result = props::save_snapshot(&AppTransport::new(&app), &serial, name.as_deref()).await;
+    let (success, error_msg, detail) = match &result {
+        Ok(s) => (true, None, format!("保存属性快照 {} ({} 项)", s.name, s.props.len())),
+        Err(e) => (false, Some(e.to_string()), "保存属性快照".to_string()),
+    };
+    op_log::add_entry(&state, op_log::OpLogEntry {
+        error_message: error_msg,
+        command: Some(format!("adb -s {} shell getprop", serial)),
+        ..op_log::OpLogEntry::new("prop_snapshot", &serial, detail, success)
+    });
+    result
+}
+
+#[tauri::command]
+fn list_prop_snapshots() -> Vec<props::PropSnapshotInfo> {
+    props::list_snapshots()
+}
+
+#[tauri::command]
+fn delete_prop_snapshot(name: String) -> Result<(), AdbError> {
+    props::delete_snapshot(&name)
+}
+
+// ---------------------------------------------------------------------------
+// Broadcast commands — the same operation on several devices at once
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+async fn broadcast_install_apk(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serials: Vec<String>,
+    apk_path: String,
+    flags: Vec<String>,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    let op = broadcast::BroadcastOp::Install { apk_path, flags };
+    run_broadcast(&app, &ops, serials, op, parallelism, op_id).await
+}
+
+#[tauri::command]
+async fn broadcast_uninstall_app(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serials: Vec<String>,
+    package_name: String,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    let op = broadcast::BroadcastOp::Uninstall { package_name };
+    run_broadcast(&app, &ops, serials, op, parallelism, op_id).await
+}
+
+#[tauri::command]
+async fn broadcast_clear_app_data(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serials: Vec<String>,
+    package_name: String,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    let op = broadcast::BroadcastOp::ClearAppData { package_name };
+    run_broadcast(&app, &ops, serials, op, parallelism, op_id).await
+}
+
+#[tauri::command]
+async fn broadcast_force_stop(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serials: Vec<String>,
+    package_name: String,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    let op = broadcast::BroadcastOp::ForceStop { package_name };
+    run_broadcast(&app, &ops, serials, op, parallelism, op_id).await
+}
+
+#[tauri::command]
+async fn broadcast_launch_app(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serials: Vec<String>,
+    package_name: String,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    let op = broadcast::BroadcastOp::LaunchApp { package_name };
+    run_broadcast(&app, &ops, serials, op, parallelism, op_id).await
 }
 
 #[tauri::command]
-async fn kill_server(app: tauri::AppHandle) -> Result<String, String> {
-    adb::kill_server(&app).await
+async fn broadcast_push_file(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serials: Vec<String>,
+    local_path: String,
+    remote_path: String,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    let op = broadcast::BroadcastOp::PushFile {
+        local_path,
+        remote_path,
+    };
+    run_broadcast(&app, &ops, serials, op, parallelism, op_id).await
 }
 
 #[tauri::command]
-async fn start_server(app: tauri::AppHandle) -> Result<String, String> {
-    adb::start_server(&app).await
+async fn broadcast_take_screenshot(
+    app: tauri::AppHandle,
+    ops: tauri::State<'_, operation::OperationState>,
+    serials: Vec<String>,
+    local_dir: String,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    let op = broadcast::BroadcastOp::Screenshot { local_dir };
+    run_broadcast(&app, &ops, serials, op, parallelism, op_id).await
 }
 
+/// Shared body of the broadcast commands. `op_id` doubles as the broadcast id
+/// in progress events and lets `cancel_operation` stop the remaining devices.
+async fn run_broadcast(
+    app: &tauri::AppHandle,
+    ops: &operation::OperationState,
+    serials: Vec<String>,
+    op: broadcast::BroadcastOp,
+    parallelism: Option<usize>,
+    op_id: Option<String>,
+) -> Result<broadcast::BroadcastResult, AdbError> {
+    if serials.is_empty() {
+        return Err(AdbError::InvalidState("未选择设备".to_string()));
+    }
+    let broadcast_id = op_id.unwrap_or_else(|| {
+        format!("broadcast-{}", chrono::Local::now().timestamp_millis())
+    });
+    let parallelism = parallelism.unwrap_or(broadcast::DEFAULT_PARALLELISM);
+    let cancel = ops.register(&broadcast_id);
+    let result = broadcast::run(app, &broadcast_id, &serials, op, parallelism, &cancel).await;
+    ops.finish(&broadcast_id);
+    Ok(result)
+}
+
+// ---------------------------------------------------------------------------
+// ADB settings — adb executable and server selection
+// ---------------------------------------------------------------------------
+
 #[tauri::command]
-async fn start_logcat(app: tauri::AppHandle, serial: String) -> Result<u32, String> {
-    logcat::start_stream(&app, &serial).await
+fn get_adb_settings(settings: tauri::State<'_, settings::SettingsState>) -> settings::AdbSettings {
+    settings.adb.lock().map(|s| s.clone()).unwrap_or_default()
 }
 
+/// Save the adb executable / server selection. Takes effect for the next
+/// command and logcat stream; the device tracker reconnects right away.
 #[tauri::command]
-async fn stop_logcat(app: tauri::AppHandle, serial: String) -> Result<(), String> {
-    logcat::stop_stream(&app, &serial).await
+fn set_adb_settings(
+    state: tauri::State<'_, settings::SettingsState>,
+    tracker: tauri::State<'_, device_tracker::DeviceTrackerState>,
+    settings: settings::AdbSettings,
+) -> Result<(), AdbError> {
+    settings::update(&state, settings)?;
+    tracker.reconnect.notify_one();
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Timeouts and cancellation
+// ---------------------------------------------------------------------------
+
+/// Cancel an in-flight operation started with `op_id` (or a transfer id).
+#[tauri::command]
+fn cancel_operation(
+    ops: tauri::State<'_, operation::OperationState>,
+    op_id: String,
+) -> Result<(), AdbError> {
+    if ops.cancel(&op_id) {
+        Ok(())
+    } else {
+        Err(AdbError::InvalidState(format!("没有进行中的操作 {}", op_id)))
+    }
+}
+
+#[tauri::command]
+fn get_timeouts(ops: tauri::State<'_, operation::OperationState>) -> operation::Timeouts {
+    ops.timeouts.lock().map(|t| t.clone()).unwrap_or_default()
+}
+
+#[tauri::command]
+fn set_timeouts(
+    ops: tauri::State<'_, operation::OperationState>,
+    timeouts: operation::Timeouts,
+) -> Result<(), AdbError> {
+    ops.set_timeouts(timeouts)
+}
+
+/// Commands that do not keep their own op log still record timeouts and
+/// cancellations, so a killed adb call shows up in the history.
+fn log_if_interrupted<T>(
+    state: &op_log::OpLogState,
+    result: &Result<T, AdbError>,
+    op_type: &str,
+    serial: &str,
+    detail: &str,
+) {
+    let Err(e @ (AdbError::Timeout(_) | AdbError::Cancelled)) = result else {
+        return;
+    };
+    op_log::add_entry(state, op_log::OpLogEntry {
+        error_message: Some(e.to_string()),
+        raw_output: Some(e.detail()),
+        ..op_log::OpLogEntry::new(op_type, serial, detail, false)
+    });
 }
 
 #[tauri::command]
@@ -267,10 +1273,34 @@ fn get_op_logs(
 }
 
 #[tauri::command]
-fn clear_op_logs(state: tauri::State<'_, op_log::OpLogState>) -> Result<(), String> {
+fn clear_op_logs(state: tauri::State<'_, op_log::OpLogState>) -> Result<(), AdbError> {
     op_log::clear_entries(&state)
 }
 
+// ---------------------------------------------------------------------------
+// Automation API
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+fn get_api_status(state: tauri::State<'_, api::ApiState>) -> api::ApiStatus {
+    state.status()
+}
+
+/// Start or stop the local automation API; the choice is saved.
+#[tauri::command]
+async fn set_api_enabled(
+    app: tauri::AppHandle,
+    enabled: bool,
+    port: Option<u16>,
+) -> Result<api::ApiStatus, AdbError> {
+    api::set_enabled(&app, enabled, port).await
+}
+
+#[tauri::command]
+fn regenerate_api_token(state: tauri::State<'_, api::ApiState>) -> Result<api::ApiStatus, AdbError> {
+    api::regenerate_token(&state)
+}
+
 #[tauri::command]
 async fn check_for_updates(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
     let updater = app
@@ -357,6 +1387,26 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .manage(logcat::LogcatState::new())
         .manage(op_log::OpLogState::new())
+        .manage(device_tracker::DeviceTrackerState::new())
+        .manage(adb_client::FeatureCache::default())
+        .manage(device_registry::DeviceRegistryState::new())
+        .manage(health::HealthState::new())
+        .manage(operation::OperationState::new())
+        .manage(settings::SettingsState::new())
+        .manage(api::ApiState::new())
+        .manage(wireless::WirelessState::new())
+        .manage(forward::ForwardState::new())
+        .manage(dev_presets::DevPresetState::new())
+        .setup(|app| {
+            app.state::<op_log::OpLogState>().attach(app.handle().clone());
+            device_tracker::start(app.handle().clone());
+            api::start_if_enabled(app.handle().clone());
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                reconnect_saved_endpoints(&handle).await;
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             adb_version,
             get_devices,
@@ -370,16 +1420,72 @@ pub fn run() {
             take_screenshot,
             push_file,
             pull_file,
+            stat_remote_file,
+            list_remote_dir,
             list_remote_files,
             delete_remote_file,
             connect_wifi,
             disconnect_wifi,
+            pair_wifi,
+            discover_wireless_devices,
+            start_qr_pairing,
+            complete_qr_pairing,
+            wifi_handoff,
+            list_wireless_endpoints,
+            save_wireless_endpoint,
+            remove_wireless_endpoint,
+            reconnect_wireless_endpoints,
+            list_port_rules,
+            add_port_rule,
+            remove_port_rule,
+            remove_all_port_rules,
+            get_port_presets,
+            set_port_preset,
+            list_device_settings,
+            get_device_setting,
+            set_device_setting,
+            pending_setting_reverts,
+            revert_device_settings,
+            list_dev_presets,
+            get_applied_dev_presets,
+            apply_dev_preset,
+            revert_dev_preset,
+            emulator_console,
+            list_emulator_snapshots,
             kill_server,
             start_server,
             start_logcat,
             stop_logcat,
+            reboot_device,
+            wait_for_boot,
+            get_device_labels,
+            set_device_label,
+            remove_device_label,
+            get_health_config,
+            set_health_config,
+            get_health_history,
+            get_device_props,
+            diff_device_props,
+            save_prop_snapshot,
+            list_prop_snapshots,
+            delete_prop_snapshot,
+            broadcast_install_apk,
+            broadcast_uninstall_app,
+            broadcast_clear_app_data,
+            broadcast_force_stop,
+            broadcast_launch_app,
+            broadcast_push_file,
+            broadcast_take_screenshot,
             get_op_logs,
             clear_op_logs,
+            cancel_operation,
+            get_timeouts,
+            set_timeouts,
+            get_adb_settings,
+            set_adb_settings,
+            get_api_status,
+            set_api_enabled,
+            regenerate_api_token,
             check_for_updates,
             download_and_install_update,
             get_app_version,
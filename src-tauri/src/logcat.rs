@@ -2,15 +2,20 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use chrono::Local;
 use serde::{Deserialize, Serialize};
 use tauri::AppHandle;
 use tauri::Emitter;
-use tauri::Manager;
-use tauri_plugin_shell::ShellExt;
-use tauri_plugin_shell::process::CommandEvent;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
+
+use crate::adb_client::{self, AdbClient};
+use crate::device_registry;
+use crate::error::AdbError;
+use crate::transport::{AdbTransport, ProcessLines};
 
 // ---------------------------------------------------------------------------
 // Structs
@@ -27,20 +32,26 @@ pub struct LogcatLine {
     pub raw: String,
 }
 
-/// Managed state: tracks active logcat streams per device serial.
-/// Value is the sidecar child PID so we can kill it later.
+/// Managed state: tracks active logcat streams per device serial. Each
+/// stream's reader task stops when its token is cancelled.
 pub struct LogcatState {
-    pub active_streams: Mutex<HashMap<String, u32>>,
+    pub active_streams: Arc<Mutex<HashMap<String, CancellationToken>>>,
 }
 
 impl LogcatState {
     pub fn new() -> Self {
         Self {
-            active_streams: Mutex::new(HashMap::new()),
+            active_streams: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
 
+impl Default for LogcatState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Parsing
 // ---------------------------------------------------------------------------
@@ -109,9 +120,9 @@ pub fn parse_logcat_line(line: &str) -> Option<LogcatLine> {
             after_level[..colon_pos].trim().to_string(),
             after_level[colon_pos + 2..].to_string(),
         )
-    } else if after_level.ends_with(':') {
+    } else if let Some(tag) = after_level.strip_suffix(':') {
         // Tag with empty message
-        (after_level[..after_level.len() - 1].trim().to_string(), String::new())
+        (tag.trim().to_string(), String::new())
     } else {
         (after_level.trim().to_string(), String::new())
     };
@@ -127,6 +138,35 @@ pub fn parse_logcat_line(line: &str) -> Option<LogcatLine> {
     })
 }
 
+/// Check a logcat filterspec, `TAG:LEVEL` where TAG may be `*` and LEVEL is
+/// one of V/D/I/W/E/F/S, e.g. `ActivityManager:I` or `*:S`.
+pub fn parse_filter_spec(spec: &str) -> Result<String, AdbError> {
+    let invalid = || AdbError::InvalidConfig(format!("invalid logcat filter: {}", spec));
+    let (tag, level) = spec.rsplit_once(':').ok_or_else(invalid)?;
+    let tag_ok = !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c == '*' || c.is_ascii_alphanumeric() || "._-$/".contains(c));
+    if !tag_ok || !matches!(level, "V" | "D" | "I" | "W" | "E" | "F" | "S") {
+        return Err(invalid());
+    }
+    Ok(spec.to_string())
+}
+
+/// Device-side `logcat` command line in threadtime format. `dump` exits after
+/// the current buffer (`-d`); `filters` must have passed [`parse_filter_spec`]
+/// and are quoted so the device shell does not glob `*:S`.
+pub fn logcat_command(dump: bool, filters: &[String]) -> String {
+    let mut command = "logcat -v threadtime".to_string();
+    if dump {
+        command.push_str(" -d");
+    }
+    for filter in filters {
+        command.push_str(&format!(" '{}'", filter));
+    }
+    command
+}
+
 // ---------------------------------------------------------------------------
 // Log directory management
 // ---------------------------------------------------------------------------
@@ -169,144 +209,211 @@ pub fn cleanup_old_logs() {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Native stream
+// ---------------------------------------------------------------------------
+
+/// `logcat -v threadtime` read line by line over the adb server's `shell:`
+/// service, without spawning an adb process.
+pub struct NativeLogcat {
+    reader: BufReader<TcpStream>,
+    /// Partial line kept across calls, so `next_line` is cancel safe.
+    pending: Vec<u8>,
+}
+
+impl NativeLogcat {
+    pub async fn open(client: &AdbClient, serial: &str) -> Result<Self, AdbError> {
+        Self::open_command(client, serial, "logcat -v threadtime").await
+    }
+
+    /// Stream the output of an arbitrary `logcat ...` command line, e.g. one
+    /// built by [`logcat_command`].
+    pub async fn open_command(
+        client: &AdbClient,
+        serial: &str,
+        command: &str,
+    ) -> Result<Self, AdbError> {
+        let mut stream = client.transport(serial).await?;
+        adb_client::send_request(&mut stream, &format!("shell:{}", command)).await?;
+        Ok(Self {
+            reader: BufReader::new(stream),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Next line without the trailing newline; `None` once the device closes the stream.
+    /// Safe to use as a `select!` branch: a partly read line is kept for the next call.
+    pub async fn next_line(&mut self) -> Result<Option<String>, AdbError> {
+        self.reader
+            .read_until(b'\n', &mut self.pending)
+            .await
+            .map_err(|e| AdbError::Protocol(e.to_string()))?;
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let buf = std::mem::take(&mut self.pending);
+        let line = String::from_utf8_lossy(&buf);
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Stream control
 // ---------------------------------------------------------------------------
 
+/// A running logcat: the native `shell:` stream, or an adb process when the
+/// server cannot be reached directly.
+pub enum LogcatStream {
+    Native(NativeLogcat),
+    Process(ProcessLines),
+}
+
+impl LogcatStream {
+    /// Open `logcat -v threadtime` over the native protocol, falling back to
+    /// spawning `adb -s {serial} logcat -v threadtime` through the transport
+    /// when the server is unreachable. `dump` and `filters` are as for
+    /// [`logcat_command`].
+    pub async fn open(
+        adb: &impl AdbTransport,
+        serial: &str,
+        dump: bool,
+        filters: &[String],
+    ) -> Result<Self, AdbError> {
+        if let Some(client) = adb.client() {
+            let command = logcat_command(dump, filters);
+            match NativeLogcat::open_command(client, serial, &command).await {
+                Err(AdbError::ServerUnavailable(_)) => {}
+                opened => return opened.map(Self::Native),
+            }
+        }
+        let mut args = vec!["-s", serial, "logcat", "-v", "threadtime"];
+        if dump {
+            args.push("-d");
+        }
+        args.extend(filters.iter().map(String::as_str));
+        adb.spawn_lines(&args).map(Self::Process)
+    }
+
+    /// PID of the adb child, 0 for a native stream.
+    pub fn pid(&self) -> u32 {
+        match self {
+            Self::Native(_) => 0,
+            Self::Process(process) => process.pid(),
+        }
+    }
+
+    /// Next line without the trailing newline; `None` once the stream ends.
+    /// Safe to use as a `select!` branch.
+    pub async fn next_line(&mut self) -> Result<Option<String>, AdbError> {
+        match self {
+            Self::Native(native) => native.next_line().await,
+            Self::Process(process) => process.next_line().await,
+        }
+    }
+}
+
 /// Start a logcat stream for the given device.
 ///
-/// Spawns `adb -s {serial} logcat -v threadtime` via sidecar, reads stdout
-/// line-by-line, writes each line to a log file, parses it, and emits a
-/// `logcat-line-{serial}` event to the frontend.
+/// Opens it with [`LogcatStream::open`]; each line is written to a log file,
+/// parsed, and passed to `on_line` until the device closes the stream or
+/// [`stop_stream`] is called.
 ///
-/// Returns the child PID on success.
-pub async fn start_stream(app: &AppHandle, serial: &str) -> Result<u32, String> {
+/// Returns the adb child PID on success (0 for a native stream).
+pub async fn start_stream(
+    adb: &impl AdbTransport,
+    state: &LogcatState,
+    serial: &str,
+    mut on_line: impl FnMut(LogcatLine) + Send + 'static,
+) -> Result<u32, AdbError> {
     // Check if already streaming
     {
-        let state = app.state::<LogcatState>();
-        let streams = state.active_streams.lock().map_err(|e| e.to_string())?;
+        let streams = state.active_streams.lock().map_err(|e| AdbError::InvalidState(e.to_string()))?;
         if streams.contains_key(serial) {
-            return Err(format!("Logcat stream already active for device {}", serial));
+            return Err(AdbError::InvalidState(format!("设备 {} 的 Logcat 已在运行", serial)));
         }
     }
 
     // Prepare log file
     let log_dir = get_log_dir();
     let timestamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
-    let log_filename = format!("logcat_{}_{}.log", serial, timestamp);
+    let log_filename = format!("logcat_{}_{}.log", device_registry::file_label(serial), timestamp);
     let log_path = log_dir.join(&log_filename);
 
     let mut log_file = fs::File::create(&log_path)
-        .map_err(|e| format!("Failed to create log file: {}", e))?;
+        .map_err(|e| AdbError::Io(e.to_string()))?;
 
-    // Spawn sidecar with streaming
-    let (mut rx, child) = app
-        .shell()
-        .sidecar("adb")
-        .map_err(|e| format!("Failed to create sidecar: {}", e))?
-        .args(&["-s", serial, "logcat", "-v", "threadtime"])
-        .spawn()
-        .map_err(|e| format!("Failed to spawn logcat: {}", e))?;
-
-    let child_pid = child.pid();
-
-    // Store in active streams
+    let mut stream = LogcatStream::open(adb, serial, false, &[]).await?;
+    let pid = stream.pid();
+    let token = CancellationToken::new();
     {
-        let state = app.state::<LogcatState>();
-        let mut streams = state.active_streams.lock().map_err(|e| e.to_string())?;
-        streams.insert(serial.to_string(), child_pid);
+        let mut streams = state.active_streams.lock().map_err(|e| AdbError::InvalidState(e.to_string()))?;
+        streams.insert(serial.to_string(), token.clone());
     }
 
-    // Clone what we need for the async task
-    let app_handle = app.clone();
+    let active_streams = Arc::clone(&state.active_streams);
     let serial_owned = serial.to_string();
-    let event_name = format!("logcat-line-{}", serial);
-
-    // Spawn async reader task
-    tauri::async_runtime::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            match event {
-                CommandEvent::Stdout(line_bytes) => {
-                    let line = String::from_utf8_lossy(&line_bytes).to_string();
-
+    tokio::spawn(async move {
+        loop {
+            let line = tokio::select! {
+                _ = token.cancelled() => break,
+                line = stream.next_line() => line,
+            };
+            match line {
+                Ok(Some(line)) => {
                     // Write to log file (ignore write errors to keep streaming)
                     let _ = writeln!(log_file, "{}", line);
-
-                    // Parse and emit to frontend
-                    if let Some(parsed) = parse_logcat_line(&line) {
-                        let _ = app_handle.emit(&event_name, &parsed);
-                    } else if !line.trim().is_empty() {
-                        // Emit unparseable non-empty lines as raw
-                        let raw_line = LogcatLine {
-                            timestamp: String::new(),
-                            pid: String::new(),
-                            tid: String::new(),
-                            level: String::new(),
-                            tag: String::new(),
-                            message: line.clone(),
-                            raw: line,
-                        };
-                        let _ = app_handle.emit(&event_name, &raw_line);
-                    }
-                }
-                CommandEvent::Stderr(line_bytes) => {
-                    let line = String::from_utf8_lossy(&line_bytes).to_string();
-                    let _ = writeln!(log_file, "[STDERR] {}", line);
-                }
-                CommandEvent::Terminated(status) => {
-                    let _ = writeln!(
-                        log_file,
-                        "\n--- logcat terminated: {:?} ---",
-                        status
-                    );
-
-                    // Clean up from active streams
-                    if let Some(state) = app_handle.try_state::<LogcatState>() {
-                        if let Ok(mut streams) = state.active_streams.lock() {
-                            streams.remove(&serial_owned);
-                        }
+                    if let Some(parsed) = to_logcat_line(line) {
+                        on_line(parsed);
                     }
-                    break;
-                }
-                CommandEvent::Error(err) => {
-                    let _ = writeln!(log_file, "[ERROR] {}", err);
                 }
-                _ => {}
+                Ok(None) | Err(_) => break,
+            }
+        }
+        let _ = writeln!(log_file, "\n--- logcat stream closed ---");
+        // Only clean up if stop_stream has not already done so
+        if !token.is_cancelled() {
+            if let Ok(mut streams) = active_streams.lock() {
+                streams.remove(&serial_owned);
             }
         }
     });
 
-    Ok(child_pid)
+    Ok(pid)
 }
 
-/// Stop the logcat stream for the given device.
-///
-/// Removes the stream from active_streams. The sidecar process is killed
-/// by dropping it through Tauri's command child API.
-pub async fn stop_stream(app: &AppHandle, serial: &str) -> Result<(), String> {
-    let pid = {
-        let state = app.state::<LogcatState>();
-        let mut streams = state.active_streams.lock().map_err(|e| e.to_string())?;
-        streams
-            .remove(serial)
-            .ok_or_else(|| format!("No active logcat stream for device {}", serial))?
-    };
-
-    // Kill the sidecar process by PID using system kill
-    #[cfg(unix)]
-    {
-        unsafe {
-            libc::kill(pid as i32, libc::SIGTERM);
-        }
+/// Parse a line for the frontend; unparseable non-empty lines are passed on raw.
+fn to_logcat_line(line: String) -> Option<LogcatLine> {
+    if let Some(parsed) = parse_logcat_line(&line) {
+        return Some(parsed);
     }
+    (!line.trim().is_empty()).then(|| LogcatLine {
+        timestamp: String::new(),
+        pid: String::new(),
+        tid: String::new(),
+        level: String::new(),
+        tag: String::new(),
+        message: line.clone(),
+        raw: line,
+    })
+}
 
-    #[cfg(windows)]
-    {
-        let _ = std::process::Command::new("taskkill")
-            .args(&["/PID", &pid.to_string(), "/F"])
-            .output();
+/// Line callback that emits `logcat-line-{serial}` events to the frontend.
+pub fn emit_lines(app: &AppHandle, serial: &str) -> impl FnMut(LogcatLine) + Send + 'static {
+    let app = app.clone();
+    let event_name = format!("logcat-line-{}", serial);
+    move |line| {
+        let _ = app.emit(&event_name, &line);
     }
+}
 
+/// Stop the logcat stream for the given device. Its reader task closes the
+/// stream; an adb child is killed when it is dropped.
+pub fn stop_stream(state: &LogcatState, serial: &str) -> Result<(), AdbError> {
+    let mut streams = state.active_streams.lock().map_err(|e| AdbError::InvalidState(e.to_string()))?;
+    let token = streams
+        .remove(serial)
+        .ok_or_else(|| AdbError::InvalidState(format!("设备 {} 没有运行中的 Logcat", serial)))?;
+    token.cancel();
     Ok(())
 }
 
@@ -346,6 +453,27 @@ mod tests {
         assert!(parse_logcat_line("--------- beginning of main").is_none());
     }
 
+    #[test]
+    fn test_parse_filter_spec() {
+        assert!(parse_filter_spec("ActivityManager:I").is_ok());
+        assert!(parse_filter_spec("*:S").is_ok());
+        assert!(parse_filter_spec("chromium.net:W").is_ok());
+        assert!(parse_filter_spec("ActivityManager").is_err());
+        assert!(parse_filter_spec("Tag:X").is_err());
+        assert!(parse_filter_spec(":E").is_err());
+        assert!(parse_filter_spec("'; reboot; ':E").is_err());
+    }
+
+    #[test]
+    fn test_logcat_command() {
+        assert_eq!(logcat_command(false, &[]), "logcat -v threadtime");
+        let filters = vec!["MyApp:D".to_string(), "*:S".to_string()];
+        assert_eq!(
+            logcat_command(true, &filters),
+            "logcat -v threadtime -d 'MyApp:D' '*:S'"
+        );
+    }
+
     #[test]
     fn test_parse_logcat_line_with_spaces_in_message() {
         let line = "03-10 14:22:33.456  1000  2000 I ActivityManager: Start proc 1234:com.example/u0a12 for activity";
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+use crate::device_registry::{self, DeviceRegistryState};
+use crate::error::AdbError;
+use crate::storage;
 
 // ---------------------------------------------------------------------------
 // Data types
@@ -10,7 +15,7 @@ use std::sync::Mutex;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpLogEntry {
     pub timestamp: String,
-    pub op_type: String, // "install", "uninstall", "screenshot", "upload", "download"
+    pub op_type: String, // "install", "uninstall", "clear_data", "force_stop", "launch", "screenshot", "upload", "download"
     pub device: String,
     pub detail: String,
     pub success: bool,
@@ -18,44 +23,85 @@ pub struct OpLogEntry {
     #[serde(default)]
     pub command: Option<String>,
     pub raw_output: Option<String>,
+    /// Bytes moved by a file transfer.
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// Who asked for the operation: `None` for the GUI, `"cli"` or `"api"`.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Registry alias of `device` when the entry was written.
+    #[serde(default)]
+    pub device_alias: Option<String>,
+    /// For `setting` entries: the value that was replaced, so the change can
+    /// be reverted.
+    #[serde(default)]
+    pub setting: Option<crate::device_settings::SettingChange>,
+}
+
+impl OpLogEntry {
+    /// Entry stamped with the current local time; the optional fields start
+    /// empty and are set with struct update syntax.
+    pub fn new(op_type: &str, device: &str, detail: impl Into<String>, success: bool) -> Self {
+        Self {
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            op_type: op_type.to_string(),
+            device: device.to_string(),
+            detail: detail.into(),
+            success,
+            error_message: None,
+            command: None,
+            raw_output: None,
+            size_bytes: None,
+            duration_ms: None,
+            source: None,
+            device_alias: None,
+            setting: None,
+        }
+    }
 }
 
 pub struct OpLogState {
     pub entries: Mutex<Vec<OpLogEntry>>,
+    /// Set by [`OpLogState::attach`] in the app; the CLI has none.
+    app: OnceLock<AppHandle>,
 }
 
+/// `~/AdbTool/op_history.json`; a missing or corrupt file starts an empty log.
+const LOG_FILE: &str = "op_history.json";
+
 // ---------------------------------------------------------------------------
-// Persistence helpers
+// Source scope
+//
+// Commands write their own entries and do not know who called them. The
+// automation API runs them inside `with_source`, which tags those entries and
+// tells the caller whether the command logged anything at all.
 // ---------------------------------------------------------------------------
 
-/// Returns `~/AdbTool/op_history.json`.
-fn get_log_path() -> PathBuf {
-    let home = dirs::home_dir().expect("cannot resolve home directory");
-    home.join("AdbTool").join("op_history.json")
+struct SourceScope {
+    source: &'static str,
+    logged: AtomicBool,
 }
 
-/// Read the JSON log file into a Vec. Returns an empty Vec on any I/O or
-/// parse error so callers never need to handle a missing/corrupt file.
-fn load_from_file() -> Result<Vec<OpLogEntry>, String> {
-    let path = get_log_path();
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
-    let data = fs::read_to_string(&path).map_err(|e| format!("read log file: {e}"))?;
-    if data.trim().is_empty() {
-        return Ok(Vec::new());
-    }
-    serde_json::from_str(&data).map_err(|e| format!("parse log file: {e}"))
+tokio::task_local! {
+    static SCOPE: SourceScope;
 }
 
-/// Persist the full entry list back to disk (atomic-ish: write then overwrite).
-fn save_to_file(entries: &[OpLogEntry]) -> Result<(), String> {
-    let path = get_log_path();
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("create log dir: {e}"))?;
-    }
-    let json = serde_json::to_string_pretty(entries).map_err(|e| format!("serialize: {e}"))?;
-    fs::write(&path, json).map_err(|e| format!("write log file: {e}"))
+/// Run `fut` with every entry it adds tagged with `source`. Also returns
+/// whether any entry was added. Tasks spawned by `fut` are not covered.
+pub async fn with_source<F: Future>(source: &'static str, fut: F) -> (F::Output, bool) {
+    let scope = SourceScope {
+        source,
+        logged: AtomicBool::new(false),
+    };
+    SCOPE
+        .scope(scope, async {
+            let output = fut.await;
+            let logged = SCOPE.with(|scope| scope.logged.load(Ordering::Relaxed));
+            (output, logged)
+        })
+        .await
 }
 
 // ---------------------------------------------------------------------------
@@ -65,27 +111,66 @@ fn save_to_file(entries: &[OpLogEntry]) -> Result<(), String> {
 impl OpLogState {
     /// Create state, pre-loading any existing entries from disk.
     pub fn new() -> Self {
-        let entries = load_from_file().unwrap_or_default();
+        let entries = storage::load_or_default(LOG_FILE);
         Self {
             entries: Mutex::new(entries),
+            app: OnceLock::new(),
+        }
+    }
+
+    /// Resolve device aliases from the app's managed registry instead of
+    /// reading `devices.json` for every entry.
+    pub fn attach(&self, app: AppHandle) {
+        let _ = self.app.set(app);
+    }
+
+    fn alias_for(&self, serial: &str) -> Option<String> {
+        match self.app.get() {
+            Some(app) => app.try_state::<DeviceRegistryState>()?.alias(serial),
+            None => device_registry::alias_for(serial),
         }
     }
 }
 
-/// Append an entry and persist to disk.
-pub fn add_entry(state: &OpLogState, entry: OpLogEntry) {
+impl Default for OpLogState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Append an entry and persist to disk. The file is re-read under a lock
+/// first, so entries the CLI (or a running GUI) appended are kept. Inside
+/// [`with_source`] the entry is tagged with that source unless it already
+/// names one; the device alias is filled from the registry.
+pub fn add_entry(state: &OpLogState, mut entry: OpLogEntry) {
+    if entry.device_alias.is_none() {
+        entry.device_alias = state.alias_for(&entry.device);
+    }
+    let _ = SCOPE.try_with(|scope| {
+        scope.logged.store(true, Ordering::Relaxed);
+        if entry.source.is_none() {
+            entry.source = Some(scope.source.to_string());
+        }
+    });
     let mut entries = state.entries.lock().expect("op_log lock poisoned");
-    entries.push(entry);
-    if let Err(e) = save_to_file(&entries) {
+    let saved = storage::locked(LOG_FILE, || {
+        // The GUI and the CLI share the file: pick up what the other wrote
+        if let Ok(Some(on_disk)) = storage::read(LOG_FILE) {
+            *entries = on_disk;
+        }
+        entries.push(entry);
+        storage::write(LOG_FILE, &*entries)
+    });
+    if let Err(e) = saved {
         eprintln!("[op_log] failed to save: {e}");
     }
 }
 
 /// Clear all entries and persist the empty list to disk.
-pub fn clear_entries(state: &OpLogState) -> Result<(), String> {
+pub fn clear_entries(state: &OpLogState) -> Result<(), AdbError> {
     let mut entries = state.entries.lock().expect("op_log lock poisoned");
     entries.clear();
-    save_to_file(&entries)
+    storage::locked(LOG_FILE, || storage::write(LOG_FILE, &*entries))
 }
 
 /// Return entries, optionally filtered by `op_type` and/or `device`.
@@ -97,8 +182,8 @@ pub fn get_entries(
     let entries = state.entries.lock().expect("op_log lock poisoned");
     entries
         .iter()
-        .filter(|e| op_type.map_or(true, |t| e.op_type == t))
-        .filter(|e| device.map_or(true, |d| e.device == d))
+        .filter(|e| op_type.is_none_or(|t| e.op_type == t))
+        .filter(|e| device.is_none_or(|d| e.device == d || e.device_alias.as_deref() == Some(d)))
         .cloned()
         .collect()
 }
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::AdbError;
+use crate::storage;
+
+// ---------------------------------------------------------------------------
+// Operation classes and timeouts
+// ---------------------------------------------------------------------------
+
+/// Rough cost of an adb call; selects which default timeout applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpClass {
+    /// getprop, devices, version — should answer within seconds.
+    Fast,
+    /// dumpsys, pm, ls, screencap and other shell work.
+    Normal,
+    /// install, push, pull — bounded only by file size.
+    Long,
+}
+
+/// Per-class timeouts in seconds, adjustable from the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timeouts {
+    pub fast_secs: u64,
+    pub normal_secs: u64,
+    pub long_secs: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            fast_secs: 10,
+            normal_secs: 60,
+            long_secs: 30 * 60,
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn for_class(&self, class: OpClass) -> Duration {
+        let secs = match class {
+            OpClass::Fast => self.fast_secs,
+            OpClass::Normal => self.normal_secs,
+            OpClass::Long => self.long_secs,
+        };
+        Duration::from_secs(secs.max(1))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Managed state
+// ---------------------------------------------------------------------------
+
+/// `~/AdbTool/timeouts.json`; a missing or unreadable file means the defaults.
+const TIMEOUTS_FILE: &str = "timeouts.json";
+
+/// Managed state: configured timeouts plus a cancellation token for every
+/// in-flight operation the frontend gave an id to.
+pub struct OperationState {
+    pub timeouts: Mutex<Timeouts>,
+    pub active: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl OperationState {
+    pub fn new() -> Self {
+        Self {
+            timeouts: Mutex::new(storage::load_or_default(TIMEOUTS_FILE)),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn timeout_for(&self, class: OpClass) -> Duration {
+        self.timeouts
+            .lock()
+            .map(|t| t.for_class(class))
+            .unwrap_or_else(|_| Timeouts::default().for_class(class))
+    }
+
+    /// Persist and apply new timeouts; they take effect for the next call.
+    pub fn set_timeouts(&self, timeouts: Timeouts) -> Result<(), AdbError> {
+        let mut current = self
+            .timeouts
+            .lock()
+            .map_err(|_| AdbError::InvalidState("超时设置被锁定".to_string()))?;
+        storage::write(TIMEOUTS_FILE, &timeouts)?;
+        *current = timeouts;
+        Ok(())
+    }
+
+    /// Register an operation and return the token its worker should observe.
+    pub fn register(&self, op_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        if let Ok(mut active) = self.active.lock() {
+            active.insert(op_id.to_string(), token.clone());
+        }
+        token
+    }
+
+    pub fn finish(&self, op_id: &str) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(op_id);
+        }
+    }
+
+    /// Trigger cancellation. Returns false if no such operation is running.
+    pub fn cancel(&self, op_id: &str) -> bool {
+        let Ok(active) = self.active.lock() else {
+            return false;
+        };
+        match active.get(op_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run `fut` under `op_id` (if given) so `cancel_operation` can abort it.
+    /// Dropping the future closes native sockets and kills sidecar children.
+    pub async fn run<T, F>(&self, op_id: Option<&str>, fut: F) -> Result<T, AdbError>
+    where
+        F: Future<Output = Result<T, AdbError>>,
+    {
+        let Some(op_id) = op_id else {
+            return fut.await;
+        };
+        let token = self.register(op_id);
+        let result = cancellable(&token, fut).await;
+        self.finish(op_id);
+        result
+    }
+}
+
+impl Default for OperationState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Race `fut` against the token; a cancelled token yields `AdbError::Cancelled`.
+pub async fn cancellable<T, F>(token: &CancellationToken, fut: F) -> Result<T, AdbError>
+where
+    F: Future<Output = Result<T, AdbError>>,
+{
+    // Checked first, so an already-cancelled token wins over a ready future
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(AdbError::Cancelled),
+        result = fut => result,
+    }
+}
+
+/// Bound `fut` by `timeout`; `what` names the call in the Timeout detail.
+pub async fn with_timeout<T, F>(timeout: Duration, what: &str, fut: F) -> Result<T, AdbError>
+where
+    F: Future<Output = Result<T, AdbError>>,
+{
+    tokio::time::timeout(timeout, fut)
+        .await
+        .unwrap_or_else(|_| {
+            Err(AdbError::Timeout(format!(
+                "{} ({}s)",
+                what,
+                timeout.as_secs()
+            )))
+        })
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_with_timeout_expires() {
+        let result: Result<(), AdbError> =
+            with_timeout(Duration::from_millis(20), "adb shell dumpsys", async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(AdbError::Timeout(d)) if d.contains("dumpsys")));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_operation() {
+        let state = std::sync::Arc::new(OperationState::new());
+        let runner = state.clone();
+        let task = tokio::spawn(async move {
+            runner
+                .run(Some("op-1"), async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(())
+                })
+                .await
+        });
+
+        // Wait until the operation is registered, then cancel it
+        while !state.cancel("op-1") {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(task.await.unwrap(), Err(AdbError::Cancelled));
+        assert!(!state.cancel("op-1"));
+    }
+
+    #[test]
+    fn test_timeouts_per_class() {
+        let timeouts = Timeouts::default();
+        assert!(timeouts.for_class(OpClass::Fast) < timeouts.for_class(OpClass::Normal));
+        assert!(timeouts.for_class(OpClass::Normal) < timeouts.for_class(OpClass::Long));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::device_info;
+use crate::error::AdbError;
+use crate::operation::OpClass;
+use crate::storage;
+use crate::transport::AdbTransport;
+
+// ---------------------------------------------------------------------------
+// Structs
+// ---------------------------------------------------------------------------
+
+/// Full `getprop` map of a device at one point in time, saved as
+/// `~/AdbTool/props/<name>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropSnapshot {
+    pub name: String,
+    pub serial: String,
+    pub model: String,
+    pub fingerprint: String,
+    pub created_at: String,
+    pub props: BTreeMap<String, String>,
+}
+
+/// Snapshot listing entry, without the property map.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropSnapshotInfo {
+    pub name: String,
+    pub serial: String,
+    pub model: String,
+    pub fingerprint: String,
+    pub created_at: String,
+    pub prop_count: usize,
+}
+
+/// One side of a diff: `{"device": "SERIAL"}` or `{"snapshot": "name"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropSource {
+    Device(String),
+    Snapshot(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropChange {
+    pub key: String,
+    /// `None` when the property only exists on the right.
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropDiffGroup {
+    /// One of [`DIFF_GROUPS`], or `other`.
+    pub prefix: String,
+    pub changes: Vec<PropChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropDiff {
+    pub left: String,
+    pub right: String,
+    /// Only groups with at least one change, in [`DIFF_GROUPS`] order.
+    pub groups: Vec<PropDiffGroup>,
+    pub unchanged: usize,
+}
+
+/// Diff groups, first match wins. `ro.vendor.*` counts as vendor.
+pub const DIFF_GROUPS: &[(&str, &[&str])] = &[
+    ("ro.build", &["ro.build."]),
+    ("persist", &["persist."]),
+    ("debug", &["debug."]),
+    ("vendor", &["vendor.", "ro.vendor."]),
+];
+
+// ---------------------------------------------------------------------------
+// Reading and diffing
+// ---------------------------------------------------------------------------
+
+/// The complete parsed `getprop` map of a device.
+pub async fn get_props(
+    adb: &impl AdbTransport,
+    serial: &str,
+) -> Result<BTreeMap<String, String>, AdbError> {
+    let output = adb
+        .exec_device(serial, &["shell", "getprop"], OpClass::Fast)
+        .await?;
+    Ok(device_info::parse_getprop(&output))
+}
+
+/// Resolve a diff side to its label and property map.
+pub async fn load_source(
+    adb: &impl AdbTransport,
+    source: &PropSource,
+) -> Result<(String, BTreeMap<String, String>), AdbError> {
+    match source {
+        PropSource::Device(serial) => Ok((serial.clone(), get_props(adb, serial).await?)),
+        PropSource::Snapshot(name) => {
+            let snapshot = load_snapshot(name)?;
+            Ok((
+                format!("{} ({})", snapshot.name, snapshot.created_at),
+                snapshot.props,
+            ))
+        }
+    }
+}
+
+/// Compare two property maps key by key and group the differences.
+pub fn diff_props(
+    left_label: &str,
+    left: &BTreeMap<String, String>,
+    right_label: &str,
+    right: &BTreeMap<String, String>,
+) -> PropDiff {
+    let mut groups: Vec<PropDiffGroup> = DIFF_GROUPS
+        .iter()
+        .map(|(prefix, _)| prefix.to_string())
+        .chain(std::iter::once("other".to_string()))
+        .map(|prefix| PropDiffGroup {
+            prefix,
+            changes: Vec::new(),
+        })
+        .collect();
+
+    let mut unchanged = 0;
+    let keys: std::collections::BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+    for key in keys {
+        let (l, r) = (left.get(key), right.get(key));
+        if l == r {
+            unchanged += 1;
+            continue;
+        }
+        let index = DIFF_GROUPS
+            .iter()
+            .position(|(_, prefixes)| prefixes.iter().any(|p| key.starts_with(p)))
+            .unwrap_or(DIFF_GROUPS.len());
+        groups[index].changes.push(PropChange {
+            key: key.clone(),
+            left: l.cloned(),
+            right: r.cloned(),
+        });
+    }
+    groups.retain(|g| !g.changes.is_empty());
+
+    PropDiff {
+        left: left_label.to_string(),
+        right: right_label.to_string(),
+        groups,
+        unchanged,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Snapshots
+// ---------------------------------------------------------------------------
+
+/// Snapshots live in `~/AdbTool/props/`.
+const SNAPSHOT_DIR: &str = "props";
+
+/// Keep snapshot names usable as file names.
+fn sanitize_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+/// Storage name of snapshot `name`.
+fn snapshot_file(name: &str) -> String {
+    format!("{}/{}.json", SNAPSHOT_DIR, sanitize_name(name))
+}
+
+/// Save the device's current properties. The name defaults to
+/// `<serial>_<YYYYmmdd_HHMMSS>`; an existing snapshot of the same name is
+/// replaced.
+pub async fn save_snapshot(
+    adb: &impl AdbTransport,
+    serial: &str,
+    name: Option<&str>,
+) -> Result<PropSnapshot, AdbError> {
+    let props = get_props(adb, serial).await?;
+    let now = chrono::Local::now();
+    let name = match name.map(sanitize_name).filter(|n| !n.is_empty()) {
+        Some(name) => name,
+        None => sanitize_name(&format!("{}_{}", serial, now.format("%Y%m%d_%H%M%S"))),
+    };
+    let snapshot = PropSnapshot {
+        name,
+        serial: serial.to_string(),
+        model: props.get("ro.product.model").cloned().unwrap_or_default(),
+        fingerprint: props
+            .get("ro.build.fingerprint")
+            .cloned()
+            .unwrap_or_default(),
+        created_at: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        props,
+    };
+
+    storage::write(&snapshot_file(&snapshot.name), &snapshot)?;
+    Ok(snapshot)
+}
+
+pub fn load_snapshot(name: &str) -> Result<PropSnapshot, AdbError> {
+    let file = snapshot_file(name);
+    storage::read(&file)?
+        .ok_or_else(|| AdbError::NoSuchPath(storage::path(&file).to_string_lossy().to_string()))
+}
+
+/// All saved snapshots, newest first. Unreadable files are skipped.
+pub fn list_snapshots() -> Vec<PropSnapshotInfo> {
+    let Ok(entries) = fs::read_dir(storage::path(SNAPSHOT_DIR)) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<PropSnapshotInfo> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|data| serde_json::from_str::<PropSnapshot>(&data).ok())
+        .map(|s| PropSnapshotInfo {
+            prop_count: s.props.len(),
+            name: s.name,
+            serial: s.serial,
+            model: s.model,
+            fingerprint: s.fingerprint,
+            created_at: s.created_at,
+        })
+        .collect();
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    snapshots
+}
+
+pub fn delete_snapshot(name: &str) -> Result<(), AdbError> {
+    let path = storage::path(&snapshot_file(name));
+    fs::remove_file(&path).map_err(|_| AdbError::NoSuchPath(path.to_string_lossy().to_string()))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_props_groups() {
+        let before = map(&[
+            ("ro.build.id", "UQ1A.240105.004"),
+            ("ro.build.version.security_patch", "2024-01-05"),
+            ("persist.sys.locale", "en-US"),
+            ("ro.vendor.build.id", "UQ1A.240105.004"),
+            ("ro.product.model", "Pixel 7"),
+            ("debug.hwui.renderer", "skiagl"),
+        ]);
+        let after = map(&[
+            ("ro.build.id", "AP1A.240305.019"),
+            ("ro.build.version.security_patch", "2024-03-05"),
+            ("persist.sys.locale", "en-US"),
+            ("ro.vendor.build.id", "AP1A.240305.019"),
+            ("ro.product.model", "Pixel 7"),
+            ("sys.new_prop", "1"),
+        ]);
+
+        let diff = diff_props("before", &before, "after", &after);
+        assert_eq!(diff.unchanged, 2);
+        let prefixes: Vec<&str> = diff.groups.iter().map(|g| g.prefix.as_str()).collect();
+        assert_eq!(prefixes, vec!["ro.build", "debug", "vendor", "other"]);
+        assert_eq!(diff.groups[0].changes.len(), 2);
+        assert_eq!(
+            diff.groups[1].changes[0],
+            PropChange {
+                key: "debug.hwui.renderer".to_string(),
+                left: Some("skiagl".to_string()),
+                right: None,
+            }
+        );
+        assert_eq!(diff.groups[3].changes[0].left, None);
+    }
+
+    #[test]
+    fn test_sanitize_name_and_source_shape() {
+        assert_eq!(
+            sanitize_name("Pixel 7 / before OTA"),
+            "Pixel_7___before_OTA"
+        );
+        assert_eq!(sanitize_name("../etc"), "_etc");
+
+        let source: PropSource = serde_json::from_str(r#"{"snapshot":"base"}"#).unwrap();
+        assert!(matches!(source, PropSource::Snapshot(ref n) if n == "base"));
+        let source: PropSource = serde_json::from_str(r#"{"device":"SER"}"#).unwrap();
+        assert!(matches!(source, PropSource::Device(ref s) if s == "SER"));
+    }
+}
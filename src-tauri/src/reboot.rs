@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::adb;
+use crate::error::AdbError;
+use crate::operation::OpClass;
+use crate::transport::AdbTransport;
+
+// ---------------------------------------------------------------------------
+// Reboot and boot detection
+//
+// `adb reboot` returns as soon as the device accepted the request. Boot
+// progress is then followed by polling the device list (gone/offline →
+// `device`) and, once the shell is reachable, `sys.boot_completed` and
+// `init.svc.bootanim`.
+// ---------------------------------------------------------------------------
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to wait for the device to drop off after `adb reboot` before
+/// assuming it rebooted faster than the poll interval.
+const GO_DOWN_GRACE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RebootMode {
+    System,
+    Bootloader,
+    Recovery,
+    Sideload,
+}
+
+impl RebootMode {
+    /// Argument to `adb reboot`; empty for a normal reboot.
+    pub fn target(self) -> &'static str {
+        match self {
+            RebootMode::System => "",
+            RebootMode::Bootloader => "bootloader",
+            RebootMode::Recovery => "recovery",
+            RebootMode::Sideload => "sideload",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BootPhase {
+    /// Not listed, or listed as offline.
+    Offline,
+    /// Listed as `device`, still booting.
+    Device,
+    /// `sys.boot_completed` is 1 and the boot animation has stopped.
+    Booted,
+}
+
+/// Payload for `reboot-progress-{serial}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BootProgress {
+    pub serial: String,
+    pub phase: BootPhase,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebootResult {
+    pub mode: RebootMode,
+    /// Time until fully booted; `None` when not waited for.
+    pub boot_ms: Option<u64>,
+}
+
+/// Ask the device to reboot into `mode`.
+pub async fn reboot(
+    adb: &impl AdbTransport,
+    serial: &str,
+    mode: RebootMode,
+) -> Result<(), AdbError> {
+    let mut args = vec!["reboot"];
+    if !mode.target().is_empty() {
+        args.push(mode.target());
+    }
+    adb.exec_device(serial, &args, OpClass::Normal).await?;
+    Ok(())
+}
+
+/// Wait until `serial` is back and fully booted, reporting each phase once
+/// through `on_phase`. With `expect_down` the device is first given
+/// [`GO_DOWN_GRACE`] to drop off, so a call right after `reboot` does not
+/// see the old, still-running system as booted.
+pub async fn wait_for_boot(
+    adb: &impl AdbTransport,
+    serial: &str,
+    timeout: Duration,
+    expect_down: bool,
+    mut on_phase: impl FnMut(BootPhase, u64),
+) -> Result<u64, AdbError> {
+    let started = Instant::now();
+    let deadline = started + timeout;
+    let elapsed = || started.elapsed().as_millis() as u64;
+    let mut phase: Option<BootPhase> = None;
+    let mut report = |next: BootPhase, phase: &mut Option<BootPhase>| {
+        if *phase != Some(next) {
+            *phase = Some(next);
+            on_phase(next, elapsed());
+        }
+    };
+
+    if expect_down {
+        let grace = started + GO_DOWN_GRACE.min(timeout);
+        while Instant::now() < grace {
+            if device_state(adb, serial).await.as_deref() != Some("device") {
+                report(BootPhase::Offline, &mut phase);
+                break;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    loop {
+        match device_state(adb, serial).await.as_deref() {
+            Some("device") => {
+                report(BootPhase::Device, &mut phase);
+                if boot_completed(adb, serial).await {
+                    report(BootPhase::Booted, &mut phase);
+                    return Ok(elapsed());
+                }
+            }
+            _ => report(BootPhase::Offline, &mut phase),
+        }
+        if Instant::now() + POLL_INTERVAL > deadline {
+            return Err(AdbError::Timeout(format!(
+                "设备 {} 未在 {} 秒内完成启动",
+                serial,
+                timeout.as_secs()
+            )));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Current state of `serial` in the device list, `None` when not listed or
+/// the server is unreachable (it may be restarting with the device).
+async fn device_state(adb: &impl AdbTransport, serial: &str) -> Option<String> {
+    adb::list_devices(adb)
+        .await
+        .ok()?
+        .into_iter()
+        .find(|d| d.serial == serial)
+        .map(|d| d.state)
+}
+
+async fn boot_completed(adb: &impl AdbTransport, serial: &str) -> bool {
+    let Ok(output) = adb
+        .shell(
+            serial,
+            "getprop sys.boot_completed; getprop init.svc.bootanim",
+            OpClass::Fast,
+        )
+        .await
+    else {
+        return false;
+    };
+    is_boot_completed(&output.stdout)
+}
+
+/// `sys.boot_completed` and `init.svc.bootanim`, one per line. Builds without
+/// a boot animation (headless emulators) print an empty second line.
+fn is_boot_completed(output: &str) -> bool {
+    let mut lines = output.lines().map(str::trim);
+    let completed = lines.next() == Some("1");
+    let bootanim = lines.next().unwrap_or_default();
+    completed && (bootanim.is_empty() || bootanim == "stopped")
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_is_boot_completed() {
+        assert!(is_boot_completed("1\nstopped\n"));
+        assert!(is_boot_completed("1\n\n"));
+        assert!(!is_boot_completed("1\nrunning\n"));
+        assert!(!is_boot_completed("\nrunning\n"));
+        assert!(!is_boot_completed(""));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_booted_device() {
+        let mock = MockTransport::from_transcript(
+            "$ adb devices -l\n\
+             List of devices attached\n\
+             SER                    device product:panther model:Pixel_7\n\
+             $ adb -s SER shell getprop sys.boot_completed; getprop init.svc.bootanim\n\
+             1\n\
+             stopped\n",
+        );
+        let mut phases = Vec::new();
+        wait_for_boot(&mock, "SER", Duration::from_secs(5), false, |p, _| {
+            phases.push(p)
+        })
+        .await
+        .unwrap();
+        assert_eq!(phases, vec![BootPhase::Device, BootPhase::Booted]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_boot_times_out() {
+        let mock = MockTransport::from_transcript("$ adb devices -l\nList of devices attached\n");
+        let mut phases = Vec::new();
+        let err = wait_for_boot(&mock, "SER", Duration::from_secs(1), true, |p, _| {
+            phases.push(p)
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AdbError::Timeout(_)));
+        assert_eq!(phases, vec![BootPhase::Offline]);
+    }
+}
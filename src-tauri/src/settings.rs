@@ -0,0 +1,340 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri::Manager;
+
+use crate::adb_client::{AdbClient, DEFAULT_HOST, DEFAULT_PORT};
+use crate::error::AdbError;
+use crate::storage;
+
+// ---------------------------------------------------------------------------
+// adb connection settings
+//
+// Which adb executable to run and which adb server to talk to. The same
+// settings drive both paths: the native protocol client connects to the
+// resolved server, and every spawned adb process gets the matching `-H/-P`
+// arguments or `ADB_SERVER_SOCKET` environment variable.
+// ---------------------------------------------------------------------------
+
+pub const SERVER_SOCKET_ENV: &str = "ADB_SERVER_SOCKET";
+
+/// `~/AdbTool/settings.json`; missing or unreadable settings fall back to
+/// the defaults.
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AdbSettings {
+    /// adb executable to run; `None` uses the bundled sidecar.
+    #[serde(default)]
+    pub adb_path: Option<String>,
+    /// adb server host (`adb -H`); `None` means localhost.
+    #[serde(default)]
+    pub server_host: Option<String>,
+    /// adb server port (`adb -P`); `None` means 5037.
+    #[serde(default)]
+    pub server_port: Option<u16>,
+    /// Forwarded as `ADB_SERVER_SOCKET`, e.g. `tcp:192.168.1.20:5037`.
+    /// Takes precedence over host/port, as it does for adb itself. A
+    /// configured host/port in turn beats an inherited `ADB_SERVER_SOCKET`.
+    #[serde(default)]
+    pub server_socket: Option<String>,
+}
+
+impl AdbSettings {
+    /// Reject values adb would choke on before they are saved.
+    pub fn validate(&self) -> Result<(), AdbError> {
+        if let Some(path) = &self.adb_path {
+            if !Path::new(path).is_file() {
+                return Err(AdbError::InvalidConfig(format!("adb not found: {}", path)));
+            }
+        }
+        if self
+            .server_host
+            .as_deref()
+            .is_some_and(|h| h.trim().is_empty())
+        {
+            return Err(AdbError::InvalidConfig("empty server host".to_string()));
+        }
+        if self.server_port == Some(0) {
+            return Err(AdbError::InvalidConfig("server port 0".to_string()));
+        }
+        if let Some(socket) = &self.server_socket {
+            let bad_tcp = socket.starts_with("tcp:") && parse_tcp_socket(socket).is_none();
+            if bad_tcp || !socket.contains(':') {
+                return Err(AdbError::InvalidConfig(format!(
+                    "invalid server socket: {}",
+                    socket
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Client for the configured server. `None` when the server is only
+    /// reachable through a non-TCP socket (`localabstract:...`), in which case
+    /// callers go straight to the adb executable.
+    pub fn client(&self) -> Option<AdbClient> {
+        self.client_with_env(inherited_socket().as_deref())
+    }
+
+    /// Global arguments for a spawned adb process, placed before the command.
+    pub fn global_args(&self) -> Vec<String> {
+        self.global_args_with_env(inherited_socket().as_deref())
+    }
+
+    /// Environment for a spawned adb process (in addition to the inherited one).
+    pub fn envs(&self) -> Vec<(String, String)> {
+        self.envs_with_env(inherited_socket().as_deref())
+    }
+
+    /// `client()` with the inherited `ADB_SERVER_SOCKET` passed in explicitly.
+    fn client_with_env(&self, inherited_socket: Option<&str>) -> Option<AdbClient> {
+        let socket = self
+            .server_socket
+            .as_deref()
+            .or(inherited_socket.filter(|_| !self.has_host_port()));
+        if let Some(socket) = socket {
+            let (host, port) = parse_tcp_socket(socket)?;
+            return Some(AdbClient::new(&host, port));
+        }
+        Some(AdbClient::new(
+            self.server_host.as_deref().unwrap_or(DEFAULT_HOST),
+            self.server_port.unwrap_or(DEFAULT_PORT),
+        ))
+    }
+
+    fn global_args_with_env(&self, inherited_socket: Option<&str>) -> Vec<String> {
+        if self.socket_for_process(inherited_socket).is_some() {
+            return Vec::new();
+        }
+        let mut args = Vec::new();
+        if let Some(host) = &self.server_host {
+            args.push("-H".to_string());
+            args.push(host.clone());
+        }
+        if let Some(port) = self.server_port {
+            args.push("-P".to_string());
+            args.push(port.to_string());
+        }
+        args
+    }
+
+    fn envs_with_env(&self, inherited_socket: Option<&str>) -> Vec<(String, String)> {
+        self.socket_for_process(inherited_socket)
+            .map(|socket| (SERVER_SOCKET_ENV.to_string(), socket))
+            .into_iter()
+            .collect()
+    }
+
+    fn has_host_port(&self) -> bool {
+        self.server_host.is_some() || self.server_port.is_some()
+    }
+
+    /// `ADB_SERVER_SOCKET` to set for a spawned adb. adb refuses `-H/-P`
+    /// next to an inherited socket, so a configured host/port is passed as a
+    /// socket that replaces it.
+    fn socket_for_process(&self, inherited_socket: Option<&str>) -> Option<String> {
+        if let Some(socket) = &self.server_socket {
+            return Some(socket.clone());
+        }
+        if inherited_socket.is_none() || !self.has_host_port() {
+            return None;
+        }
+        let host = self.server_host.as_deref().unwrap_or(DEFAULT_HOST);
+        let port = self.server_port.unwrap_or(DEFAULT_PORT);
+        Some(if host.contains(':') {
+            format!("tcp:[{}]:{}", host, port)
+        } else {
+            format!("tcp:{}:{}", host, port)
+        })
+    }
+}
+
+/// `ADB_SERVER_SOCKET` from our own environment, if set and non-empty.
+fn inherited_socket() -> Option<String> {
+    std::env::var(SERVER_SOCKET_ENV)
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
+/// Parse `tcp:PORT` / `tcp:HOST:PORT` / `tcp:[V6]:PORT` into host and port.
+pub fn parse_tcp_socket(spec: &str) -> Option<(String, u16)> {
+    let rest = spec.strip_prefix("tcp:")?;
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) => (host.trim_start_matches('[').trim_end_matches(']'), port),
+        None => (DEFAULT_HOST, rest),
+    };
+    let port: u16 = port.parse().ok().filter(|p| *p != 0)?;
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port))
+}
+
+// ---------------------------------------------------------------------------
+// Public API
+// ---------------------------------------------------------------------------
+
+pub struct SettingsState {
+    pub adb: Mutex<AdbSettings>,
+}
+
+impl SettingsState {
+    /// Create state, pre-loading saved settings from disk.
+    pub fn new() -> Self {
+        Self {
+            adb: Mutex::new(storage::load_or_default(SETTINGS_FILE)),
+        }
+    }
+}
+
+impl Default for SettingsState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Saved settings, for callers without an `AppHandle` (the CLI).
+pub fn load() -> AdbSettings {
+    storage::load_or_default(SETTINGS_FILE)
+}
+
+/// Settings in effect for `app` (defaults when the state is not managed).
+pub fn current(app: &AppHandle) -> AdbSettings {
+    app.try_state::<SettingsState>()
+        .and_then(|state| state.adb.lock().ok().map(|s| s.clone()))
+        .unwrap_or_default()
+}
+
+/// Validate, persist and apply new settings.
+pub fn update(state: &SettingsState, settings: AdbSettings) -> Result<(), AdbError> {
+    settings.validate()?;
+    let mut current = state
+        .adb
+        .lock()
+        .map_err(|_| AdbError::InvalidState("设置被锁定".to_string()))?;
+    storage::write(SETTINGS_FILE, &settings)?;
+    *current = settings;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tcp_socket() {
+        assert_eq!(
+            parse_tcp_socket("tcp:5038"),
+            Some(("127.0.0.1".to_string(), 5038))
+        );
+        assert_eq!(
+            parse_tcp_socket("tcp:192.168.1.20:5037"),
+            Some(("192.168.1.20".to_string(), 5037))
+        );
+        assert_eq!(
+            parse_tcp_socket("tcp:[::1]:5037"),
+            Some(("::1".to_string(), 5037))
+        );
+        assert_eq!(parse_tcp_socket("tcp:host:notaport"), None);
+        assert_eq!(parse_tcp_socket("localabstract:adb"), None);
+    }
+
+    #[test]
+    fn test_client_resolution() {
+        let defaults = AdbSettings::default();
+        let client = defaults.client_with_env(None).unwrap();
+        assert_eq!(
+            (client.host.as_str(), client.port),
+            (DEFAULT_HOST, DEFAULT_PORT)
+        );
+
+        let remote = AdbSettings {
+            server_host: Some("10.0.0.2".to_string()),
+            server_port: Some(5040),
+            ..Default::default()
+        };
+        let client = remote.client_with_env(None).unwrap();
+        assert_eq!((client.host.as_str(), client.port), ("10.0.0.2", 5040));
+        assert_eq!(
+            remote.global_args_with_env(None),
+            ["-H", "10.0.0.2", "-P", "5040"]
+        );
+        assert!(remote.envs_with_env(None).is_empty());
+
+        // Configured host/port beat an inherited socket on both paths
+        let inherited = Some("tcp:10.0.0.9:6000");
+        let client = remote.client_with_env(inherited).unwrap();
+        assert_eq!((client.host.as_str(), client.port), ("10.0.0.2", 5040));
+        assert!(remote.global_args_with_env(inherited).is_empty());
+        assert_eq!(
+            remote.envs_with_env(inherited),
+            [(
+                SERVER_SOCKET_ENV.to_string(),
+                "tcp:10.0.0.2:5040".to_string()
+            )]
+        );
+        let port_only = AdbSettings {
+            server_port: Some(5040),
+            ..Default::default()
+        };
+        assert_eq!(
+            port_only.envs_with_env(inherited),
+            [(
+                SERVER_SOCKET_ENV.to_string(),
+                "tcp:127.0.0.1:5040".to_string()
+            )]
+        );
+
+        // Nothing configured: the inherited socket applies
+        let client = defaults.client_with_env(inherited).unwrap();
+        assert_eq!((client.host.as_str(), client.port), ("10.0.0.9", 6000));
+        assert!(defaults.envs_with_env(inherited).is_empty());
+
+        let socket = AdbSettings {
+            server_host: Some("ignored".to_string()),
+            server_socket: Some("tcp:10.0.0.3:5037".to_string()),
+            ..Default::default()
+        };
+        let client = socket.client_with_env(Some("tcp:1")).unwrap();
+        assert_eq!(client.host, "10.0.0.3");
+        assert!(socket.global_args_with_env(None).is_empty());
+        assert_eq!(
+            socket.envs_with_env(None),
+            [(
+                SERVER_SOCKET_ENV.to_string(),
+                "tcp:10.0.0.3:5037".to_string()
+            )]
+        );
+
+        let abstract_socket = AdbSettings {
+            server_socket: Some("localabstract:adb".to_string()),
+            ..Default::default()
+        };
+        assert!(abstract_socket.client_with_env(None).is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(AdbSettings::default().validate().is_ok());
+        let missing = AdbSettings {
+            adb_path: Some("/definitely/not/adb".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            missing.validate(),
+            Err(AdbError::InvalidConfig(_))
+        ));
+        let bad_socket = AdbSettings {
+            server_socket: Some("tcp:host:".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_socket.validate().is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+use crate::adb_client::{self, AdbClient};
+use crate::error::AdbError;
+
+// ---------------------------------------------------------------------------
+// Shell protocol
+//
+// Devices advertising the `shell_v2` feature take `shell,v2,raw:<command>`
+// and answer with packets: a 1-byte id, a little-endian u32 length and the
+// data. stdout and stderr arrive as separate packets and the last one carries
+// the exit status. Older devices only have the merged `shell:` stream, so the
+// command is suffixed with `; echo` of a marker and `$?` instead.
+// ---------------------------------------------------------------------------
+
+pub const SHELL_V2_FEATURE: &str = "shell_v2";
+
+const ID_STDOUT: u8 = 1;
+const ID_STDERR: u8 = 2;
+const ID_EXIT: u8 = 3;
+
+/// Printed after the command on devices without shell v2; followed by `$?`.
+const EXIT_MARKER: &str = "__adbtool_exit:";
+
+/// Result of one shell command. A non-zero `exit_code` is not an `Err` by
+/// itself; use [`ShellOutput::check`] when the command is expected to succeed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShellOutput {
+    pub exit_code: i32,
+    pub stdout: String,
+    /// Empty on the fallback path, where stderr is merged into stdout.
+    pub stderr: String,
+    pub duration_ms: u64,
+}
+
+impl ShellOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Turn a non-zero exit status into an error classified from what the
+    /// command printed (stderr, or stdout when stderr is empty).
+    pub fn check(self) -> Result<Self, AdbError> {
+        if self.success() {
+            return Ok(self);
+        }
+        let message = if self.stderr.trim().is_empty() {
+            self.stdout.trim()
+        } else {
+            self.stderr.trim()
+        };
+        if message.is_empty() {
+            return Err(AdbError::Command(format!("exit code {}", self.exit_code)));
+        }
+        Err(AdbError::from_adb_message(message))
+    }
+}
+
+/// Run `command` on the device with shell v2 when supported, otherwise over
+/// `shell:` with the exit-status sentinel.
+pub async fn run(client: &AdbClient, serial: &str, command: &str) -> Result<ShellOutput, AdbError> {
+    let started = Instant::now();
+    let features = client.features(serial).await?;
+    let mut output = if features.split(',').any(|f| f.trim() == SHELL_V2_FEATURE) {
+        run_v2(client, serial, command).await?
+    } else {
+        let merged = client.shell(serial, &sentinel_command(command)).await?;
+        let (exit_code, stdout) = parse_sentinel(&String::from_utf8_lossy(&merged));
+        ShellOutput {
+            // No marker means the shell never got to run it (e.g. `exec`)
+            exit_code: exit_code.unwrap_or(-1),
+            stdout,
+            ..ShellOutput::default()
+        }
+    };
+    output.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(output)
+}
+
+async fn run_v2(client: &AdbClient, serial: &str, command: &str) -> Result<ShellOutput, AdbError> {
+    let mut stream = client.transport(serial).await?;
+    adb_client::send_request(&mut stream, &format!("shell,v2,raw:{}", command)).await?;
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    loop {
+        let Some((id, data)) = read_packet(&mut stream).await? else {
+            return Err(AdbError::Protocol(
+                "shell stream closed without exit status".to_string(),
+            ));
+        };
+        match id {
+            ID_STDOUT => stdout.extend_from_slice(&data),
+            ID_STDERR => stderr.extend_from_slice(&data),
+            ID_EXIT => {
+                return Ok(ShellOutput {
+                    exit_code: data.first().copied().unwrap_or(0) as i32,
+                    stdout: String::from_utf8_lossy(&stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&stderr).to_string(),
+                    duration_ms: 0,
+                })
+            }
+            // stdin / close-stdin / window-size are client → device only
+            _ => {}
+        }
+    }
+}
+
+/// Read one shell v2 packet; `None` on a clean EOF between packets.
+async fn read_packet(stream: &mut TcpStream) -> Result<Option<(u8, Vec<u8>)>, AdbError> {
+    let mut header = [0u8; 5];
+    match stream.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(AdbError::Protocol(e.to_string())),
+    }
+    let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    let mut data = vec![0u8; len];
+    stream
+        .read_exact(&mut data)
+        .await
+        .map_err(|e| AdbError::Protocol(e.to_string()))?;
+    Ok(Some((header[0], data)))
+}
+
+// ---------------------------------------------------------------------------
+// Exit-status sentinel for devices without shell v2
+// ---------------------------------------------------------------------------
+
+/// Single-quote `arg` for the device shell.
+pub fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// `command` followed by a line holding the marker and its exit status.
+pub fn sentinel_command(command: &str) -> String {
+    format!("{}; echo \"\n{}$?\"", command, EXIT_MARKER)
+}
+
+/// Split sentinel output into the exit status and the command's own output.
+/// Returns `None` for the status when the marker is missing.
+pub fn parse_sentinel(output: &str) -> (Option<i32>, String) {
+    let Some(pos) = output.rfind(EXIT_MARKER) else {
+        return (None, output.to_string());
+    };
+    let digits: String = output[pos + EXIT_MARKER.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let code = digits.parse::<i32>().ok();
+    let before = &output[..pos];
+    // Drop the newline the sentinel itself printed (CRLF under a PTY)
+    let before = before
+        .strip_suffix("\r\n")
+        .or_else(|| before.strip_suffix('\n'))
+        .unwrap_or(before);
+    (code, before.to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sentinel() {
+        let (code, out) = parse_sentinel("hello\n\n__adbtool_exit:0\n");
+        assert_eq!((code, out.as_str()), (Some(0), "hello\n"));
+
+        // No trailing newline from the command, PTY line endings
+        let (code, out) = parse_sentinel("no newline\r\n__adbtool_exit:1\r\n");
+        assert_eq!((code, out.as_str()), (Some(1), "no newline"));
+
+        let (code, out) = parse_sentinel("\n__adbtool_exit:127\n");
+        assert_eq!((code, out.as_str()), (Some(127), ""));
+
+        let (code, out) = parse_sentinel("killed before the marker\n");
+        assert_eq!(code, None);
+        assert_eq!(out, "killed before the marker\n");
+    }
+
+    #[test]
+    fn test_sentinel_round_trip_shape() {
+        let command = sentinel_command("rm /sdcard/x");
+        assert!(command.starts_with("rm /sdcard/x; echo \""));
+        assert!(command.ends_with("__adbtool_exit:$?\""));
+    }
+
+    #[test]
+    fn test_check_classifies_failure() {
+        let ok = ShellOutput {
+            stdout: "done\n".to_string(),
+            ..Default::default()
+        };
+        assert!(ok.check().is_ok());
+
+        let missing = ShellOutput {
+            exit_code: 1,
+            stderr: "rm: /sdcard/x: No such file or directory\n".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(missing.check(), Err(AdbError::NoSuchPath(_))));
+
+        let silent = ShellOutput {
+            exit_code: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            silent.check().unwrap_err(),
+            AdbError::Command("exit code 3".to_string())
+        );
+    }
+}
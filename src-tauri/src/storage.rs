@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::AdbError;
+
+// ---------------------------------------------------------------------------
+// JSON files under ~/AdbTool
+//
+// Settings, the op history, device labels, presets and snapshots are each a
+// pretty-printed JSON file in one app directory. `name` is relative to it
+// and may contain a subdirectory (`props/<snapshot>.json`).
+// ---------------------------------------------------------------------------
+
+/// Returns `~/AdbTool`.
+pub fn app_dir() -> PathBuf {
+    let home = dirs::home_dir().expect("cannot resolve home directory");
+    home.join("AdbTool")
+}
+
+/// Returns `~/AdbTool/<name>`.
+pub fn path(name: &str) -> PathBuf {
+    app_dir().join(name)
+}
+
+/// Read and parse `name`; `Ok(None)` when the file is missing or empty.
+pub fn read<T: DeserializeOwned>(name: &str) -> Result<Option<T>, AdbError> {
+    let path = path(name);
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AdbError::Io(format!("read {}: {e}", path.display()))),
+    };
+    if data.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&data)
+        .map(Some)
+        .map_err(|e| AdbError::InvalidConfig(format!("{}: {e}", path.display())))
+}
+
+/// Missing, unreadable or corrupt files fall back to the default.
+pub fn load_or_default<T: DeserializeOwned + Default>(name: &str) -> T {
+    read(name).ok().flatten().unwrap_or_default()
+}
+
+/// Write `value` to `name`, creating the directory as needed. The JSON goes
+/// to a temporary file first and is renamed over the old one, so a crash
+/// mid-write never leaves a truncated file behind.
+pub fn write<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<(), AdbError> {
+    let path = path(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AdbError::Io(format!("create {}: {e}", parent.display())))?;
+    }
+    let json =
+        serde_json::to_string_pretty(value).map_err(|e| AdbError::Io(format!("serialize: {e}")))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| AdbError::Io(format!("write {}: {e}", tmp.display())))?;
+    fs::rename(&tmp, &path).map_err(|e| AdbError::Io(format!("write {}: {e}", path.display())))
+}
+
+/// Run `f` holding an exclusive lock on `<name>.lock`, so a read-modify-write
+/// of `name` from the GUI and the CLI cannot interleave. If the lock file
+/// cannot be opened or locked, `f` runs unlocked.
+pub fn locked<R>(name: &str, f: impl FnOnce() -> R) -> R {
+    let path = path(&format!("{name}.lock"));
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .and_then(|file| file.lock().map(|()| file));
+    if let Err(e) = &lock {
+        eprintln!("[storage] cannot lock {}: {e}", path.display());
+    }
+    // Released when `lock` is dropped
+    f()
+}
@@ -0,0 +1,654 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
+
+use crate::adb_client::{self, AdbClient};
+use crate::error::AdbError;
+
+// ---------------------------------------------------------------------------
+// SYNC protocol client
+//
+// After `host:transport:<serial>` + `sync:`, the connection switches to
+// binary messages: a 4-byte id followed by a little-endian u32 length or
+// value. File data is moved in DATA chunks of at most 64 KiB.
+// ---------------------------------------------------------------------------
+
+const SYNC_DATA_MAX: usize = 64 * 1024;
+/// Device feature for `LST2`/`STA2`, which report 64-bit sizes.
+pub const STAT_V2_FEATURE: &str = "stat_v2";
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+// ---------------------------------------------------------------------------
+// Structs
+// ---------------------------------------------------------------------------
+
+/// Result of a SYNC `STAT`/`LST2`/`STA2`. A missing path comes back with every
+/// field zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteStat {
+    pub path: String,
+    pub mode: u32,
+    pub size: u64,
+    pub mtime: u64,
+    pub exists: bool,
+    pub is_dir: bool,
+}
+
+/// One `DENT` entry returned by a SYNC `LIST`. `LIST` is v1 only, so `size`
+/// wraps at 4 GiB; [`SyncConnection::stat`] a file for its real size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub mode: u32,
+    pub size: u64,
+    pub mtime: u64,
+    pub is_dir: bool,
+}
+
+/// Payload for the `transfer-progress` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    pub serial: String,
+    pub direction: String, // "push" | "pull"
+    pub path: String,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub rate_bps: f64,
+}
+
+/// Returned to the caller once a push/pull completes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferResult {
+    pub transfer_id: String,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    /// Remote file metadata after the transfer; None when the sidecar fallback was used.
+    pub stat: Option<RemoteStat>,
+}
+
+// ---------------------------------------------------------------------------
+// Connection
+// ---------------------------------------------------------------------------
+
+pub struct SyncConnection {
+    stream: TcpStream,
+    stat_v2: bool,
+}
+
+impl SyncConnection {
+    /// Open a SYNC session on the given device.
+    pub async fn open(client: &AdbClient, serial: &str) -> Result<Self, AdbError> {
+        let features = client.features(serial).await?;
+        let stat_v2 = features.split(',').any(|f| f.trim() == STAT_V2_FEATURE);
+        let mut stream = client.transport(serial).await?;
+        adb_client::send_request(&mut stream, "sync:").await?;
+        Ok(Self { stream, stat_v2 })
+    }
+
+    /// Stat a remote path without following symlinks. Uses `LST2` when the
+    /// device supports it; the v1 `STAT` fallback (pre-Android 8 devices)
+    /// carries a 32-bit size, so files of 4 GiB or more are reported with
+    /// their size modulo 4 GiB there.
+    pub async fn stat(&mut self, path: &str) -> Result<RemoteStat, AdbError> {
+        if self.stat_v2 {
+            return self.stat2(b"LST2", path).await;
+        }
+        self.send_packet(b"STAT", path.as_bytes()).await?;
+        let id = self.read_id().await?;
+        if &id != b"STAT" {
+            return Err(self.unexpected(&id).await);
+        }
+        let mode = self.read_u32().await?;
+        let size = self.read_u32().await? as u64;
+        let mtime = self.read_u32().await? as u64;
+        Ok(RemoteStat {
+            path: path.to_string(),
+            mode,
+            size,
+            mtime,
+            exists: mode != 0,
+            is_dir: mode & S_IFMT == S_IFDIR,
+        })
+    }
+
+    /// Stat what a remote path points to, following symlinks (`/sdcard` is
+    /// one). Uses `STA2`; v1 has no such request, so a symlink is stat'ed
+    /// again with a trailing `/`, which resolves links to directories.
+    pub async fn stat_target(&mut self, path: &str) -> Result<RemoteStat, AdbError> {
+        if self.stat_v2 {
+            return self.stat2(b"STA2", path).await;
+        }
+        let stat = self.stat(path).await?;
+        if stat.mode & S_IFMT == S_IFLNK && !path.ends_with('/') {
+            let target = self.stat(&format!("{}/", path)).await?;
+            if target.exists {
+                return Ok(RemoteStat {
+                    path: path.to_string(),
+                    ..target
+                });
+            }
+        }
+        Ok(stat)
+    }
+
+    /// `LST2`/`STA2`: error (errno, 0 on success), dev, ino, mode, nlink,
+    /// uid, gid, size, atime, mtime, ctime.
+    async fn stat2(&mut self, request: &[u8; 4], path: &str) -> Result<RemoteStat, AdbError> {
+        self.send_packet(request, path.as_bytes()).await?;
+        let id = self.read_id().await?;
+        if &id != request {
+            return Err(self.unexpected(&id).await);
+        }
+        let reply = self.read_bytes(68).await?;
+        let u32_at =
+            |at: usize| u32::from_le_bytes(reply[at..at + 4].try_into().unwrap_or_default());
+        let u64_at =
+            |at: usize| u64::from_le_bytes(reply[at..at + 8].try_into().unwrap_or_default());
+        let (mode, size, mtime) = if u32_at(0) == 0 {
+            (u32_at(20), u64_at(36), u64_at(52))
+        } else {
+            (0, 0, 0)
+        };
+        Ok(RemoteStat {
+            path: path.to_string(),
+            mode,
+            size,
+            mtime,
+            exists: mode != 0,
+            is_dir: mode & S_IFMT == S_IFDIR,
+        })
+    }
+
+    /// `LIST` a remote directory (includes `.` and `..` like the device does).
+    pub async fn list(&mut self, path: &str) -> Result<Vec<RemoteEntry>, AdbError> {
+        self.send_packet(b"LIST", path.as_bytes()).await?;
+        let mut entries = Vec::new();
+        loop {
+            let id = self.read_id().await?;
+            match &id {
+                b"DENT" => {
+                    let mode = self.read_u32().await?;
+                    let size = self.read_u32().await? as u64;
+                    let mtime = self.read_u32().await? as u64;
+                    let name_len = self.read_u32().await? as usize;
+                    let name = self.read_bytes(name_len).await?;
+                    entries.push(RemoteEntry {
+                        name: String::from_utf8_lossy(&name).to_string(),
+                        mode,
+                        size,
+                        mtime,
+                        is_dir: mode & S_IFMT == S_IFDIR,
+                    });
+                }
+                b"DONE" => {
+                    // DONE carries the same four u32 fields as DENT, all zero
+                    self.read_bytes(16).await?;
+                    return Ok(entries);
+                }
+                _ => return Err(self.unexpected(&id).await),
+            }
+        }
+    }
+
+    /// `SEND` a local file to `remote_path`.
+    ///
+    /// `progress` is called with (bytes_done, bytes_total) after every chunk.
+    /// When `cancel` fires the transfer stops and the connection must be dropped.
+    pub async fn send(
+        &mut self,
+        local_path: &Path,
+        remote_path: &str,
+        progress: &mut (dyn FnMut(u64, u64) + Send),
+        cancel: &CancellationToken,
+    ) -> Result<u64, AdbError> {
+        let metadata = tokio::fs::metadata(local_path)
+            .await
+            .map_err(|e| AdbError::Io(e.to_string()))?;
+        let total = metadata.len();
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+
+        let mut file = tokio::fs::File::open(local_path)
+            .await
+            .map_err(|e| AdbError::Io(e.to_string()))?;
+
+        let header = format!("{},{}", remote_path, local_mode(&metadata));
+        self.send_packet(b"SEND", header.as_bytes()).await?;
+
+        let mut buf = vec![0u8; SYNC_DATA_MAX];
+        let mut done: u64 = 0;
+        loop {
+            if cancel.is_cancelled() {
+                return Err(AdbError::Cancelled);
+            }
+            let n = file
+                .read(&mut buf)
+                .await
+                .map_err(|e| AdbError::Io(e.to_string()))?;
+            if n == 0 {
+                break;
+            }
+            self.send_packet(b"DATA", &buf[..n]).await?;
+            done += n as u64;
+            progress(done, total);
+        }
+
+        self.write(b"DONE").await?;
+        self.write(&mtime.to_le_bytes()).await?;
+
+        let id = self.read_id().await?;
+        if &id != b"OKAY" {
+            return Err(self.unexpected(&id).await);
+        }
+        self.read_u32().await?;
+        Ok(done)
+    }
+
+    /// `RECV` `remote_path` into a local file. `total` is only used for progress.
+    /// On failure or cancellation the partial local file is removed.
+    pub async fn recv(
+        &mut self,
+        remote_path: &str,
+        local_path: &Path,
+        total: u64,
+        progress: &mut (dyn FnMut(u64, u64) + Send),
+        cancel: &CancellationToken,
+    ) -> Result<u64, AdbError> {
+        let result = self
+            .recv_inner(remote_path, local_path, total, progress, cancel)
+            .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(local_path).await;
+        }
+        result
+    }
+
+    async fn recv_inner(
+        &mut self,
+        remote_path: &str,
+        local_path: &Path,
+        total: u64,
+        progress: &mut (dyn FnMut(u64, u64) + Send),
+        cancel: &CancellationToken,
+    ) -> Result<u64, AdbError> {
+        self.send_packet(b"RECV", remote_path.as_bytes()).await?;
+
+        let mut file = tokio::fs::File::create(local_path)
+            .await
+            .map_err(|e| AdbError::Io(e.to_string()))?;
+
+        let mut done: u64 = 0;
+        loop {
+            if cancel.is_cancelled() {
+                return Err(AdbError::Cancelled);
+            }
+            let id = self.read_id().await?;
+            match &id {
+                b"DATA" => {
+                    let len = self.read_u32().await? as usize;
+                    let chunk = self.read_bytes(len).await?;
+                    file.write_all(&chunk)
+                        .await
+                        .map_err(|e| AdbError::Io(e.to_string()))?;
+                    done += len as u64;
+                    progress(done, total);
+                }
+                b"DONE" => {
+                    self.read_u32().await?;
+                    break;
+                }
+                _ => return Err(self.unexpected(&id).await),
+            }
+        }
+
+        file.flush()
+            .await
+            .map_err(|e| AdbError::Io(e.to_string()))?;
+        Ok(done)
+    }
+
+    /// End the session politely.
+    pub async fn quit(mut self) {
+        let _ = self.send_packet(b"QUIT", b"").await;
+    }
+
+    // -- framing ------------------------------------------------------------
+
+    async fn send_packet(&mut self, id: &[u8; 4], payload: &[u8]) -> Result<(), AdbError> {
+        let mut packet = Vec::with_capacity(8 + payload.len());
+        packet.extend_from_slice(id);
+        packet.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        packet.extend_from_slice(payload);
+        self.write(&packet).await
+    }
+
+    async fn write(&mut self, data: &[u8]) -> Result<(), AdbError> {
+        self.stream
+            .write_all(data)
+            .await
+            .map_err(|e| AdbError::Protocol(e.to_string()))
+    }
+
+    async fn read_id(&mut self) -> Result<[u8; 4], AdbError> {
+        let mut id = [0u8; 4];
+        self.stream
+            .read_exact(&mut id)
+            .await
+            .map_err(|e| AdbError::Protocol(e.to_string()))?;
+        Ok(id)
+    }
+
+    async fn read_u32(&mut self) -> Result<u32, AdbError> {
+        let mut buf = [0u8; 4];
+        self.stream
+            .read_exact(&mut buf)
+            .await
+            .map_err(|e| AdbError::Protocol(e.to_string()))?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    async fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, AdbError> {
+        let mut buf = vec![0u8; len];
+        self.stream
+            .read_exact(&mut buf)
+            .await
+            .map_err(|e| AdbError::Protocol(e.to_string()))?;
+        Ok(buf)
+    }
+
+    /// Turn an unexpected id into an error, reading the message if it is a FAIL.
+    async fn unexpected(&mut self, id: &[u8; 4]) -> AdbError {
+        if id == b"FAIL" {
+            if let Ok(len) = self.read_u32().await {
+                if let Ok(msg) = self.read_bytes(len as usize).await {
+                    return AdbError::from_adb_message(&String::from_utf8_lossy(&msg));
+                }
+            }
+        }
+        AdbError::Protocol(format!(
+            "unexpected sync response: {}",
+            String::from_utf8_lossy(id)
+        ))
+    }
+}
+
+/// File mode sent with SEND — keeps the executable bit on unix hosts.
+fn local_mode(metadata: &std::fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        S_IFREG | (metadata.permissions().mode() & 0o777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        S_IFREG | 0o644
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Fake server: answer the features query with `features`, then accept
+    /// the sync connection, answer the transport + sync: handshake and hand
+    /// the raw stream to `handler`.
+    async fn fake_sync_server<F, Fut>(features: &'static str, handler: F) -> AdbClient
+    where
+        F: FnOnce(TcpStream) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let req = adb_client::read_length_prefixed(&mut s).await.unwrap();
+            assert_eq!(req, "host-serial:SER1:features");
+            s.write_all(format!("OKAY{:04x}{}", features.len(), features).as_bytes())
+                .await
+                .unwrap();
+
+            let (mut s, _) = listener.accept().await.unwrap();
+            for expected in ["host:transport:SER1", "sync:"] {
+                let req = adb_client::read_length_prefixed(&mut s).await.unwrap();
+                assert_eq!(req, expected);
+                s.write_all(b"OKAY").await.unwrap();
+            }
+            handler(s).await;
+        });
+        AdbClient::new("127.0.0.1", port)
+    }
+
+    async fn read_packet(s: &mut TcpStream) -> ([u8; 4], Vec<u8>) {
+        let mut id = [0u8; 4];
+        s.read_exact(&mut id).await.unwrap();
+        let mut len = [0u8; 4];
+        s.read_exact(&mut len).await.unwrap();
+        let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+        s.read_exact(&mut data).await.unwrap();
+        (id, data)
+    }
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_stat() {
+        let client = fake_sync_server("", |mut s| async move {
+            let (id, path) = read_packet(&mut s).await;
+            assert_eq!(&id, b"STAT");
+            assert_eq!(path, b"/sdcard/a.txt");
+            s.write_all(b"STAT").await.unwrap();
+            s.write_all(&u32s(&[S_IFREG | 0o660, 1234, 1_700_000_000]))
+                .await
+                .unwrap();
+        })
+        .await;
+        let mut conn = SyncConnection::open(&client, "SER1").await.unwrap();
+        let stat = conn.stat("/sdcard/a.txt").await.unwrap();
+        assert!(stat.exists);
+        assert!(!stat.is_dir);
+        assert_eq!(stat.size, 1234);
+        assert_eq!(stat.mtime, 1_700_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_stat_v2_reports_64_bit_size() {
+        let size: u64 = 5 * 1024 * 1024 * 1024;
+        let client = fake_sync_server("shell_v2,stat_v2", move |mut s| async move {
+            let (id, path) = read_packet(&mut s).await;
+            assert_eq!(&id, b"LST2");
+            assert_eq!(path, b"/sdcard/big.img");
+            let mut reply = b"LST2".to_vec();
+            reply.extend(u32s(&[0]));
+            reply.extend([0u8; 16]); // dev, ino
+            reply.extend(u32s(&[S_IFREG | 0o660, 1, 0, 0]));
+            reply.extend(size.to_le_bytes());
+            reply.extend([0u8; 8]); // atime
+            reply.extend(1_700_000_000u64.to_le_bytes());
+            reply.extend([0u8; 8]); // ctime
+            s.write_all(&reply).await.unwrap();
+
+            let (id, _) = read_packet(&mut s).await;
+            assert_eq!(&id, b"LST2");
+            let mut missing = b"LST2".to_vec();
+            missing.extend(u32s(&[2])); // ENOENT
+            missing.extend([0u8; 64]);
+            s.write_all(&missing).await.unwrap();
+        })
+        .await;
+        let mut conn = SyncConnection::open(&client, "SER1").await.unwrap();
+        let stat = conn.stat("/sdcard/big.img").await.unwrap();
+        assert_eq!(stat.size, size);
+        assert_eq!(stat.mtime, 1_700_000_000);
+        assert!(stat.exists && !stat.is_dir);
+        assert!(!conn.stat("/sdcard/nope").await.unwrap().exists);
+    }
+
+    #[tokio::test]
+    async fn test_stat_target_follows_symlink_on_v1() {
+        let client = fake_sync_server("", |mut s| async move {
+            let (_, path) = read_packet(&mut s).await;
+            assert_eq!(path, b"/sdcard");
+            s.write_all(b"STAT").await.unwrap();
+            s.write_all(&u32s(&[S_IFLNK | 0o777, 21, 0])).await.unwrap();
+
+            let (id, path) = read_packet(&mut s).await;
+            assert_eq!(&id, b"STAT");
+            assert_eq!(path, b"/sdcard/");
+            s.write_all(b"STAT").await.unwrap();
+            s.write_all(&u32s(&[S_IFDIR | 0o771, 4096, 0]))
+                .await
+                .unwrap();
+        })
+        .await;
+        let mut conn = SyncConnection::open(&client, "SER1").await.unwrap();
+        let stat = conn.stat_target("/sdcard").await.unwrap();
+        assert!(stat.is_dir);
+        assert_eq!(stat.path, "/sdcard");
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let client = fake_sync_server("", |mut s| async move {
+            let (id, _) = read_packet(&mut s).await;
+            assert_eq!(&id, b"LIST");
+            for (name, mode) in [("DCIM", S_IFDIR | 0o771), ("a.txt", S_IFREG | 0o660)] {
+                s.write_all(b"DENT").await.unwrap();
+                s.write_all(&u32s(&[mode, 10, 0, name.len() as u32]))
+                    .await
+                    .unwrap();
+                s.write_all(name.as_bytes()).await.unwrap();
+            }
+            s.write_all(b"DONE").await.unwrap();
+            s.write_all(&u32s(&[0, 0, 0, 0])).await.unwrap();
+        })
+        .await;
+        let mut conn = SyncConnection::open(&client, "SER1").await.unwrap();
+        let entries = conn.list("/sdcard").await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[1].name, "a.txt");
+    }
+
+    #[tokio::test]
+    async fn test_send_reports_progress() {
+        let dir = std::env::temp_dir().join(format!("adb_sync_send_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let local = dir.join("payload.bin");
+        let payload = vec![7u8; SYNC_DATA_MAX + 100];
+        std::fs::write(&local, &payload).unwrap();
+
+        let expected_len = payload.len();
+        let client = fake_sync_server("", move |mut s| async move {
+            let (id, header) = read_packet(&mut s).await;
+            assert_eq!(&id, b"SEND");
+            assert!(String::from_utf8_lossy(&header).starts_with("/data/local/tmp/p.bin,"));
+            let mut received = 0;
+            loop {
+                let mut id = [0u8; 4];
+                s.read_exact(&mut id).await.unwrap();
+                let mut val = [0u8; 4];
+                s.read_exact(&mut val).await.unwrap();
+                if &id == b"DONE" {
+                    break;
+                }
+                let mut data = vec![0u8; u32::from_le_bytes(val) as usize];
+                s.read_exact(&mut data).await.unwrap();
+                received += data.len();
+            }
+            assert_eq!(received, expected_len);
+            s.write_all(b"OKAY").await.unwrap();
+            s.write_all(&u32s(&[0])).await.unwrap();
+        })
+        .await;
+
+        let mut conn = SyncConnection::open(&client, "SER1").await.unwrap();
+        let mut calls = Vec::new();
+        let cancel = CancellationToken::new();
+        let sent = conn
+            .send(
+                &local,
+                "/data/local/tmp/p.bin",
+                &mut |d, t| calls.push((d, t)),
+                &cancel,
+            )
+            .await
+            .unwrap();
+        assert_eq!(sent, payload.len() as u64);
+        assert!(calls.len() >= 2);
+        assert_eq!(calls.last().unwrap(), &(sent, sent));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_send_stops_when_cancelled() {
+        let dir = std::env::temp_dir().join(format!("adb_sync_cancel_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let local = dir.join("payload.bin");
+        std::fs::write(&local, vec![1u8; 1024]).unwrap();
+
+        let client = fake_sync_server("", |mut s| async move {
+            let (id, _) = read_packet(&mut s).await;
+            assert_eq!(&id, b"SEND");
+        })
+        .await;
+
+        let mut conn = SyncConnection::open(&client, "SER1").await.unwrap();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = conn
+            .send(&local, "/data/local/tmp/p.bin", &mut |_, _| {}, &cancel)
+            .await
+            .unwrap_err();
+        assert_eq!(err, AdbError::Cancelled);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_recv_fail_removes_partial_file() {
+        let dir = std::env::temp_dir().join(format!("adb_sync_recv_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let local = dir.join("out.bin");
+
+        let client = fake_sync_server("", |mut s| async move {
+            let (id, _) = read_packet(&mut s).await;
+            assert_eq!(&id, b"RECV");
+            s.write_all(b"DATA").await.unwrap();
+            s.write_all(&u32s(&[3])).await.unwrap();
+            s.write_all(b"abc").await.unwrap();
+            let msg = "open failed: Permission denied";
+            s.write_all(b"FAIL").await.unwrap();
+            s.write_all(&u32s(&[msg.len() as u32])).await.unwrap();
+            s.write_all(msg.as_bytes()).await.unwrap();
+        })
+        .await;
+
+        let mut conn = SyncConnection::open(&client, "SER1").await.unwrap();
+        let cancel = CancellationToken::new();
+        let err = conn
+            .recv("/data/x", &local, 10, &mut |_, _| {}, &cancel)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "PERMISSION_DENIED");
+        assert!(!local.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
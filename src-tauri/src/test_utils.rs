@@ -0,0 +1,16 @@
+use crate::adb::AdbDevice;
+
+// ---------------------------------------------------------------------------
+// Fixtures shared by unit tests
+// ---------------------------------------------------------------------------
+
+/// A device as `adb devices -l` would list it, without model or label.
+pub fn device(serial: &str, state: &str) -> AdbDevice {
+    AdbDevice {
+        serial: serial.to_string(),
+        state: state.to_string(),
+        model: String::new(),
+        product: String::new(),
+        label: None,
+    }
+}
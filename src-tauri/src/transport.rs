@@ -0,0 +1,926 @@
+use std::future::Future;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use tauri::async_runtime::Receiver;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::process::ChildStdout;
+use tokio_util::sync::CancellationToken;
+
+use crate::adb_client::{AdbClient, FeatureCache};
+use crate::error::AdbError;
+use crate::operation::{self, OpClass, OperationState, Timeouts};
+use crate::settings::{self, AdbSettings};
+use crate::shell::{self, ShellOutput};
+use crate::sync::SyncConnection;
+
+// ---------------------------------------------------------------------------
+// Transport abstraction
+//
+// Everything in `adb.rs` that runs a command and parses its output goes
+// through `AdbTransport`, so the parsers can be driven by `MockTransport`
+// transcripts in tests instead of a real device and `AppHandle`.
+// ---------------------------------------------------------------------------
+
+/// Collected output of one adb invocation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawOutput {
+    /// Process exit status (-1 when it was killed by a signal).
+    pub exit_code: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl RawOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+impl From<ShellOutput> for RawOutput {
+    fn from(output: ShellOutput) -> Self {
+        Self {
+            exit_code: output.exit_code,
+            stdout: output.stdout.into_bytes(),
+            stderr: output.stderr.into_bytes(),
+        }
+    }
+}
+
+pub trait AdbTransport: Send + Sync {
+    /// Run `adb <args>` and collect both streams plus the exit status.
+    fn run(
+        &self,
+        args: &[&str],
+        class: OpClass,
+    ) -> impl Future<Output = Result<RawOutput, AdbError>> + Send;
+
+    /// Run `command` on the device and report its real exit status. A
+    /// non-zero exit is still `Ok`; see [`ShellOutput::check`].
+    fn shell(
+        &self,
+        serial: &str,
+        command: &str,
+        class: OpClass,
+    ) -> impl Future<Output = Result<ShellOutput, AdbError>> + Send;
+
+    /// Run `command` via `exec:` — no PTY, so binary output survives.
+    fn exec_out(
+        &self,
+        serial: &str,
+        command: &str,
+        class: OpClass,
+    ) -> impl Future<Output = Result<Vec<u8>, AdbError>> + Send;
+
+    /// Device listing in `adb devices -l` format (header optional).
+    fn devices(&self) -> impl Future<Output = Result<String, AdbError>> + Send;
+
+    /// Human-readable adb version string.
+    fn version(&self) -> impl Future<Output = Result<String, AdbError>> + Send;
+
+    /// Protocol client for what `run` cannot express (SYNC transfers,
+    /// streaming logcat); `None` when the server is only reachable through adb.
+    fn client(&self) -> Option<&AdbClient> {
+        None
+    }
+
+    /// Configured timeout for an operation class.
+    fn timeout(&self, class: OpClass) -> Duration {
+        Timeouts::default().for_class(class)
+    }
+
+    /// Start `adb <args>` and hand back its stdout as it is written, for
+    /// streams that outlive a single call. Only transports that spawn adb
+    /// support it.
+    fn spawn_lines(&self, args: &[&str]) -> Result<ProcessLines, AdbError> {
+        Err(AdbError::Command(format!(
+            "cannot spawn `adb {}` from this transport",
+            args.join(" ")
+        )))
+    }
+
+    /// Run `adb <args>` and return stdout.
+    /// Returns Err only if there is no stdout and the command failed with stderr.
+    fn exec(
+        &self,
+        args: &[&str],
+        class: OpClass,
+    ) -> impl Future<Output = Result<String, AdbError>> + Send {
+        async move {
+            let output = self.run(args, class).await?;
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+            // Return stdout even for non-zero exits (adb install returns 0 on failure)
+            // Only fail if there's no useful output at all and stderr has content
+            if stdout.trim().is_empty() && !stderr.trim().is_empty() && !output.success() {
+                return Err(AdbError::from_adb_message(&stderr));
+            }
+
+            Ok(stdout)
+        }
+    }
+
+    /// Run adb with `-s serial` prefix; a leading `shell` goes through
+    /// [`Self::shell`] and fails when the command exits non-zero.
+    fn exec_device(
+        &self,
+        serial: &str,
+        args: &[&str],
+        class: OpClass,
+    ) -> impl Future<Output = Result<String, AdbError>> + Send {
+        async move {
+            if let Some((&"shell", command)) = args.split_first() {
+                let output = self.shell(serial, &command.join(" "), class).await?;
+                return Ok(output.check()?.stdout);
+            }
+
+            let mut full_args: Vec<&str> = vec!["-s", serial];
+            full_args.extend_from_slice(args);
+            self.exec(&full_args, class).await
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Native transport: adb server protocol only, no adb binary needed
+// ---------------------------------------------------------------------------
+
+/// Speaks the host protocol to an adb server (real or fake) and never spawns
+/// a process. `run` understands the subset of command lines the adb module
+/// uses; anything else is reported as unsupported.
+#[derive(Debug, Clone)]
+pub struct NativeTransport {
+    pub client: AdbClient,
+    pub timeouts: Timeouts,
+}
+
+impl NativeTransport {
+    pub fn new(client: AdbClient) -> Self {
+        Self {
+            client,
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    async fn bounded<T>(
+        &self,
+        class: OpClass,
+        what: &str,
+        fut: impl Future<Output = Result<T, AdbError>>,
+    ) -> Result<T, AdbError> {
+        operation::with_timeout(self.timeouts.for_class(class), what, fut).await
+    }
+
+    /// `adb install`: push the APK to /data/local/tmp, `pm install` it, clean up.
+    async fn install(
+        &self,
+        serial: &str,
+        flags: &[&str],
+        apk: &str,
+    ) -> Result<RawOutput, AdbError> {
+        let name = Path::new(apk)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| AdbError::InvalidApk(apk.to_string()))?;
+        let remote = format!("/data/local/tmp/{}", name);
+        let quoted = shell::quote(&remote);
+
+        let mut conn = SyncConnection::open(&self.client, serial).await?;
+        conn.send(
+            Path::new(apk),
+            &remote,
+            &mut |_, _| {},
+            &CancellationToken::new(),
+        )
+        .await?;
+        conn.quit().await;
+
+        let mut command = vec!["pm", "install"];
+        command.extend_from_slice(flags);
+        command.push(&quoted);
+        let output = shell::run(&self.client, serial, &command.join(" ")).await;
+        let _ = shell::run(&self.client, serial, &format!("rm -f {}", quoted)).await;
+        Ok(output?.into())
+    }
+
+    /// [`AdbTransport::run`] for the command lines the protocol can express;
+    /// `None` for anything else (`start-server`, `pair`, directory pushes...).
+    async fn try_run(&self, args: &[&str], class: OpClass) -> Option<Result<RawOutput, AdbError>> {
+        let what = format!("adb {}", args.join(" "));
+        let client = &self.client;
+        let stdout = match args {
+            ["-s", serial, "install", rest @ ..] if !rest.is_empty() => {
+                let (apk, flags) = rest.split_last().unwrap_or((&"", &[]));
+                return Some(
+                    self.bounded(class, &what, self.install(serial, flags, apk))
+                        .await,
+                );
+            }
+            ["-s", serial, "uninstall", package] => {
+                let command = format!("pm uninstall {}", package);
+                return Some(self.shell(serial, &command, class).await.map(Into::into));
+            }
+            ["-s", serial, "shell", command @ ..] => {
+                return Some(
+                    self.shell(serial, &command.join(" "), class)
+                        .await
+                        .map(Into::into),
+                );
+            }
+            ["-s", serial, "exec-out", command @ ..] => {
+                self.exec_out(serial, &command.join(" "), class).await
+            }
+            ["-s", serial, "reboot"] => {
+                self.bounded(class, &what, client.device_service(serial, "reboot:"))
+                    .await
+            }
+            ["-s", serial, "reboot", target] => {
+                let service = format!("reboot:{}", target);
+                self.bounded(class, &what, client.device_service(serial, &service))
+                    .await
+            }
+            ["-s", serial, "tcpip", port] => {
+                let service = format!("tcpip:{}", port);
+                self.bounded(class, &what, client.device_service(serial, &service))
+                    .await
+            }
+            ["forward", "--list"] => self
+                .bounded(class, &what, client.host_query("host:list-forward"))
+                .await
+                .map(String::into_bytes),
+            ["-s", serial, direction @ ("forward" | "reverse"), rest @ ..] => {
+                let service = match rest {
+                    ["--list"] if *direction == "reverse" => "list-forward".to_string(),
+                    ["--remove-all"] if *direction == "reverse" => "killforward-all".to_string(),
+                    ["--remove", listener] => format!("killforward:{}", listener),
+                    [listener, target] if !listener.starts_with('-') => {
+                        format!("forward:{};{}", listener, target)
+                    }
+                    _ => return None,
+                };
+                let reply = if *direction == "forward" {
+                    self.bounded(class, &what, client.forward(serial, &service))
+                        .await
+                } else {
+                    self.bounded(class, &what, client.reverse(serial, &service))
+                        .await
+                };
+                // adb prints the allocated port of a `tcp:0` listener
+                reply.map(|port| {
+                    if port.is_empty() {
+                        Vec::new()
+                    } else {
+                        format!("{}\n", port.trim_end()).into_bytes()
+                    }
+                })
+            }
+            ["devices", ..] => self.devices().await.map(String::into_bytes),
+            ["version"] => self.version().await.map(String::into_bytes),
+            ["connect", address] | ["disconnect", address] => {
+                let service = format!("host:{}:{}", args[0], address);
+                self.bounded(class, &what, client.host_query(&service))
+                    .await
+                    .map(String::into_bytes)
+            }
+            ["mdns", "services"] => self
+                .bounded(class, &what, client.host_query("host:mdns:services"))
+                .await
+                .map(String::into_bytes),
+            ["kill-server"] => self
+                .bounded(class, &what, client.kill())
+                .await
+                .map(|()| Vec::new()),
+            _ => return None,
+        };
+        Some(stdout.map(|stdout| RawOutput {
+            exit_code: 0,
+            stdout,
+            stderr: Vec::new(),
+        }))
+    }
+}
+
+impl AdbTransport for NativeTransport {
+    async fn run(&self, args: &[&str], class: OpClass) -> Result<RawOutput, AdbError> {
+        self.try_run(args, class).await.unwrap_or_else(|| {
+            Err(AdbError::Command(format!(
+                "not supported over the native protocol: adb {}",
+                args.join(" ")
+            )))
+        })
+    }
+
+    async fn shell(
+        &self,
+        serial: &str,
+        command: &str,
+        class: OpClass,
+    ) -> Result<ShellOutput, AdbError> {
+        let what = format!("adb -s {} shell {}", serial, command);
+        self.bounded(class, &what, shell::run(&self.client, serial, command))
+            .await
+    }
+
+    async fn exec_out(
+        &self,
+        serial: &str,
+        command: &str,
+        class: OpClass,
+    ) -> Result<Vec<u8>, AdbError> {
+        let what = format!("adb -s {} exec-out {}", serial, command);
+        self.bounded(class, &what, self.client.exec_out(serial, command))
+            .await
+    }
+
+    async fn devices(&self) -> Result<String, AdbError> {
+        self.bounded(OpClass::Fast, "adb devices -l", self.client.devices_long())
+            .await
+    }
+
+    async fn version(&self) -> Result<String, AdbError> {
+        let version = self
+            .bounded(OpClass::Fast, "adb version", self.client.version())
+            .await?;
+        Ok(format!("Android Debug Bridge version 1.0.{}\n", version))
+    }
+
+    fn client(&self) -> Option<&AdbClient> {
+        Some(&self.client)
+    }
+
+    fn timeout(&self, class: OpClass) -> Duration {
+        self.timeouts.for_class(class)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Production transport: native first, adb process fallback
+// ---------------------------------------------------------------------------
+
+/// Talks to the configured adb server directly where possible and falls back
+/// to the configured adb executable (the bundled sidecar by default). Every
+/// call is bounded by the timeout of its class.
+#[derive(Clone)]
+pub struct AppTransport {
+    spawner: Spawner,
+    /// `None` when the server is only reachable through a non-TCP socket.
+    native: Option<NativeTransport>,
+    timeouts: Timeouts,
+}
+
+/// How the fallback adb process is started.
+#[derive(Clone)]
+enum Spawner {
+    /// Through the shell plugin, so the bundled sidecar can be used.
+    App(AppHandle),
+    /// As a plain child process, for callers without an `AppHandle`.
+    Process(AdbSettings),
+}
+
+impl AppTransport {
+    pub fn new(app: &AppHandle) -> Self {
+        let timeouts = app
+            .try_state::<OperationState>()
+            .and_then(|state| state.timeouts.lock().ok().map(|t| t.clone()))
+            .unwrap_or_default();
+        Self {
+            spawner: Spawner::App(app.clone()),
+            native: native_client(app)
+                .ok()
+                .map(|client| NativeTransport::new(client).with_timeouts(timeouts.clone())),
+            timeouts,
+        }
+    }
+
+    /// Transport for headless use (the CLI). The fallback runs
+    /// `settings.adb_path`, or `adb` from `PATH` since there is no sidecar.
+    pub fn headless(settings: &AdbSettings, timeouts: Timeouts) -> Self {
+        Self {
+            native: settings
+                .client()
+                .map(|client| NativeTransport::new(client).with_timeouts(timeouts.clone())),
+            spawner: Spawner::Process(settings.clone()),
+            timeouts,
+        }
+    }
+
+    /// Run the adb process, skipping the native client.
+    async fn run_adb(&self, args: &[&str], class: OpClass) -> Result<RawOutput, AdbError> {
+        match &self.spawner {
+            Spawner::App(app) => run_sidecar(app, args, class).await,
+            Spawner::Process(settings) => {
+                run_process(settings, args, self.timeouts.for_class(class)).await
+            }
+        }
+    }
+}
+
+impl AdbTransport for AppTransport {
+    /// Native where the protocol can express the command line; otherwise, or
+    /// when the server is unreachable, through the adb process.
+    async fn run(&self, args: &[&str], class: OpClass) -> Result<RawOutput, AdbError> {
+        if let Some(native) = &self.native {
+            match native.try_run(args, class).await {
+                None => {}
+                Some(Err(AdbError::ServerUnavailable(e))) => {
+                    eprintln!("[adb] native run failed, falling back to sidecar: {e}")
+                }
+                Some(result) => return result,
+            }
+        }
+        self.run_adb(args, class).await
+    }
+
+    async fn shell(
+        &self,
+        serial: &str,
+        command: &str,
+        class: OpClass,
+    ) -> Result<ShellOutput, AdbError> {
+        // Only an unreachable server falls back; a command or device error
+        // would come back the same from the sidecar
+        if let Some(native) = &self.native {
+            match native.shell(serial, command, class).await {
+                Err(AdbError::ServerUnavailable(e)) => {
+                    eprintln!("[adb] native shell failed, falling back to sidecar: {e}")
+                }
+                result => return result,
+            }
+        }
+
+        // The adb binary only forwards the exit status on shell v2 devices,
+        // so the sentinel is used either way
+        let started = Instant::now();
+        let sentinel = shell::sentinel_command(command);
+        let output = self
+            .run_adb(&["-s", serial, "shell", &sentinel], class)
+            .await?;
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let (exit_code, stdout) = shell::parse_sentinel(&String::from_utf8_lossy(&output.stdout));
+        let exit_code = match exit_code {
+            Some(code) => code,
+            // adb itself failed (no device, unauthorized, ...)
+            None if !output.success() => return Err(AdbError::from_adb_message(&stderr)),
+            None => -1,
+        };
+        Ok(ShellOutput {
+            exit_code,
+            stdout,
+            stderr,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    async fn exec_out(
+        &self,
+        serial: &str,
+        command: &str,
+        class: OpClass,
+    ) -> Result<Vec<u8>, AdbError> {
+        if let Some(native) = &self.native {
+            match native.exec_out(serial, command, class).await {
+                Err(AdbError::ServerUnavailable(_)) => {}
+                result => return result,
+            }
+        }
+
+        let mut args = vec!["-s", serial, "exec-out"];
+        args.extend(command.split_whitespace());
+        let output = self.run_adb(&args, class).await?;
+        if !output.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(AdbError::from_adb_message(&stderr));
+        }
+        Ok(output.stdout)
+    }
+
+    async fn devices(&self) -> Result<String, AdbError> {
+        self.exec(&["devices", "-l"], OpClass::Fast).await
+    }
+
+    /// Asks the running server first; the sidecar fallback also starts it if needed.
+    async fn version(&self) -> Result<String, AdbError> {
+        self.exec(&["version"], OpClass::Fast).await
+    }
+
+    fn client(&self) -> Option<&AdbClient> {
+        self.native.as_ref().map(|native| &native.client)
+    }
+
+    fn timeout(&self, class: OpClass) -> Duration {
+        self.timeouts.for_class(class)
+    }
+
+    fn spawn_lines(&self, args: &[&str]) -> Result<ProcessLines, AdbError> {
+        match &self.spawner {
+            Spawner::App(app) => {
+                let (rx, child) = adb_command(app)?
+                    .args(args)
+                    .spawn()
+                    .map_err(|e| AdbError::SpawnFailed(e.to_string()))?;
+                Ok(ProcessLines {
+                    pid: child.pid(),
+                    source: LineSource::Sidecar {
+                        rx,
+                        _child: KillOnDrop(Some(child)),
+                    },
+                })
+            }
+            Spawner::Process(settings) => {
+                let mut child = process_command(settings)
+                    .args(args)
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| AdbError::SpawnFailed(e.to_string()))?;
+                let stdout = child
+                    .stdout
+                    .take()
+                    .ok_or_else(|| AdbError::SpawnFailed("adb stdout unavailable".to_string()))?;
+                Ok(ProcessLines {
+                    pid: child.id().unwrap_or(0),
+                    source: LineSource::Process {
+                        lines: BufReader::new(stdout).lines(),
+                        _child: Box::new(child),
+                    },
+                })
+            }
+        }
+    }
+}
+
+/// Stdout of a running adb process, line by line. The process is killed
+/// when this is dropped.
+pub struct ProcessLines {
+    pid: u32,
+    source: LineSource,
+}
+
+/// The child handles are only held so dropping them ends the process.
+enum LineSource {
+    Sidecar {
+        rx: Receiver<CommandEvent>,
+        _child: KillOnDrop,
+    },
+    Process {
+        lines: Lines<BufReader<ChildStdout>>,
+        _child: Box<tokio::process::Child>,
+    },
+}
+
+impl ProcessLines {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Next stdout line without the trailing newline; `None` once the
+    /// process exits. stderr is dropped. Safe to use as a `select!` branch.
+    pub async fn next_line(&mut self) -> Result<Option<String>, AdbError> {
+        match &mut self.source {
+            LineSource::Sidecar { rx, .. } => {
+                while let Some(event) = rx.recv().await {
+                    match event {
+                        CommandEvent::Stdout(bytes) => {
+                            let line = String::from_utf8_lossy(&bytes);
+                            return Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()));
+                        }
+                        CommandEvent::Terminated(_) => break,
+                        CommandEvent::Error(err) => return Err(AdbError::Io(err)),
+                        _ => {}
+                    }
+                }
+                Ok(None)
+            }
+            LineSource::Process { lines, .. } => lines
+                .next_line()
+                .await
+                .map_err(|e| AdbError::Io(e.to_string())),
+        }
+    }
+}
+
+/// Configured timeout for an operation class.
+pub fn timeout_for(app: &AppHandle, class: OpClass) -> Duration {
+    app.try_state::<OperationState>()
+        .map(|state| state.timeout_for(class))
+        .unwrap_or_else(|| Timeouts::default().for_class(class))
+}
+
+/// Kills the sidecar child if the run is dropped before the process exits,
+/// which is what happens on timeout or cancellation.
+struct KillOnDrop(Option<CommandChild>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Some(child) = self.0.take() {
+            let _ = child.kill();
+        }
+    }
+}
+
+/// Native client for the configured server, for callers that need more than
+/// `AdbTransport` (SYNC, streaming logcat, device tracking). Shares the app's
+/// [`FeatureCache`].
+pub fn native_client(app: &AppHandle) -> Result<AdbClient, AdbError> {
+    let settings = settings::current(app);
+    let client = settings.client().ok_or_else(|| {
+        AdbError::ServerUnavailable(format!(
+            "{} is not a tcp: socket",
+            settings.server_socket.unwrap_or_default()
+        ))
+    })?;
+    Ok(match app.try_state::<FeatureCache>() {
+        Some(cache) => client.with_feature_cache(cache.inner().clone()),
+        None => client,
+    })
+}
+
+/// The configured adb executable (bundled sidecar unless a path is set), with
+/// the server selection applied. Callers append the command arguments.
+pub fn adb_command(app: &AppHandle) -> Result<Command, AdbError> {
+    let settings = settings::current(app);
+    let command = match &settings.adb_path {
+        Some(path) => app.shell().command(path),
+        None => app
+            .shell()
+            .sidecar("adb")
+            .map_err(|e| AdbError::SpawnFailed(e.to_string()))?,
+    };
+    Ok(command.args(settings.global_args()).envs(settings.envs()))
+}
+
+/// Spawn the configured adb and collect raw stdout/stderr until it exits.
+async fn run_sidecar(
+    app: &AppHandle,
+    args: &[&str],
+    class: OpClass,
+) -> Result<RawOutput, AdbError> {
+    let (mut rx, child) = adb_command(app)?
+        .args(args)
+        .set_raw_out(true)
+        .spawn()
+        .map_err(|e| AdbError::SpawnFailed(e.to_string()))?;
+    let mut guard = KillOnDrop(Some(child));
+
+    let collect = async {
+        let mut output = RawOutput::default();
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(bytes) => output.stdout.extend_from_slice(&bytes),
+                CommandEvent::Stderr(bytes) => output.stderr.extend_from_slice(&bytes),
+                CommandEvent::Terminated(status) => {
+                    output.exit_code = status.code.unwrap_or(-1);
+                    break;
+                }
+                CommandEvent::Error(err) => return Err(AdbError::SpawnFailed(err)),
+                _ => {}
+            }
+        }
+        Ok(output)
+    };
+
+    let what = format!("adb {}", args.join(" "));
+    let output = operation::with_timeout(timeout_for(app, class), &what, collect).await?;
+    // The process has exited; nothing left to kill
+    guard.0.take();
+    Ok(output)
+}
+
+/// adb as a plain child process: `settings.adb_path`, or `adb` from `PATH`,
+/// with the server selection applied. Killed if dropped before it exits.
+pub fn process_command(settings: &AdbSettings) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new(settings.adb_path.as_deref().unwrap_or("adb"));
+    command
+        .args(settings.global_args())
+        .envs(settings.envs())
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    command
+}
+
+/// Run adb as a child process and collect raw stdout/stderr until it exits.
+async fn run_process(
+    settings: &AdbSettings,
+    args: &[&str],
+    timeout: Duration,
+) -> Result<RawOutput, AdbError> {
+    let child = process_command(settings).args(args).output();
+    let what = format!("adb {}", args.join(" "));
+    let output = operation::with_timeout(timeout, &what, async {
+        child.await.map_err(|e| AdbError::SpawnFailed(e.to_string()))
+    })
+    .await?;
+    Ok(RawOutput {
+        exit_code: output.status.code().unwrap_or(-1),
+        stdout: output.stdout,
+        stderr: output.stderr,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Scripted transport for tests
+// ---------------------------------------------------------------------------
+
+/// Replays recorded `command → output` transcripts.
+///
+/// Commands are keyed by their adb command line without the leading `adb`,
+/// e.g. `-s SERIAL shell getprop ro.product.model` or `devices -l`. A
+/// transcript file looks like:
+///
+/// ```text
+/// # comment
+/// $ adb -s SERIAL shell getprop ro.build.version.sdk
+/// 34
+/// $ adb -s SERIAL uninstall com.example
+/// ! adb: failed to uninstall
+/// [exit 1]
+/// ```
+///
+/// Lines starting with `! ` go to stderr, `[exit N]` sets the exit status,
+/// `# ` lines are comments, everything else is stdout.
+#[cfg(test)]
+pub mod mock {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    pub struct MockTransport {
+        responses: HashMap<String, RawOutput>,
+        /// Every command line the code under test asked for, in order.
+        pub calls: Mutex<Vec<String>>,
+    }
+
+    impl MockTransport {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Build a mock from transcript text (see the module docs for the format).
+        pub fn from_transcript(transcript: &str) -> Self {
+            let mut mock = Self::new();
+            let mut current: Option<(String, RawOutput)> = None;
+
+            for line in transcript.lines() {
+                if let Some(command) = line.strip_prefix("$ adb ") {
+                    if let Some((key, output)) = current.take() {
+                        mock.responses.insert(key, output);
+                    }
+                    current = Some((command.trim().to_string(), RawOutput::default()));
+                } else if let Some((_, output)) = current.as_mut() {
+                    if let Some(err) = line.strip_prefix("! ") {
+                        output.stderr.extend_from_slice(err.as_bytes());
+                        output.stderr.push(b'\n');
+                    } else if let Some(code) = line
+                        .strip_prefix("[exit ")
+                        .and_then(|rest| rest.strip_suffix(']'))
+                    {
+                        output.exit_code = code.trim().parse().unwrap_or(1);
+                    } else if !line.starts_with("# ") {
+                        output.stdout.extend_from_slice(line.as_bytes());
+                        output.stdout.push(b'\n');
+                    }
+                }
+            }
+            if let Some((key, output)) = current.take() {
+                mock.responses.insert(key, output);
+            }
+            mock
+        }
+
+        /// Script the stdout of one command line.
+        pub fn with_output(mut self, command: &str, stdout: impl Into<Vec<u8>>) -> Self {
+            self.responses.insert(
+                command.to_string(),
+                RawOutput {
+                    exit_code: 0,
+                    stdout: stdout.into(),
+                    stderr: Vec::new(),
+                },
+            );
+            self
+        }
+
+        pub fn calls(&self) -> Vec<String> {
+            self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+        }
+
+        fn respond(&self, command: String) -> Result<RawOutput, AdbError> {
+            if let Ok(mut calls) = self.calls.lock() {
+                calls.push(command.clone());
+            }
+            self.responses
+                .get(&command)
+                .cloned()
+                .ok_or_else(|| AdbError::Command(format!("no transcript for `adb {}`", command)))
+        }
+    }
+
+    impl AdbTransport for MockTransport {
+        async fn run(&self, args: &[&str], _class: OpClass) -> Result<RawOutput, AdbError> {
+            self.respond(args.join(" "))
+        }
+
+        async fn shell(
+            &self,
+            serial: &str,
+            command: &str,
+            _class: OpClass,
+        ) -> Result<ShellOutput, AdbError> {
+            let output = self.respond(format!("-s {} shell {}", serial, command))?;
+            Ok(ShellOutput {
+                exit_code: output.exit_code,
+                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                duration_ms: 0,
+            })
+        }
+
+        async fn exec_out(
+            &self,
+            serial: &str,
+            command: &str,
+            _class: OpClass,
+        ) -> Result<Vec<u8>, AdbError> {
+            let output = self.respond(format!("-s {} exec-out {}", serial, command))?;
+            if !output.success() {
+                return Err(AdbError::from_adb_message(&String::from_utf8_lossy(
+                    &output.stderr,
+                )));
+            }
+            Ok(output.stdout)
+        }
+
+        async fn devices(&self) -> Result<String, AdbError> {
+            self.exec(&["devices", "-l"], OpClass::Fast).await
+        }
+
+        async fn version(&self) -> Result<String, AdbError> {
+            self.exec(&["version"], OpClass::Fast).await
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockTransport;
+    use super::*;
+
+    const TRANSCRIPT: &str = "\
+# sample
+$ adb -s SER shell getprop ro.build.version.sdk
+34
+$ adb -s SER uninstall com.example
+! adb: failed to uninstall com.example
+[exit 1]
+";
+
+    #[tokio::test]
+    async fn test_transcript_replay() {
+        let mock = MockTransport::from_transcript(TRANSCRIPT);
+        let sdk = mock
+            .exec_device(
+                "SER",
+                &["shell", "getprop", "ro.build.version.sdk"],
+                OpClass::Fast,
+            )
+            .await
+            .unwrap();
+        assert_eq!(sdk.trim(), "34");
+
+        let err = mock
+            .exec_device("SER", &["uninstall", "com.example"], OpClass::Normal)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "COMMAND_FAILED");
+        assert_eq!(
+            mock.calls(),
+            vec![
+                "-s SER shell getprop ro.build.version.sdk",
+                "-s SER uninstall com.example"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unscripted_command_fails() {
+        let mock = MockTransport::new().with_output("version", "Android Debug Bridge 1.0.41\n");
+        assert!(mock.version().await.unwrap().contains("1.0.41"));
+        assert!(mock.devices().await.is_err());
+    }
+}
@@ -0,0 +1,669 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::adb::{self, AdbDevice};
+use crate::device_info;
+use crate::error::AdbError;
+use crate::operation::OpClass;
+use crate::storage;
+use crate::transport::AdbTransport;
+
+// ---------------------------------------------------------------------------
+// Wireless debugging (Android 11+)
+//
+// Phones with wireless debugging on advertise `_adb-tls-connect._tcp` and,
+// while the "Pair device with pairing code" dialog is open,
+// `_adb-tls-pairing._tcp`. The adb server browses for both; `adb mdns
+// services` lists what it has seen. Pairing is done by the adb client itself,
+// so it always goes through the adb binary.
+// ---------------------------------------------------------------------------
+
+pub const PAIRING_SERVICE: &str = "_adb-tls-pairing._tcp";
+pub const CONNECT_SERVICE: &str = "_adb-tls-connect._tcp";
+/// Pre-11 `adb tcpip` devices that opted into mDNS.
+pub const LEGACY_SERVICE: &str = "_adb._tcp";
+
+/// One line of `adb mdns services`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MdnsService {
+    /// Instance name, `adb-<serial>-<suffix>` for phones.
+    pub name: String,
+    pub service_type: String,
+    /// `ip:port`
+    pub address: String,
+}
+
+/// Discovered services grouped by instance name, ready to pair or connect.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WirelessEndpoint {
+    pub name: String,
+    /// Set while the phone shows its pairing dialog.
+    pub pairing_address: Option<String>,
+    /// Set once wireless debugging is on; usable after pairing.
+    pub connect_address: Option<String>,
+}
+
+/// Parse `adb mdns services`. Older adb versions print the service type with
+/// a trailing dot and separate columns with spaces instead of tabs.
+pub fn parse_mdns_services(output: &str) -> Vec<MdnsService> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [name, service_type, address] = parts.as_slice() else {
+                return None;
+            };
+            let service_type = service_type.trim_end_matches('.');
+            if !service_type.starts_with("_adb") || !address.contains(':') {
+                return None;
+            }
+            Some(MdnsService {
+                name: name.to_string(),
+                service_type: service_type.to_string(),
+                address: address.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Group services by instance name, keeping discovery order.
+pub fn group_endpoints(services: &[MdnsService]) -> Vec<WirelessEndpoint> {
+    let mut endpoints: Vec<WirelessEndpoint> = Vec::new();
+    for service in services {
+        let index = match endpoints.iter().position(|e| e.name == service.name) {
+            Some(index) => index,
+            None => {
+                endpoints.push(WirelessEndpoint {
+                    name: service.name.clone(),
+                    pairing_address: None,
+                    connect_address: None,
+                });
+                endpoints.len() - 1
+            }
+        };
+        let endpoint = &mut endpoints[index];
+        match service.service_type.as_str() {
+            PAIRING_SERVICE => endpoint.pairing_address = Some(service.address.clone()),
+            CONNECT_SERVICE | LEGACY_SERVICE => {
+                endpoint.connect_address = Some(service.address.clone())
+            }
+            _ => {}
+        }
+    }
+    endpoints
+}
+
+/// Devices currently advertising wireless debugging or pairing.
+pub async fn discover(adb: &impl AdbTransport) -> Result<Vec<WirelessEndpoint>, AdbError> {
+    let output = adb.exec(&["mdns", "services"], OpClass::Fast).await?;
+    Ok(group_endpoints(&parse_mdns_services(&output)))
+}
+
+/// `adb pair <address> <code>` with the 6-digit code shown on the phone.
+/// adb exits 0 on a wrong code too, so the result is read from its output.
+pub async fn pair(adb: &impl AdbTransport, address: &str, code: &str) -> Result<String, AdbError> {
+    let code = code.trim();
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AdbError::InvalidConfig(format!(
+            "配对码格式不正确: {}",
+            code
+        )));
+    }
+    let output = adb.run(&["pair", address, code], OpClass::Normal).await?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stdout.contains("Successfully paired") {
+        return Ok(stdout);
+    }
+    let message = if stdout.is_empty() { stderr } else { stdout };
+    Err(AdbError::from_adb_message(&message))
+}
+
+// ---------------------------------------------------------------------------
+// QR code pairing
+//
+// The host picks a service name and password and shows them as a QR code.
+// After scanning, the phone advertises `_adb-tls-pairing._tcp` under that
+// name; the host then runs `adb pair` with the password, waits for the
+// phone's `_adb-tls-connect._tcp` service and connects to it.
+// ---------------------------------------------------------------------------
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the frontend needs to show the QR code and to finish the pairing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrPairing {
+    /// `studio-<random>`, the prefix Android Studio uses.
+    pub service_name: String,
+    pub password: String,
+    /// `WIFI:T:ADB;S:<name>;P:<password>;;`
+    pub payload: String,
+    /// The payload rendered as a standalone SVG document.
+    pub svg: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrPairingPhase {
+    /// QR code shown, phone has not scanned it yet.
+    Waiting,
+    /// Phone advertised the pairing service; pairing and then waiting for
+    /// its connect service.
+    Pairing,
+    /// Connect service found; `adb connect` is running.
+    Connecting,
+    /// Connected and listed as `device`.
+    Connected,
+}
+
+/// Payload for `qr-pairing-progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QrPairingProgress {
+    pub service_name: String,
+    pub phase: QrPairingPhase,
+    /// Pairing address while pairing, connect address afterwards.
+    pub address: Option<String>,
+}
+
+/// `count` random characters from `[a-zA-Z0-9]`, uniformly distributed.
+fn random_string(count: usize) -> Result<String, AdbError> {
+    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    // Largest multiple of 62 that fits in a byte; bytes at or above it
+    // would favor the first characters, so they are drawn again
+    const LIMIT: u8 = (256 / CHARSET.len() * CHARSET.len()) as u8;
+    let mut out = String::with_capacity(count);
+    let mut bytes = [0u8; 32];
+    while out.len() < count {
+        getrandom::fill(&mut bytes).map_err(|e| AdbError::Io(format!("random: {e}")))?;
+        out.extend(
+            bytes
+                .iter()
+                .filter(|b| **b < LIMIT)
+                .take(count - out.len())
+                .map(|b| CHARSET[*b as usize % CHARSET.len()] as char),
+        );
+    }
+    Ok(out)
+}
+
+pub fn qr_payload(service_name: &str, password: &str) -> String {
+    format!("WIFI:T:ADB;S:{};P:{};;", service_name, password)
+}
+
+/// New service name and password, with the QR code to show.
+pub fn new_qr_pairing() -> Result<QrPairing, AdbError> {
+    let service_name = format!("studio-{}", random_string(10)?);
+    let password = random_string(12)?;
+    let payload = qr_payload(&service_name, &password);
+    let code = QrCode::new(payload.as_bytes())
+        .map_err(|e| AdbError::InvalidState(format!("二维码生成失败: {e}")))?;
+    let svg = code.render::<svg::Color>().min_dimensions(240, 240).build();
+    Ok(QrPairing {
+        service_name,
+        password,
+        payload,
+        svg,
+    })
+}
+
+/// `[guid=adb-<serial>-<suffix>]` from `adb pair` output: the instance name
+/// the phone's connect service is advertised under.
+fn parse_pair_guid(output: &str) -> Option<String> {
+    let start = output.find("[guid=")? + "[guid=".len();
+    let end = output[start..].find(']')?;
+    Some(output[start..start + end].to_string())
+}
+
+fn host_of(address: &str) -> &str {
+    address.rsplit_once(':').map_or(address, |(host, _)| host)
+}
+
+/// `adb connect` output means success; adb exits 0 either way.
+fn is_connected(output: &str) -> bool {
+    output.contains("connected to") && !output.contains("failed") && !output.contains("cannot")
+}
+
+/// Poll mDNS until `pick` finds an endpoint, or fail at `deadline`.
+async fn wait_for_endpoint(
+    adb: &impl AdbTransport,
+    deadline: Instant,
+    what: &str,
+    pick: impl Fn(&WirelessEndpoint) -> Option<String>,
+) -> Result<String, AdbError> {
+    loop {
+        // The server may briefly fail to answer while mDNS restarts
+        if let Ok(endpoints) = discover(adb).await {
+            if let Some(found) = endpoints.iter().find_map(&pick) {
+                return Ok(found);
+            }
+        }
+        if Instant::now() + POLL_INTERVAL > deadline {
+            return Err(AdbError::Timeout(format!("等待{}超时", what)));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Wait for the phone to scan `service_name`'s QR code, pair with
+/// `password`, then connect. Returns the connected device.
+pub async fn complete_qr_pairing(
+    adb: &impl AdbTransport,
+    service_name: &str,
+    password: &str,
+    timeout: Duration,
+    mut on_phase: impl FnMut(QrPairingPhase, Option<&str>),
+) -> Result<AdbDevice, AdbError> {
+    let deadline = Instant::now() + timeout;
+
+    on_phase(QrPairingPhase::Waiting, None);
+    let pairing_address = wait_for_endpoint(adb, deadline, "手机扫码", |e| {
+        (e.name == service_name)
+            .then(|| e.pairing_address.clone())
+            .flatten()
+    })
+    .await?;
+
+    on_phase(QrPairingPhase::Pairing, Some(&pairing_address));
+    let output = pair(adb, &pairing_address, password).await?;
+
+    // Older adb versions print no guid; the phone uses the same IP for both
+    let guid = parse_pair_guid(&output);
+    let host = host_of(&pairing_address).to_string();
+    let connect_address = wait_for_endpoint(adb, deadline, "无线调试服务", |e| {
+        let address = e.connect_address.as_ref()?;
+        let matches = match &guid {
+            Some(guid) => &e.name == guid,
+            None => host_of(address) == host,
+        };
+        matches.then(|| address.clone())
+    })
+    .await?;
+
+    on_phase(QrPairingPhase::Connecting, Some(&connect_address));
+    let output = adb::connect_wifi(adb, &connect_address).await?;
+    if !is_connected(&output) {
+        return Err(AdbError::from_adb_message(&output));
+    }
+
+    // adb may already have connected on its own under the mDNS name
+    loop {
+        let devices = adb::list_devices(adb).await.unwrap_or_default();
+        let found = devices.into_iter().find(|d| {
+            d.state == "device"
+                && (d.serial == connect_address
+                    || guid
+                        .as_ref()
+                        .is_some_and(|g| d.serial.starts_with(g.as_str())))
+        });
+        if let Some(device) = found {
+            on_phase(QrPairingPhase::Connected, Some(&connect_address));
+            return Ok(device);
+        }
+        if Instant::now() + POLL_INTERVAL > deadline {
+            return Err(AdbError::Timeout(format!(
+                "已配对，但 {} 未上线",
+                connect_address
+            )));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// USB → Wi-Fi handoff
+//
+// For a cabled device: read its wlan0 address, `adb tcpip <port>`, connect
+// and wait for `ip:port` to be listed. adbd restarts on `tcpip`, so the
+// first connects usually fail and are retried until the deadline.
+// ---------------------------------------------------------------------------
+
+pub const DEFAULT_TCPIP_PORT: u16 = 5555;
+
+/// `ip` is missing on some pre-7.0 builds, `ifconfig` on some newer ones.
+const WIFI_PROBES: &[(&str, &str)] = &[
+    ("ip", "ip -f inet addr show wlan0"),
+    ("ifconfig", "ifconfig wlan0"),
+    ("dhcp", "getprop dhcp.wlan0.ipaddress"),
+];
+
+/// IPv4 address of the device's wlan0.
+pub async fn wifi_address(adb: &impl AdbTransport, serial: &str) -> Result<String, AdbError> {
+    let output = adb
+        .shell(
+            serial,
+            &device_info::probe_command(WIFI_PROBES),
+            OpClass::Fast,
+        )
+        .await?;
+    let probes = device_info::split_probes(&output.stdout);
+    let probe = |name: &str| probes.get(name).map(String::as_str).unwrap_or_default();
+    device_info::parse_inet_addr(probe("ip"))
+        .or_else(|| device_info::parse_inet_addr(probe("ifconfig")))
+        .or_else(|| Some(probe("dhcp").trim().to_string()).filter(|ip| !ip.is_empty()))
+        .ok_or_else(|| AdbError::InvalidState(format!("设备 {} 未连接 Wi-Fi", serial)))
+}
+
+/// Switch `serial` to TCP/IP on `port` and connect to it over Wi-Fi.
+/// Returns the new `ip:port` device.
+pub async fn handoff_to_wifi(
+    adb: &impl AdbTransport,
+    serial: &str,
+    port: u16,
+    timeout: Duration,
+) -> Result<AdbDevice, AdbError> {
+    let deadline = Instant::now() + timeout;
+    let address = format!("{}:{}", wifi_address(adb, serial).await?, port);
+    adb.exec_device(serial, &["tcpip", &port.to_string()], OpClass::Normal)
+        .await?;
+
+    let mut last = String::new();
+    loop {
+        match adb::connect_wifi(adb, &address).await {
+            Ok(output) if is_connected(&output) => {
+                let devices = adb::list_devices(adb).await.unwrap_or_default();
+                if let Some(device) = devices
+                    .into_iter()
+                    .find(|d| d.serial == address && d.state == "device")
+                {
+                    return Ok(device);
+                }
+            }
+            Ok(output) => last = output.trim().to_string(),
+            Err(e) => last = e.to_string(),
+        }
+        if Instant::now() + POLL_INTERVAL > deadline {
+            return Err(AdbError::Timeout(format!("无法连接 {}: {}", address, last)));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Saved endpoints
+// ---------------------------------------------------------------------------
+
+/// A Wi-Fi endpoint remembered from a handoff, reconnected on startup and
+/// after the adb server is restarted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedEndpoint {
+    /// `ip:port`
+    pub address: String,
+    /// USB serial the endpoint was handed off from.
+    #[serde(default)]
+    pub serial: String,
+    #[serde(default)]
+    pub model: String,
+    pub saved_at: String,
+    #[serde(default = "default_true")]
+    pub auto_reconnect: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Outcome of reconnecting one saved endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconnectResult {
+    pub address: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Managed state: saved endpoints in save order.
+pub struct WirelessState {
+    pub endpoints: Mutex<Vec<SavedEndpoint>>,
+}
+
+/// `~/AdbTool/wireless.json`; a missing or unreadable file means no
+/// endpoints.
+const ENDPOINTS_FILE: &str = "wireless.json";
+
+impl WirelessState {
+    /// Create state, pre-loading saved endpoints from disk.
+    pub fn new() -> Self {
+        Self {
+            endpoints: Mutex::new(storage::load_or_default(ENDPOINTS_FILE)),
+        }
+    }
+
+    pub fn list(&self) -> Vec<SavedEndpoint> {
+        self.endpoints.lock().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    /// Add `endpoint`, replacing any saved one with the same address.
+    pub fn save(&self, endpoint: SavedEndpoint) -> Result<(), AdbError> {
+        let mut endpoints = self
+            .endpoints
+            .lock()
+            .map_err(|e| AdbError::InvalidState(e.to_string()))?;
+        endpoints.retain(|e| e.address != endpoint.address);
+        endpoints.push(endpoint);
+        storage::write(ENDPOINTS_FILE, &*endpoints)
+    }
+
+    pub fn remove(&self, address: &str) -> Result<(), AdbError> {
+        let mut endpoints = self
+            .endpoints
+            .lock()
+            .map_err(|e| AdbError::InvalidState(e.to_string()))?;
+        endpoints.retain(|e| e.address != address);
+        storage::write(ENDPOINTS_FILE, &*endpoints)
+    }
+}
+
+impl Default for WirelessState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `adb connect` every auto-reconnect endpoint that is not already listed as
+/// a device.
+pub async fn reconnect_saved(
+    adb: &impl AdbTransport,
+    endpoints: &[SavedEndpoint],
+) -> Vec<ReconnectResult> {
+    let listed = adb::list_devices(adb).await.unwrap_or_default();
+    let mut results = Vec::new();
+    for endpoint in endpoints.iter().filter(|e| e.auto_reconnect) {
+        if listed
+            .iter()
+            .any(|d| d.serial == endpoint.address && d.state == "device")
+        {
+            continue;
+        }
+        let (success, message) = match adb::connect_wifi(adb, &endpoint.address).await {
+            Ok(output) => (is_connected(&output), output.trim().to_string()),
+            Err(e) => (false, e.to_string()),
+        };
+        results.push(ReconnectResult {
+            address: endpoint.address.clone(),
+            success,
+            message,
+        });
+    }
+    results
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_parse_mdns_services() {
+        let output = "List of discovered mdns services\n\
+                      adb-2B121FDH2004Z7-oTNuHf\t_adb-tls-connect._tcp\t192.168.1.42:37891\n\
+                      adb-2B121FDH2004Z7-oTNuHf\t_adb-tls-pairing._tcp\t192.168.1.42:42135\n\
+                      adb-R58M123ABC-x1Y2z3 _adb-tls-connect._tcp. 192.168.1.57:40109\n\
+                      ERROR: mdns discovery failed\n";
+        let services = parse_mdns_services(output);
+        assert_eq!(services.len(), 3);
+        assert_eq!(services[2].service_type, CONNECT_SERVICE);
+
+        let endpoints = group_endpoints(&services);
+        assert_eq!(
+            endpoints,
+            vec![
+                WirelessEndpoint {
+                    name: "adb-2B121FDH2004Z7-oTNuHf".to_string(),
+                    pairing_address: Some("192.168.1.42:42135".to_string()),
+                    connect_address: Some("192.168.1.42:37891".to_string()),
+                },
+                WirelessEndpoint {
+                    name: "adb-R58M123ABC-x1Y2z3".to_string(),
+                    pairing_address: None,
+                    connect_address: Some("192.168.1.57:40109".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pair_reads_output() {
+        let mock = MockTransport::from_transcript(
+            "$ adb pair 192.168.1.42:42135 123456\n\
+             Successfully paired to 192.168.1.42:42135 [guid=adb-2B121FDH2004Z7-oTNuHf]\n\
+             $ adb pair 192.168.1.42:42135 000000\n\
+             Failed: Wrong password or connection was dropped.\n",
+        );
+        assert!(pair(&mock, "192.168.1.42:42135", "123456").await.is_ok());
+        let err = pair(&mock, "192.168.1.42:42135", "000000")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Wrong password"));
+        assert!(matches!(
+            pair(&mock, "192.168.1.42:42135", "12 34").await,
+            Err(AdbError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_qr_pairing_payload() {
+        let pairing = new_qr_pairing().unwrap();
+        assert!(pairing.service_name.starts_with("studio-"));
+        assert_eq!(pairing.password.len(), 12);
+        let long = random_string(100).unwrap();
+        assert_eq!(long.len(), 100);
+        assert!(long.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(
+            pairing.payload,
+            format!(
+                "WIFI:T:ADB;S:{};P:{};;",
+                pairing.service_name, pairing.password
+            )
+        );
+        assert!(pairing.svg.contains("<svg"));
+        assert_eq!(
+            parse_pair_guid("Successfully paired to 10.0.0.5:41111 [guid=adb-R58M-abc]"),
+            Some("adb-R58M-abc".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_qr_pairing() {
+        let mock = MockTransport::from_transcript(
+            "$ adb mdns services\n\
+             List of discovered mdns services\n\
+             studio-k3J9xQ2mPa\t_adb-tls-pairing._tcp\t192.168.1.42:42135\n\
+             adb-2B121FDH2004Z7-oTNuHf\t_adb-tls-connect._tcp\t192.168.1.42:37891\n\
+             $ adb pair 192.168.1.42:42135 s3cretPassw0\n\
+             Successfully paired to 192.168.1.42:42135 [guid=adb-2B121FDH2004Z7-oTNuHf]\n\
+             $ adb connect 192.168.1.42:37891\n\
+             connected to 192.168.1.42:37891\n\
+             $ adb devices -l\n\
+             List of devices attached\n\
+             192.168.1.42:37891     device product:panther model:Pixel_7\n",
+        );
+        let mut phases = Vec::new();
+        let device = complete_qr_pairing(
+            &mock,
+            "studio-k3J9xQ2mPa",
+            "s3cretPassw0",
+            Duration::from_secs(5),
+            |phase, _| phases.push(phase),
+        )
+        .await
+        .unwrap();
+        assert_eq!(device.serial, "192.168.1.42:37891");
+        assert_eq!(
+            phases,
+            vec![
+                QrPairingPhase::Waiting,
+                QrPairingPhase::Pairing,
+                QrPairingPhase::Connecting,
+                QrPairingPhase::Connected,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wifi_address_fallbacks() {
+        let probe = device_info::probe_command(WIFI_PROBES);
+        let mock = MockTransport::new()
+            .with_output(
+                &format!("-s NEW shell {}", probe),
+                "@@ip\n\
+                 5: wlan0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500\n\
+                 \x20   inet 192.168.1.42/24 brd 192.168.1.255 scope global wlan0\n\
+                 @@ifconfig\n@@dhcp\n",
+            )
+            .with_output(
+                &format!("-s OLD shell {}", probe),
+                "@@ip\n@@ifconfig\n\
+                 wlan0     Link encap:Ethernet  HWaddr 02:00:00:00:00:00\n\
+                 \x20         inet addr:10.0.0.57  Bcast:10.0.0.255  Mask:255.255.255.0\n\
+                 @@dhcp\n",
+            )
+            .with_output(
+                &format!("-s CABLE shell {}", probe),
+                "@@ip\n@@ifconfig\n@@dhcp\n\n",
+            );
+        assert_eq!(wifi_address(&mock, "NEW").await.unwrap(), "192.168.1.42");
+        assert_eq!(wifi_address(&mock, "OLD").await.unwrap(), "10.0.0.57");
+        assert!(matches!(
+            wifi_address(&mock, "CABLE").await,
+            Err(AdbError::InvalidState(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_skips_listed_endpoints() {
+        let mock = MockTransport::from_transcript(
+            "$ adb devices -l\n\
+             List of devices attached\n\
+             192.168.1.42:5555      device product:panther model:Pixel_7\n\
+             $ adb connect 10.0.0.57:5555\n\
+             failed to connect to '10.0.0.57:5555': Connection refused\n",
+        );
+        let endpoint = |address: &str, auto_reconnect| SavedEndpoint {
+            address: address.to_string(),
+            serial: String::new(),
+            model: String::new(),
+            saved_at: String::new(),
+            auto_reconnect,
+        };
+        let results = reconnect_saved(
+            &mock,
+            &[
+                endpoint("192.168.1.42:5555", true),
+                endpoint("10.0.0.57:5555", true),
+                endpoint("10.0.0.99:5555", false),
+            ],
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].address, "10.0.0.57:5555");
+        assert!(!results[0].success);
+    }
+}
@@ -0,0 +1,390 @@
+//! End-to-end tests against the fake adb server: the real protocol client,
+//! SYNC transfers and logcat stream, with no phone attached.
+
+use std::path::PathBuf;
+
+use adb_tool_lib::adb;
+use adb_tool_lib::adb_client::{self, AdbClient};
+use adb_tool_lib::error::AdbError;
+use adb_tool_lib::forward::{self, ForwardDirection, PortRule};
+use adb_tool_lib::logcat::{self, LogcatState};
+use adb_tool_lib::operation::{OpClass, Timeouts};
+use adb_tool_lib::settings::AdbSettings;
+use adb_tool_lib::sync::TransferProgress;
+use adb_tool_lib::transport::{AdbTransport, AppTransport, NativeTransport};
+use fake_adb_server::{FakeAdbServer, FakeDevice};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+async fn start(devices: Vec<FakeDevice>) -> (FakeAdbServer, NativeTransport) {
+    let server = FakeAdbServer::start(devices)
+        .await
+        .expect("bind fake server");
+    let transport = NativeTransport::new(AdbClient::new("127.0.0.1", server.port()));
+    (server, transport)
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("adb_e2e_{}_{}", std::process::id(), name))
+}
+
+#[tokio::test]
+async fn test_list_devices_and_detail() {
+    let (_server, adb) = start(vec![
+        FakeDevice::new("PIXEL7"),
+        FakeDevice::new("LOCKED").with_state("unauthorized"),
+    ])
+    .await;
+
+    let devices = adb::list_devices(&adb).await.unwrap();
+    assert_eq!(devices.len(), 2);
+    assert_eq!(devices[0].model, "Pixel_7");
+    assert_eq!(devices[1].state, "unauthorized");
+
+    let detail = adb::get_device_detail(&adb, "PIXEL7").await.unwrap();
+    assert_eq!(detail.model, "Pixel 7");
+    assert_eq!(detail.sdk_version, "34");
+    assert_eq!(detail.storage_total_mb, 52_428_800 / 1024);
+    assert_eq!(detail.manufacturer, "Google");
+    assert_eq!(detail.abis, vec!["arm64-v8a".to_string()]);
+    assert_eq!(detail.screen_resolution, "1080x2400");
+    assert_eq!(detail.battery_level, Some(100));
+    assert_eq!(detail.ram_total_mb, 8045096 / 1024);
+    assert_eq!(detail.uptime_secs, 3600);
+    // `ip` and `which` are not emulated
+    assert_eq!((detail.wifi_ip, detail.rooted), (None, false));
+
+    let err = adb::list_packages(&adb, "LOCKED", false).await.unwrap_err();
+    assert!(matches!(err, AdbError::Unauthorized(_)));
+}
+
+#[tokio::test]
+async fn test_packages_and_uninstall() {
+    let (server, adb) = start(vec![FakeDevice::new("PIXEL7")]).await;
+
+    let apps = adb::list_packages(&adb, "PIXEL7", false).await.unwrap();
+    assert_eq!(apps.len(), 1);
+    assert_eq!(apps[0].package_name, "com.example.notes");
+    assert_eq!(apps[0].version_code, "123");
+
+    adb::uninstall_app(&adb, "PIXEL7", "com.example.notes")
+        .await
+        .unwrap();
+    assert!(server
+        .device("PIXEL7")
+        .unwrap()
+        .packages
+        .iter()
+        .all(|p| p.name != "com.example.notes"));
+    assert!(adb::uninstall_app(&adb, "PIXEL7", "com.example.notes")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_install_apk() {
+    let mut rejecting = FakeDevice::new("OLDPHONE");
+    rejecting.install_response = "Failure [INSTALL_FAILED_OLDER_SDK]".to_string();
+    let (server, adb) = start(vec![FakeDevice::new("PIXEL7"), rejecting]).await;
+
+    let apk = temp_path("app.apk");
+    std::fs::write(&apk, b"PK\x03\x04 not really an apk").unwrap();
+    let apk_path = apk.to_string_lossy().to_string();
+
+    let ok = adb::install_apk(&adb, "PIXEL7", &apk_path, &["-r"])
+        .await
+        .unwrap();
+    assert!(ok.success, "{}", ok.raw_output);
+    // The staged copy is removed after pm install
+    assert!(server
+        .device("PIXEL7")
+        .unwrap()
+        .files
+        .keys()
+        .all(|f| !f.starts_with("/data/local/tmp/")));
+
+    let failed = adb::install_apk(&adb, "OLDPHONE", &apk_path, &[])
+        .await
+        .unwrap();
+    assert!(!failed.success);
+    assert_eq!(
+        failed.error_code.as_deref(),
+        Some("INSTALL_FAILED_OLDER_SDK")
+    );
+    let _ = std::fs::remove_file(&apk);
+}
+
+#[tokio::test]
+async fn test_push_pull_round_trip() {
+    let (server, adb) = start(vec![FakeDevice::new("PIXEL7")]).await;
+    let local = temp_path("payload.bin");
+    let payload: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&local, &payload).unwrap();
+
+    let cancel = CancellationToken::new();
+    let mut reported = Vec::new();
+    let pushed = adb::push_file(
+        &adb,
+        "PIXEL7",
+        &local.to_string_lossy(),
+        "/sdcard/Download",
+        "t1",
+        &cancel,
+        |p: &TransferProgress| reported.push(p.bytes_done),
+    )
+    .await
+    .unwrap();
+    // An existing directory gets the local file name appended
+    let name = local.file_name().unwrap().to_string_lossy().to_string();
+    let remote = format!("/sdcard/Download/{}", name);
+    let stat = pushed.stat.unwrap();
+    assert_eq!(stat.size, payload.len() as u64);
+    assert_eq!(pushed.bytes, payload.len() as u64);
+    assert_eq!(reported.last(), Some(&(payload.len() as u64)));
+    assert_eq!(
+        server.device("PIXEL7").unwrap().files[&remote].data,
+        payload
+    );
+
+    let pulled = temp_path("pulled.bin");
+    let pulled_path = pulled.to_string_lossy().to_string();
+    let result = adb::pull_file(
+        &adb,
+        "PIXEL7",
+        &remote,
+        &pulled_path,
+        "t2",
+        &cancel,
+        |_: &TransferProgress| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.bytes, payload.len() as u64);
+    assert_eq!(std::fs::read(&pulled).unwrap(), payload);
+    assert!(!PathBuf::from(format!("{}.part", pulled_path)).exists());
+
+    let missing = adb::pull_file(
+        &adb,
+        "PIXEL7",
+        "/sdcard/Download/nope.bin",
+        &pulled_path,
+        "t3",
+        &cancel,
+        |_: &TransferProgress| {},
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(missing, AdbError::NoSuchPath(_)), "{missing:?}");
+    // A failed pull leaves the earlier download alone
+    assert_eq!(std::fs::read(&pulled).unwrap(), payload);
+
+    let listing = adb::list_files(&adb, "PIXEL7", "/sdcard/Download")
+        .await
+        .unwrap();
+    assert!(listing.iter().any(|l| l.ends_with(&name)));
+    assert!(adb::list_files(&adb, "PIXEL7", "/nope")
+        .await
+        .unwrap()
+        .is_empty());
+
+    let _ = std::fs::remove_file(&local);
+    let _ = std::fs::remove_file(&pulled);
+}
+
+#[tokio::test]
+async fn test_push_pull_through_symlinked_dir() {
+    let mut device = FakeDevice::new("PIXEL7");
+    device.link("/mnt/sdcard", "/sdcard");
+    let (server, adb) = start(vec![device]).await;
+    let local = temp_path("linked.txt");
+    std::fs::write(&local, b"via the link").unwrap();
+    let name = local.file_name().unwrap().to_string_lossy().to_string();
+
+    let cancel = CancellationToken::new();
+    adb::push_file(
+        &adb,
+        "PIXEL7",
+        &local.to_string_lossy(),
+        "/mnt/sdcard",
+        "t1",
+        &cancel,
+        |_: &TransferProgress| {},
+    )
+    .await
+    .unwrap();
+    // Lands inside the directory the link points to
+    let pushed = format!("/sdcard/{}", name);
+    assert_eq!(
+        server.device("PIXEL7").unwrap().files[&pushed].data,
+        b"via the link"
+    );
+
+    let pulled = temp_path("linked_pulled.txt");
+    let pulled_path = pulled.to_string_lossy().to_string();
+    adb::pull_file(
+        &adb,
+        "PIXEL7",
+        &format!("/mnt/sdcard/{}", name),
+        &pulled_path,
+        "t2",
+        &cancel,
+        |_: &TransferProgress| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(std::fs::read(&pulled).unwrap(), b"via the link");
+
+    // A linked directory is pulled by `adb pull`, which the native
+    // transport does not run, rather than RECV'd as one file
+    let dir = adb::pull_file(
+        &adb,
+        "PIXEL7",
+        "/mnt/sdcard",
+        &pulled_path,
+        "t3",
+        &cancel,
+        |_: &TransferProgress| {},
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(&dir, AdbError::Command(m) if m.contains("pull /mnt/sdcard")),
+        "{dir:?}"
+    );
+
+    let _ = std::fs::remove_file(&local);
+    let _ = std::fs::remove_file(&pulled);
+}
+
+#[tokio::test]
+async fn test_screenshot() {
+    let (_server, adb) = start(vec![FakeDevice::new("PIXEL7")]).await;
+    let local = temp_path("shot.png");
+    let local_path = local.to_string_lossy().to_string();
+    adb::screenshot(&adb, "PIXEL7", &local_path).await.unwrap();
+    assert!(std::fs::read(&local).unwrap().starts_with(b"\x89PNG"));
+    let _ = std::fs::remove_file(&local);
+}
+
+#[tokio::test]
+async fn test_logcat_stream() {
+    let mut device = FakeDevice::new("PIXEL7");
+    device
+        .logcat_line("--------- beginning of main")
+        .logcat_line("01-15 12:34:56.789  1234  5678 I ActivityManager: Start proc")
+        .logcat_line("01-15 12:34:56.790  1234  5679 E AndroidRuntime: FATAL EXCEPTION");
+    let (_server, adb) = start(vec![device]).await;
+
+    let state = LogcatState::new();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let pid = logcat::start_stream(&adb, &state, "PIXEL7", move |line| {
+        let _ = tx.send(line);
+    })
+    .await
+    .unwrap();
+    assert_eq!(pid, 0, "native stream");
+    assert!(logcat::start_stream(&adb, &state, "PIXEL7", |_| {})
+        .await
+        .is_err());
+
+    let mut lines = Vec::new();
+    for _ in 0..3 {
+        lines.push(rx.recv().await.unwrap());
+    }
+    // The buffer header is passed on raw
+    assert_eq!(lines[0].level, "");
+    assert_eq!(lines[0].raw, "--------- beginning of main");
+    assert_eq!(lines[2].level, "E");
+    assert_eq!(lines[2].tag, "AndroidRuntime");
+
+    logcat::stop_stream(&state, "PIXEL7").unwrap();
+    assert!(logcat::stop_stream(&state, "PIXEL7").is_err());
+    // The reader task drops the callback once it sees the cancellation
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_track_devices_sees_attach() {
+    let (server, adb) = start(vec![FakeDevice::new("PIXEL7")]).await;
+    let mut stream = adb.client.track_devices().await.unwrap();
+    let first = adb_client::read_length_prefixed(&mut stream).await.unwrap();
+    assert_eq!(adb::parse_devices_output(&first).len(), 1);
+
+    server.update(|devices| devices.push(FakeDevice::new("EMULATOR")));
+    let second = adb_client::read_length_prefixed(&mut stream).await.unwrap();
+    assert_eq!(adb::parse_devices_output(&second).len(), 2);
+}
+
+#[tokio::test]
+async fn test_shell_exit_status_v2_and_legacy() {
+    let (_server, adb) = start(vec![
+        FakeDevice::new("PIXEL7"),
+        FakeDevice::new("KITKAT").with_shell_v2(false),
+    ])
+    .await;
+
+    for serial in ["PIXEL7", "KITKAT"] {
+        let out = adb.shell(serial, "ls /nope", OpClass::Fast).await.unwrap();
+        assert_eq!(out.exit_code, 1, "{serial}");
+        let printed = format!("{}{}", out.stdout, out.stderr);
+        assert!(printed.contains("No such file or directory"), "{serial}");
+
+        let ok = adb.shell(serial, "echo hi", OpClass::Fast).await.unwrap();
+        assert_eq!((ok.exit_code, ok.stdout.as_str()), (0, "hi\n"), "{serial}");
+
+        adb::delete_file(&adb, serial, "/sdcard/Download/readme.txt")
+            .await
+            .unwrap();
+        let err = adb::delete_file(&adb, serial, "/sdcard/Download/readme.txt")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AdbError::NoSuchPath(_)), "{serial}: {err:?}");
+    }
+
+    // Only shell v2 keeps stderr apart
+    let out = adb
+        .shell("PIXEL7", "ls /nope", OpClass::Fast)
+        .await
+        .unwrap();
+    assert!(out.stdout.is_empty());
+    assert!(out.stderr.contains("/nope"));
+}
+
+#[tokio::test]
+async fn test_app_transport_runs_forwards_natively() {
+    let (server, _) = start(vec![FakeDevice::new("PIXEL7")]).await;
+    // Any fallback to the adb process would fail to spawn
+    let settings = AdbSettings {
+        adb_path: Some("/nonexistent/adb".to_string()),
+        server_host: Some("127.0.0.1".to_string()),
+        server_port: Some(server.port()),
+        server_socket: None,
+    };
+    let adb = AppTransport::headless(&settings, Timeouts::default());
+
+    let any_port = PortRule {
+        direction: ForwardDirection::Forward,
+        host: "tcp:0".to_string(),
+        device: "localabstract:chrome_devtools_remote".to_string(),
+    };
+    let port = forward::add_rule(&adb, "PIXEL7", &any_port).await.unwrap();
+    let metro = PortRule {
+        direction: ForwardDirection::Reverse,
+        host: "tcp:8081".to_string(),
+        device: "tcp:8081".to_string(),
+    };
+    forward::add_rule(&adb, "PIXEL7", &metro).await.unwrap();
+
+    let rules = forward::list_rules(&adb, "PIXEL7").await.unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].host, format!("tcp:{}", port));
+    assert_eq!(rules[1], metro);
+
+    assert_eq!(forward::remove_all(&adb, "PIXEL7", None).await.unwrap(), 2);
+    assert!(server.forwards().is_empty());
+    assert!(forward::remove_rule(&adb, "PIXEL7", &metro).await.is_err());
+    assert!(matches!(
+        adb.exec(&["start-server"], OpClass::Fast).await,
+        Err(AdbError::SpawnFailed(_))
+    ));
+}
@@ -5,6 +5,7 @@ import type {
   InstallResult,
   LogcatLine,
   OpLogEntry,
+  TransferResult,
 } from "../types";
 import type {
   Bridge,
@@ -118,7 +119,7 @@ export const electronBridge: Bridge = {
       serial,
       localPath,
       remotePath,
-    )) as string;
+    )) as TransferResult;
   },
 
   async pullFile(serial, remotePath, localPath) {
@@ -127,7 +128,7 @@ export const electronBridge: Bridge = {
       serial,
       remotePath,
       localPath,
-    )) as string;
+    )) as TransferResult;
   },
 
   async deleteRemoteFile(serial, remotePath) {
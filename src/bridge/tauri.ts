@@ -1,4 +1,4 @@
-import { invoke, convertFileSrc } from "@tauri-apps/api/core";
+import { invoke as tauriInvoke, convertFileSrc } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { open, save } from "@tauri-apps/plugin-dialog";
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
@@ -9,9 +9,35 @@ import type {
   InstallResult,
   LogcatLine,
   OpLogEntry,
+  TransferResult,
 } from "../types";
 import type { Bridge, OpenDialogOptions, SaveDialogOptions } from "./types";
 
+/** Structured error returned by the Rust commands. */
+interface AdbErrorPayload {
+  code: string;
+  message: string;
+  detail: string;
+}
+
+function isAdbError(err: unknown): err is AdbErrorPayload {
+  return typeof err === "object" && err !== null && "code" in err && "message" in err;
+}
+
+/** Rethrow backend errors as `Error` (keeping `code`) so `String(err)` stays readable. */
+async function invoke<T>(cmd: string, args?: Record<string, unknown>): Promise<T> {
+  try {
+    return await tauriInvoke<T>(cmd, args);
+  } catch (err) {
+    if (isAdbError(err)) {
+      const error = new Error(err.detail ? `${err.message}: ${err.detail}` : err.message);
+      error.name = "AdbError";
+      throw Object.assign(error, { code: err.code, detail: err.detail });
+    }
+    throw err;
+  }
+}
+
 export const tauriBridge: Bridge = {
   getDevices() {
     return invoke<AdbDevice[]>("get_devices");
@@ -66,11 +92,19 @@ export const tauriBridge: Bridge = {
   },
 
   pushFile(serial, localPath, remotePath) {
-    return invoke<string>("push_file", { serial, localPath, remotePath });
+    return invoke<TransferResult>("push_file", {
+      serial,
+      localPath,
+      remotePath,
+    });
   },
 
   pullFile(serial, remotePath, localPath) {
-    return invoke<string>("pull_file", { serial, remotePath, localPath });
+    return invoke<TransferResult>("pull_file", {
+      serial,
+      remotePath,
+      localPath,
+    });
   },
 
   deleteRemoteFile(serial, remotePath) {
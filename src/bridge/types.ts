@@ -5,6 +5,7 @@ import type {
   InstallResult,
   LogcatLine,
   OpLogEntry,
+  TransferResult,
 } from "../types";
 
 export type UnlistenFn = () => void;
@@ -70,12 +71,12 @@ export interface Bridge {
     serial: string,
     localPath: string,
     remotePath: string,
-  ): Promise<string>;
+  ): Promise<TransferResult>;
   pullFile(
     serial: string,
     remotePath: string,
     localPath: string,
-  ): Promise<string>;
+  ): Promise<TransferResult>;
   deleteRemoteFile(serial: string, remotePath: string): Promise<string>;
   takeScreenshot(serial: string, localPath: string): Promise<string>;
   startLogcat(serial: string): Promise<number>;
@@ -1,6 +1,7 @@
 import { useState, useEffect, useCallback } from "react";
 import { bridge } from "../bridge";
 import { useDevices } from "../hooks/useDevices";
+import type { TransferResult } from "../types";
 import "./FilesPage.css";
 
 interface FileEntry {
@@ -18,6 +19,14 @@ const QUICK_PATHS = [
   { label: "Documents", path: "/sdcard/Documents/" },
 ];
 
+/** e.g. "1.2 MB, 0.8 s" */
+function describeTransfer(result: TransferResult): string {
+  const mb = result.bytes / (1024 * 1024);
+  const size =
+    mb >= 1 ? `${mb.toFixed(1)} MB` : `${(result.bytes / 1024).toFixed(1)} KB`;
+  return `${size}, ${(result.duration_ms / 1000).toFixed(1)} s`;
+}
+
 function parseLsLine(line: string): FileEntry | null {
   const trimmed = line.trim();
   if (!trimmed || trimmed.startsWith("total")) return null;
@@ -183,8 +192,12 @@ function FilesPage() {
     try {
       const fileName =
         file.split("/").pop() || file.split("\\").pop() || "file";
-      await bridge().pushFile(selectedDevice, file, remotePath + fileName);
-      setStatus("上传成功: " + fileName);
+      const result = await bridge().pushFile(
+        selectedDevice,
+        file,
+        remotePath + fileName,
+      );
+      setStatus(`上传成功: ${fileName} (${describeTransfer(result)})`);
       loadFiles();
     } catch (err) {
       setStatus("上传失败: " + String(err));
@@ -198,8 +211,12 @@ function FilesPage() {
       if (!savePath) return;
       setStatus("正在下载...");
       try {
-        await bridge().pullFile(selectedDevice, remotePath + fileName, savePath);
-        setStatus("下载成功: " + savePath);
+        const result = await bridge().pullFile(
+          selectedDevice,
+          remotePath + fileName,
+          savePath,
+        );
+        setStatus(`下载成功: ${savePath} (${describeTransfer(result)})`);
       } catch (err) {
         setStatus("下载失败: " + String(err));
       }
@@ -23,6 +23,25 @@ export interface InstallResult {
   raw_output: string;
 }
 
+/** Remote file metadata from a SYNC `STAT`. */
+export interface RemoteStat {
+  path: string;
+  mode: number;
+  size: number;
+  mtime: number;
+  exists: boolean;
+  is_dir: boolean;
+}
+
+/** Returned once a push/pull completes. */
+export interface TransferResult {
+  transfer_id: string;
+  bytes: number;
+  duration_ms: number;
+  /** null when the adb binary fallback was used. */
+  stat: RemoteStat | null;
+}
+
 export interface InstalledApp {
   package_name: string;
   version_name: string;